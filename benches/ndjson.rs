@@ -0,0 +1,59 @@
+// Compares the single threaded NdjsonReader with parse_parallel.
+// Run with `cargo bench --bench ndjson`.
+use std::hint::black_box;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use json_parser_rs::json::ndjson::{parse_parallel, NdjsonOptions, NdjsonReader};
+
+const LINES: usize = 200_000;
+const ROUNDS: u32 = 5;
+
+fn input() -> Vec<u8> {
+    let mut input = String::new();
+    for index in 0..LINES {
+        input.push_str(&format!(
+            "{{\"id\": {index}, \"name\": \"user {index}\", \"active\": {}, \"scores\": [1.5, 2.25, {index}], \"address\": {{\"city\": \"Z\\u00fcrich\", \"zip\": \"80{index}\"}}}}\n",
+            index % 2 == 0
+        ));
+    }
+    input.into_bytes()
+}
+
+fn measure<F: FnMut() -> usize>(mut run: F) -> Duration {
+    let mut best = Duration::MAX;
+    for _ in 0..ROUNDS {
+        let start = Instant::now();
+        assert_eq!(black_box(run()), LINES);
+        best = best.min(start.elapsed());
+    }
+    best
+}
+
+fn main() {
+    let input = input();
+    let options = NdjsonOptions::default();
+    let megabytes = input.len() as f64 / (1024.0 * 1024.0);
+
+    let sequential = measure(|| {
+        let values: Vec<_> = NdjsonReader::new(input.as_slice()).collect();
+        values.len()
+    });
+    println!(
+        "sequential reader: {sequential:>10.2?} ({:.1} MiB/s)",
+        megabytes / sequential.as_secs_f64()
+    );
+
+    let available = thread::available_parallelism().map_or(4, |workers| workers.get());
+    let mut worker_counts = vec![1, 2, 4, available];
+    worker_counts.sort();
+    worker_counts.dedup();
+    for workers in worker_counts {
+        let parallel = measure(|| parse_parallel(&input, workers, &options).len());
+        println!(
+            "{workers:>2} workers:        {parallel:>10.2?} ({:.1} MiB/s, {:.2}x)",
+            megabytes / parallel.as_secs_f64(),
+            sequential.as_secs_f64() / parallel.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,382 @@
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures_core::Stream;
+use tokio::io::{AsyncBufRead, AsyncRead, BufReader};
+
+use super::byte_lexer::ByteLexer;
+use super::events::{Event, EventError, Grammar, Position};
+use super::ndjson::{parse_line, NdjsonError, NdjsonOptions, OnError};
+use super::scanner::Lexeme;
+use super::stream::DEFAULT_BUFFER_SIZE;
+use super::syntactic_analyzer::{JsonValue, ValueBuilder};
+
+// The async counterpart of stream::StreamLexer, driving the same byte lexer
+#[derive(Debug)]
+pub struct AsyncStreamLexer<R> {
+    reader: R,
+    lexer: ByteLexer,
+    finished: bool,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncStreamLexer<R> {
+    pub fn new(reader: R) -> AsyncStreamLexer<R> {
+        AsyncStreamLexer {
+            reader,
+            lexer: ByteLexer::new(),
+            finished: false,
+        }
+    }
+
+    pub fn position(&self) -> Position {
+        self.lexer.position()
+    }
+
+    pub async fn next_lexeme(&mut self) -> Result<Option<(Lexeme<'static>, Position)>, EventError> {
+        poll_fn(|context| self.poll_next_lexeme(context)).await
+    }
+
+    // Everything the lexer needs to wait on, so the readers can be polled as streams
+    pub fn poll_next_lexeme(
+        &mut self,
+        context: &mut Context<'_>,
+    ) -> Poll<Result<Option<(Lexeme<'static>, Position)>, EventError>> {
+        while !self.finished {
+            let buffer = ready!(Pin::new(&mut self.reader).poll_fill_buf(context))
+                .map_err(|error| EventError::Io(error.to_string()))?;
+
+            if buffer.is_empty() {
+                self.finished = true;
+                return Poll::Ready(self.lexer.finish());
+            }
+
+            let (consumed, result) = self.lexer.next_lexeme(buffer);
+            Pin::new(&mut self.reader).consume(consumed);
+            if let Some(lexeme) = result? {
+                return Poll::Ready(Ok(Some(lexeme)));
+            }
+        }
+
+        Poll::Ready(Ok(None))
+    }
+}
+
+// Pull parser over an AsyncRead, each call to next_event waits for as much input
+// as the next event needs
+#[derive(Debug)]
+pub struct AsyncEventReader<R> {
+    lexer: AsyncStreamLexer<R>,
+    grammar: Grammar,
+    finished: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncEventReader<BufReader<R>> {
+    pub fn from_reader(reader: R) -> AsyncEventReader<BufReader<R>> {
+        AsyncEventReader::with_capacity(DEFAULT_BUFFER_SIZE, reader)
+    }
+
+    pub fn with_capacity(capacity: usize, reader: R) -> AsyncEventReader<BufReader<R>> {
+        AsyncEventReader::new(BufReader::with_capacity(capacity, reader))
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncEventReader<R> {
+    pub fn new(reader: R) -> AsyncEventReader<R> {
+        AsyncEventReader {
+            lexer: AsyncStreamLexer::new(reader),
+            grammar: Grammar::new(),
+            finished: false,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.grammar.depth()
+    }
+
+    pub fn position(&self) -> Position {
+        self.lexer.position()
+    }
+
+    pub async fn next_event(&mut self) -> Result<Option<(Event<'static>, Position)>, EventError> {
+        poll_fn(|context| self.poll_next_event(context)).await
+    }
+
+    pub fn poll_next_event(
+        &mut self,
+        context: &mut Context<'_>,
+    ) -> Poll<Result<Option<(Event<'static>, Position)>, EventError>> {
+        while !self.finished {
+            let next = ready!(self.lexer.poll_next_lexeme(context))
+                .inspect_err(|_| self.finished = true)?;
+
+            let Some((lexeme, position)) = next else {
+                self.finished = true;
+                self.grammar.finish(self.lexer.position())?;
+                break;
+            };
+
+            let event = self
+                .grammar
+                .accept(lexeme, position)
+                .inspect_err(|_| self.finished = true)?;
+            if let Some(event) = event {
+                return Poll::Ready(Ok(Some((event, position))));
+            }
+        }
+
+        Poll::Ready(Ok(None))
+    }
+}
+
+// Like the sync EventReader iterator, an error is the last item
+impl<R: AsyncBufRead + Unpin> Stream for AsyncEventReader<R> {
+    type Item = Result<(Event<'static>, Position), EventError>;
+
+    fn poll_next(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut()
+            .poll_next_event(context)
+            .map(Result::transpose)
+    }
+}
+
+// The async counterpart of ndjson::NdjsonReader, with the same options and errors
+#[derive(Debug)]
+pub struct AsyncNdjsonReader<R> {
+    reader: R,
+    options: NdjsonOptions,
+    line: usize,
+    buffer: Vec<u8>,
+    stopped: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncNdjsonReader<BufReader<R>> {
+    pub fn from_reader(reader: R) -> AsyncNdjsonReader<BufReader<R>> {
+        AsyncNdjsonReader::new(BufReader::with_capacity(DEFAULT_BUFFER_SIZE, reader))
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncNdjsonReader<R> {
+    pub fn new(reader: R) -> AsyncNdjsonReader<R> {
+        AsyncNdjsonReader::with_options(reader, NdjsonOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: NdjsonOptions) -> AsyncNdjsonReader<R> {
+        AsyncNdjsonReader {
+            reader,
+            options,
+            line: 0,
+            buffer: Vec::new(),
+            stopped: false,
+        }
+    }
+
+    // The number of the last line read
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub async fn next_value(&mut self) -> Option<Result<JsonValue, NdjsonError>> {
+        poll_fn(|context| self.poll_next_value(context)).await
+    }
+
+    // The line under construction stays in the buffer while the reader is pending
+    pub fn poll_next_value(
+        &mut self,
+        context: &mut Context<'_>,
+    ) -> Poll<Option<Result<JsonValue, NdjsonError>>> {
+        while !self.stopped {
+            let available = match ready!(Pin::new(&mut self.reader).poll_fill_buf(context)) {
+                Ok(available) => available,
+                Err(error) => {
+                    self.stopped = true;
+                    let error = NdjsonError::Io(self.line + 1, error.to_string());
+                    return Poll::Ready(Some(Err(error)));
+                }
+            };
+
+            if available.is_empty() && self.buffer.is_empty() {
+                self.stopped = true;
+                break;
+            }
+
+            let consumed = match available.iter().position(|&byte| byte == b'\n') {
+                Some(index) => index + 1,
+                None => available.len(),
+            };
+            self.buffer.extend_from_slice(&available[..consumed]);
+            Pin::new(&mut self.reader).consume(consumed);
+            if consumed != 0 && self.buffer.last() != Some(&b'\n') {
+                continue;
+            }
+
+            self.line += 1;
+            let result = parse_line(&self.buffer, self.line, &self.options);
+            self.buffer.clear();
+            match result {
+                None => continue,
+                Some(Err(_)) if self.options.on_error == OnError::Stop => self.stopped = true,
+                Some(_) => {}
+            }
+            return Poll::Ready(result);
+        }
+
+        Poll::Ready(None)
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> Stream for AsyncNdjsonReader<R> {
+    type Item = Result<JsonValue, NdjsonError>;
+
+    fn poll_next(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_next_value(context)
+    }
+}
+
+pub async fn parse_async<R: AsyncRead + Unpin>(reader: R) -> Result<JsonValue, EventError> {
+    let mut reader = AsyncEventReader::from_reader(reader);
+    let mut builder = ValueBuilder::new();
+    let mut result = None;
+
+    while let Some((event, _)) = reader.next_event().await? {
+        if let Some(value) = builder.push(event) {
+            result = Some(value);
+        }
+    }
+
+    result.ok_or(EventError::UnexpectedEnd(reader.position()))
+}
+
+#[cfg(test)]
+pub mod async_stream_tests {
+    use super::*;
+    use crate::json::events::EventReader;
+    use crate::json::ndjson::NdjsonReader;
+    use crate::json::syntactic_analyzer::parse_str;
+    use pretty_assertions::assert_eq;
+    use tokio::io::{duplex, AsyncWriteExt};
+
+    const INPUT: &str = r#"{"name": "café ☕", "tags": ["a\"b", "😀"], "n": -12.5e1}"#;
+
+    #[tokio::test]
+    pub async fn test_parse_async_over_duplex() {
+        // a pipe holding 3 bytes at a time forces every lexeme across several reads
+        let (mut client, server) = duplex(3);
+        let write = async move {
+            for chunk in INPUT.as_bytes().chunks(2) {
+                client.write_all(chunk).await.unwrap();
+            }
+        };
+
+        let (_, value) = tokio::join!(write, parse_async(server));
+        assert_eq!(value.unwrap(), parse_str(INPUT).unwrap());
+    }
+
+    #[tokio::test]
+    pub async fn test_async_events() {
+        let mut reader = AsyncEventReader::with_capacity(1, INPUT.as_bytes());
+        let mut events = Vec::new();
+        while let Some((event, position)) = reader.next_event().await.unwrap() {
+            events.push((event, position));
+        }
+
+        let expected: Vec<(Event, Position)> = EventReader::new(INPUT)
+            .map(|result| {
+                let (event, position) = result.unwrap();
+                (event.into_owned(), position)
+            })
+            .collect();
+        assert_eq!(events, expected);
+    }
+
+    #[tokio::test]
+    pub async fn test_async_errors() {
+        // the writer goes away in the middle of the document
+        let (mut client, server) = duplex(16);
+        let write = async move {
+            client.write_all(br#"{"unfinished": [1, "#).await.unwrap();
+            drop(client);
+        };
+
+        let (_, value) = tokio::join!(write, parse_async(server));
+        assert!(matches!(value, Err(EventError::UnexpectedEnd(_))));
+
+        assert!(matches!(
+            parse_async(&b"[1 2]"[..]).await,
+            Err(EventError::UnexpectedToken("number", _))
+        ));
+    }
+
+    const LINES: &str = "{\"id\": 1, \"name\": \"café\"}\r\n\n[1, 2]\n{\"broken\": }\n\"last\"";
+
+    // Drains a stream without an executor helper crate
+    async fn collect<S: Stream + Unpin>(mut stream: S) -> Vec<S::Item> {
+        let mut items = Vec::new();
+        while let Some(item) = poll_fn(|context| Pin::new(&mut stream).poll_next(context)).await {
+            items.push(item);
+        }
+        items
+    }
+
+    #[tokio::test]
+    pub async fn test_ndjson_over_duplex() {
+        let options = NdjsonOptions {
+            on_error: OnError::Continue,
+            ..NdjsonOptions::default()
+        };
+        let (mut client, server) = duplex(3);
+        let write = async move {
+            for chunk in LINES.as_bytes().chunks(2) {
+                client.write_all(chunk).await.unwrap();
+            }
+        };
+        let read = async {
+            let mut reader =
+                AsyncNdjsonReader::with_options(BufReader::new(server), options.clone());
+            let mut values = Vec::new();
+            while let Some(value) = reader.next_value().await {
+                values.push((reader.line(), value));
+            }
+            values
+        };
+
+        let (_, values) = tokio::join!(write, read);
+        let mut expected = NdjsonReader::with_options(LINES.as_bytes(), options);
+        let expected: Vec<_> = std::iter::from_fn(|| {
+            let value = expected.next_value()?;
+            Some((expected.line(), value))
+        })
+        .collect();
+        assert_eq!(values, expected);
+        assert_eq!(values.len(), 4);
+        assert!(matches!(values[2], (4, Err(NdjsonError::Syntax(4, _)))));
+    }
+
+    #[tokio::test]
+    pub async fn test_streams() {
+        let values = collect(AsyncNdjsonReader::from_reader(LINES.as_bytes())).await;
+        assert_eq!(
+            values,
+            NdjsonReader::new(LINES.as_bytes()).collect::<Vec<_>>()
+        );
+
+        let strict = NdjsonOptions {
+            skip_blank_lines: false,
+            ..NdjsonOptions::default()
+        };
+        let values = collect(AsyncNdjsonReader::with_options(LINES.as_bytes(), strict)).await;
+        assert_eq!(values.last(), Some(&Err(NdjsonError::BlankLine(2))));
+
+        let events = collect(AsyncEventReader::with_capacity(1, INPUT.as_bytes())).await;
+        let expected: Vec<_> = EventReader::new(INPUT)
+            .map(|result| result.map(|(event, position)| (event.into_owned(), position)))
+            .collect();
+        assert_eq!(events, expected);
+
+        let events = collect(AsyncEventReader::from_reader(&b"[1 2]"[..])).await;
+        assert!(matches!(
+            events.last(),
+            Some(Err(EventError::UnexpectedToken("number", _)))
+        ));
+    }
+}
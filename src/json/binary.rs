@@ -0,0 +1,116 @@
+// What the binary formats (CBOR, MessagePack, BSON, UBJSON and Smile) share: reading
+// their data and telling which numbers can be written as integers
+
+// Nesting limit of the decoders, the input may come from anywhere
+pub const MAX_DEPTH: usize = 512;
+
+// The data ends before what is read, at the byte given
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnexpectedEnd(pub usize);
+
+// Reads the data front to back, never past its end
+#[derive(Debug, Clone)]
+pub struct Cursor<'a> {
+    pub bytes: &'a [u8],
+    pub offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(bytes: &'a [u8], offset: usize) -> Cursor<'a> {
+        Cursor { bytes, offset }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.offset
+    }
+
+    pub fn take(&mut self, length: usize) -> Result<&'a [u8], UnexpectedEnd> {
+        if length > self.remaining() {
+            return Err(UnexpectedEnd(self.bytes.len()));
+        }
+        let taken = &self.bytes[self.offset..self.offset + length];
+        self.offset += length;
+        Ok(taken)
+    }
+
+    pub fn fixed<const N: usize>(&mut self) -> Result<[u8; N], UnexpectedEnd> {
+        Ok(self.take(N)?.try_into().expect("N bytes were taken"))
+    }
+
+    pub fn byte(&mut self) -> Result<u8, UnexpectedEnd> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    pub fn peek(&self) -> Result<u8, UnexpectedEnd> {
+        self.bytes
+            .get(self.offset)
+            .copied()
+            .ok_or(UnexpectedEnd(self.bytes.len()))
+    }
+
+    // Whether the data can hold a count of items read from it, before any is decoded:
+    // items of `size` bytes have to fit in what is left, and items taking no bytes at
+    // all cannot outnumber the bytes of the whole data
+    pub fn holds(&self, count: u64, size: usize) -> bool {
+        match size {
+            0 => count <= self.bytes.len() as u64,
+            size => count <= (self.remaining() / size) as u64,
+        }
+    }
+}
+
+// Whether a number has no fractional part, -0 aside since an integer would lose its sign
+pub fn is_integral(number: f32) -> bool {
+    number.fract() == 0.0 && !(number == 0.0 && number.is_sign_negative())
+}
+
+// The number as an int64, when it is integral and in range
+pub fn as_i64(number: f32) -> Option<i64> {
+    // 2^63, the first value out of the range of int64
+    const LIMIT: f32 = 9223372036854775808.0;
+    (is_integral(number) && (-LIMIT..LIMIT).contains(&number)).then_some(number as i64)
+}
+
+#[cfg(test)]
+pub mod binary_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    // The bytes written in hexadecimal, two digits each
+    pub fn hex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&text[index..index + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    pub fn test_cursor() {
+        let mut cursor = Cursor::new(&[1, 2, 3, 4], 1);
+
+        assert_eq!(cursor.peek(), Ok(2));
+        assert_eq!(cursor.fixed::<2>(), Ok([2, 3]));
+        assert_eq!(cursor.remaining(), 1);
+        assert_eq!(cursor.take(2), Err(UnexpectedEnd(4)));
+        assert_eq!(cursor.byte(), Ok(4));
+        assert_eq!(cursor.peek(), Err(UnexpectedEnd(4)));
+
+        let cursor = Cursor::new(&[0; 10], 4);
+        assert!(cursor.holds(3, 2));
+        assert!(!cursor.holds(4, 2));
+        assert!(cursor.holds(10, 0));
+        assert!(!cursor.holds(11, 0));
+    }
+
+    #[test]
+    pub fn test_integral_numbers() {
+        assert_eq!(as_i64(-42.0), Some(-42));
+        assert_eq!(as_i64(0.0), Some(0));
+        assert_eq!(as_i64(-0.0), None);
+        assert_eq!(as_i64(1.5), None);
+        assert_eq!(as_i64(f32::NAN), None);
+        assert_eq!(as_i64(-9223372036854775808.0), Some(i64::MIN));
+        assert_eq!(as_i64(9223372036854775808.0), None);
+        assert!(is_integral(1e30));
+    }
+}
@@ -0,0 +1,154 @@
+use std::borrow::Cow;
+
+use super::events::{EventError, EventReader};
+use super::syntactic_analyzer::{build_events, FromEvents, JsonPair, JsonValue, TreeValue};
+
+// A JsonValue whose strings and keys point into the parsed input whenever they
+// contain no escape sequence
+#[derive(Debug, Clone, PartialEq)]
+pub enum BorrowedValue<'a> {
+    String(Cow<'a, str>),
+    Number(f32),
+    Boolean(bool),
+    Null,
+    Array(Vec<BorrowedValue<'a>>),
+    Object(Vec<(Cow<'a, str>, BorrowedValue<'a>)>),
+}
+
+impl<'a> BorrowedValue<'a> {
+    // first member with that key
+    pub fn get(&self, key: &str) -> Option<&BorrowedValue<'a>> {
+        match self {
+            BorrowedValue::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn get_index(&self, index: usize) -> Option<&BorrowedValue<'a>> {
+        match self {
+            BorrowedValue::Array(elements) => elements.get(index),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            BorrowedValue::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn to_json_value(&self) -> JsonValue {
+        match self {
+            BorrowedValue::String(string) => JsonValue::String(string.to_string()),
+            BorrowedValue::Number(number) => JsonValue::Number(*number),
+            BorrowedValue::Boolean(boolean) => JsonValue::Boolean(*boolean),
+            BorrowedValue::Null => JsonValue::Null,
+            BorrowedValue::Array(elements) => {
+                JsonValue::JsonArray(elements.iter().map(BorrowedValue::to_json_value).collect())
+            }
+            BorrowedValue::Object(members) => JsonValue::JsonObject(
+                members
+                    .iter()
+                    .map(|(key, value)| JsonPair(key.to_string(), value.to_json_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<BorrowedValue<'_>> for JsonValue {
+    fn from(value: BorrowedValue<'_>) -> Self {
+        value.to_json_value()
+    }
+}
+
+impl<'a> TreeValue for BorrowedValue<'a> {
+    type Key = Cow<'a, str>;
+    type Member = (Cow<'a, str>, BorrowedValue<'a>);
+
+    fn number(number: f32) -> Self {
+        BorrowedValue::Number(number)
+    }
+
+    fn boolean(boolean: bool) -> Self {
+        BorrowedValue::Boolean(boolean)
+    }
+
+    fn null() -> Self {
+        BorrowedValue::Null
+    }
+
+    fn member(key: Cow<'a, str>, value: Self) -> Self::Member {
+        (key, value)
+    }
+
+    fn object(members: Vec<Self::Member>) -> Self {
+        BorrowedValue::Object(members)
+    }
+
+    fn array(elements: Vec<Self>) -> Self {
+        BorrowedValue::Array(elements)
+    }
+}
+
+// The strings of the events are kept as they are, borrowed or not
+impl<'a> FromEvents<'a> for BorrowedValue<'a> {
+    fn key(key: Cow<'a, str>) -> Cow<'a, str> {
+        key
+    }
+
+    fn string(string: Cow<'a, str>) -> Self {
+        BorrowedValue::String(string)
+    }
+}
+
+// Same as syntactic_analyzer::parse_str, keeping the borrowed strings of the events
+pub fn parse_borrowed(input: &str) -> Result<BorrowedValue<'_>, EventError> {
+    build_events(EventReader::new(input))
+}
+
+#[cfg(test)]
+pub mod borrowed_tests {
+    use super::*;
+    use crate::json::syntactic_analyzer::parse_str;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    pub fn test_parse_borrowed() {
+        let input = r#"{"items": [{"id": "a1"}, {"id": "b\"2"}], "count": 2}"#;
+        let value = parse_borrowed(input).unwrap();
+
+        let first = value.get("items").and_then(|items| items.get_index(0));
+        assert!(matches!(
+            first.and_then(|item| item.get("id")),
+            Some(BorrowedValue::String(Cow::Borrowed("a1")))
+        ));
+        let second = value.get("items").and_then(|items| items.get_index(1));
+        assert_eq!(
+            second
+                .and_then(|item| item.get("id"))
+                .and_then(BorrowedValue::as_str),
+            Some("b\"2")
+        );
+        assert_eq!(value.get("count"), Some(&BorrowedValue::Number(2.0)));
+        assert_eq!(value.get("missing"), None);
+
+        assert_eq!(JsonValue::from(value), parse_str(input).unwrap());
+    }
+
+    #[test]
+    pub fn test_parse_borrowed_errors() {
+        assert!(matches!(
+            parse_borrowed("[1, 2"),
+            Err(EventError::UnexpectedEnd(_))
+        ));
+        assert!(matches!(
+            parse_borrowed("[1] 2"),
+            Err(EventError::TrailingCharacters(_))
+        ));
+    }
+}
@@ -0,0 +1,1281 @@
+use thiserror::Error;
+
+use super::binary::{as_i64, Cursor, UnexpectedEnd};
+use super::syntactic_analyzer::{JsonPair, JsonValue};
+
+// The encoder keeps to the nesting limit of the decoder too
+pub use super::binary::MAX_DEPTH;
+
+// Byte offsets point at the start of the offending element or document
+#[derive(Debug, Clone, Error, PartialEq)]
+pub enum BsonError {
+    #[error("Unexpected end of the BSON data at byte {0}")]
+    UnexpectedEnd(usize),
+
+    #[error("Invalid length {0} at byte {1}")]
+    InvalidLength(i64, usize),
+
+    #[error("Unknown element type 0x{0:02x} at byte {1}")]
+    UnknownType(u8, usize),
+
+    #[error("Invalid boolean 0x{0:02x} at byte {1}")]
+    InvalidBoolean(u8, usize),
+
+    #[error("Invalid UTF-8 in the string at byte {0}")]
+    InvalidUtf8(usize),
+
+    #[error("Documents are nested deeper than {0} levels")]
+    TooDeep(usize),
+
+    #[error("{0} bytes follow the BSON document")]
+    TrailingBytes(usize),
+
+    #[error("Only objects can be written as BSON documents")]
+    NotADocument,
+
+    #[error("The key '{0}' contains a NUL character")]
+    KeyWithNul(String),
+
+    #[error("Invalid {0} value in extended json")]
+    InvalidExtendedJson(String),
+}
+
+// MongoDB Extended JSON v2. Canonical mode keeps the type of every value, relaxed
+// mode writes numbers and recent dates the way plain json would.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtendedJsonMode {
+    Canonical,
+    Relaxed,
+}
+
+const DOUBLE: u8 = 0x01;
+const STRING: u8 = 0x02;
+const DOCUMENT: u8 = 0x03;
+const ARRAY: u8 = 0x04;
+const BINARY: u8 = 0x05;
+const UNDEFINED: u8 = 0x06;
+const OBJECT_ID: u8 = 0x07;
+const BOOLEAN: u8 = 0x08;
+const DATETIME: u8 = 0x09;
+const NULL: u8 = 0x0a;
+const REGEX: u8 = 0x0b;
+const DB_POINTER: u8 = 0x0c;
+const CODE: u8 = 0x0d;
+const SYMBOL: u8 = 0x0e;
+const CODE_WITH_SCOPE: u8 = 0x0f;
+const INT32: u8 = 0x10;
+const TIMESTAMP: u8 = 0x11;
+const INT64: u8 = 0x12;
+const DECIMAL128: u8 = 0x13;
+const MAX_KEY: u8 = 0x7f;
+const MIN_KEY: u8 = 0xff;
+
+// The binary subtype that repeats the length of its data
+const OLD_BINARY: u8 = 0x02;
+
+// Writes an object as a BSON document. Members written in extended json, such as
+// {"$oid": ...} or {"$numberLong": ...}, become the BSON type they describe in either
+// mode. Integral numbers become int32 or int64 values, the other numbers doubles.
+pub fn to_bson(value: &JsonValue) -> Result<Vec<u8>, BsonError> {
+    let JsonValue::JsonObject(members) = value else {
+        return Err(BsonError::NotADocument);
+    };
+    let mut out = Vec::new();
+    document(&mut out, members_of(members), 0)?;
+    Ok(out)
+}
+
+// Reads a BSON document, the types that json lacks are written in extended json
+pub fn from_bson(bytes: &[u8], mode: ExtendedJsonMode) -> Result<JsonValue, BsonError> {
+    let mut decoder = Decoder {
+        cursor: Cursor::new(bytes, 0),
+        mode,
+    };
+    let members = decoder.document(0)?;
+    match decoder.cursor.remaining() {
+        0 => Ok(JsonValue::JsonObject(members)),
+        trailing => Err(BsonError::TrailingBytes(trailing)),
+    }
+}
+
+// Rewrites an extended json document in the given mode, checking its wrappers
+pub fn to_extended_json(value: &JsonValue, mode: ExtendedJsonMode) -> Result<JsonValue, BsonError> {
+    from_bson(&to_bson(value)?, mode)
+}
+
+fn members_of(members: &[JsonPair]) -> impl Iterator<Item = (&str, &JsonValue)> {
+    members
+        .iter()
+        .map(|JsonPair(key, member)| (key.as_str(), member))
+}
+
+fn document<'v, K: AsRef<str>>(
+    out: &mut Vec<u8>,
+    entries: impl Iterator<Item = (K, &'v JsonValue)>,
+    depth: usize,
+) -> Result<(), BsonError> {
+    if depth > MAX_DEPTH {
+        return Err(BsonError::TooDeep(MAX_DEPTH));
+    }
+
+    // the length is known once the document is written
+    let start = out.len();
+    out.extend_from_slice(&[0; 4]);
+    for (key, value) in entries {
+        let type_at = out.len();
+        out.push(0);
+        cstring(out, key.as_ref())?;
+        out[type_at] = element(out, value, depth)?;
+    }
+    out.push(0);
+    patch_length(out, start)
+}
+
+// BSON lengths are signed 32 bit, so nothing of 2 GiB or more can be written. The
+// error reports the length and where it would have been written.
+fn length_at(length: usize, at: usize) -> Result<i32, BsonError> {
+    i32::try_from(length).map_err(|_| BsonError::InvalidLength(length as i64, at))
+}
+
+fn patch_length(out: &mut [u8], start: usize) -> Result<(), BsonError> {
+    let length = length_at(out.len() - start, start)?;
+    out[start..start + 4].copy_from_slice(&length.to_le_bytes());
+    Ok(())
+}
+
+fn cstring(out: &mut Vec<u8>, text: &str) -> Result<(), BsonError> {
+    if text.contains('\0') {
+        return Err(BsonError::KeyWithNul(text.to_string()));
+    }
+    out.extend_from_slice(text.as_bytes());
+    out.push(0);
+    Ok(())
+}
+
+fn string(out: &mut Vec<u8>, text: &str) -> Result<(), BsonError> {
+    let length = length_at(text.len() + 1, out.len())?;
+    out.extend_from_slice(&length.to_le_bytes());
+    out.extend_from_slice(text.as_bytes());
+    out.push(0);
+    Ok(())
+}
+
+// Writes the value of an element and returns its type
+fn element(out: &mut Vec<u8>, value: &JsonValue, depth: usize) -> Result<u8, BsonError> {
+    match value {
+        JsonValue::Null => Ok(NULL),
+        JsonValue::Boolean(boolean) => {
+            out.push(*boolean as u8);
+            Ok(BOOLEAN)
+        }
+        JsonValue::Number(number) => Ok(plain_number(out, *number)),
+        JsonValue::String(text) => {
+            string(out, text)?;
+            Ok(STRING)
+        }
+        JsonValue::JsonArray(elements) => {
+            let entries = elements
+                .iter()
+                .enumerate()
+                .map(|(index, element)| (index.to_string(), element));
+            document(out, entries, depth + 1)?;
+            Ok(ARRAY)
+        }
+        JsonValue::JsonObject(members) => match extended(out, members, depth)? {
+            Some(element_type) => Ok(element_type),
+            None => {
+                document(out, members_of(members), depth + 1)?;
+                Ok(DOCUMENT)
+            }
+        },
+    }
+}
+
+fn plain_number(out: &mut Vec<u8>, number: f32) -> u8 {
+    match as_i64(number) {
+        Some(integer) => match i32::try_from(integer) {
+            Ok(small) => {
+                out.extend_from_slice(&small.to_le_bytes());
+                INT32
+            }
+            Err(_) => {
+                out.extend_from_slice(&integer.to_le_bytes());
+                INT64
+            }
+        },
+        None => {
+            out.extend_from_slice(&(number as f64).to_le_bytes());
+            DOUBLE
+        }
+    }
+}
+
+fn member<'v>(members: &'v [JsonPair], key: &str) -> Option<&'v JsonValue> {
+    members
+        .iter()
+        .find(|JsonPair(name, _)| name == key)
+        .map(|JsonPair(_, value)| value)
+}
+
+fn text_of(value: Option<&JsonValue>) -> Option<&str> {
+    match value {
+        Some(JsonValue::String(text)) => Some(text),
+        _ => None,
+    }
+}
+
+fn u32_of(value: Option<&JsonValue>) -> Option<u32> {
+    match value {
+        Some(JsonValue::Number(number))
+            if number.fract() == 0.0 && (0.0..=u32::MAX as f32).contains(number) =>
+        {
+            Some(*number as u32)
+        }
+        _ => None,
+    }
+}
+
+// Writes an extended json wrapper as the type it describes. None when the object is
+// an ordinary document.
+fn extended(
+    out: &mut Vec<u8>,
+    members: &[JsonPair],
+    depth: usize,
+) -> Result<Option<u8>, BsonError> {
+    let mut keys: Vec<&str> = members
+        .iter()
+        .map(|JsonPair(key, _)| key.as_str())
+        .collect();
+    keys.sort_unstable();
+    let first = members.first().map(|JsonPair(_, value)| value);
+
+    let written = match keys.as_slice() {
+        ["$oid"] => text_of(first).and_then(object_id).map(|bytes| {
+            out.extend_from_slice(&bytes);
+            OBJECT_ID
+        }),
+        ["$symbol"] => text_of(first)
+            .map(|text| string(out, text).map(|()| SYMBOL))
+            .transpose()?,
+        ["$code"] => text_of(first)
+            .map(|text| string(out, text).map(|()| CODE))
+            .transpose()?,
+        ["$code", "$scope"] => {
+            let code = text_of(member(members, "$code"));
+            match (code, member(members, "$scope")) {
+                (Some(code), Some(JsonValue::JsonObject(scope))) => {
+                    let start = out.len();
+                    out.extend_from_slice(&[0; 4]);
+                    string(out, code)?;
+                    document(out, members_of(scope), depth + 1)?;
+                    patch_length(out, start)?;
+                    Some(CODE_WITH_SCOPE)
+                }
+                _ => None,
+            }
+        }
+        ["$numberInt"] => text_of(first)
+            .and_then(|text| text.parse::<i32>().ok())
+            .map(|integer| {
+                out.extend_from_slice(&integer.to_le_bytes());
+                INT32
+            }),
+        ["$numberLong"] => text_of(first)
+            .and_then(|text| text.parse::<i64>().ok())
+            .map(|integer| {
+                out.extend_from_slice(&integer.to_le_bytes());
+                INT64
+            }),
+        ["$numberDouble"] => text_of(first).and_then(parse_double).map(|double| {
+            out.extend_from_slice(&double.to_le_bytes());
+            DOUBLE
+        }),
+        ["$numberDecimal"] => text_of(first).and_then(parse_decimal).map(|bits| {
+            out.extend_from_slice(&bits.to_le_bytes());
+            DECIMAL128
+        }),
+        ["$date"] => {
+            let milliseconds = match first {
+                Some(JsonValue::String(text)) => parse_date(text),
+                Some(JsonValue::JsonObject(inner)) => match inner.as_slice() {
+                    [JsonPair(key, JsonValue::String(text))] if key == "$numberLong" => {
+                        text.parse::<i64>().ok()
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+            milliseconds.map(|milliseconds| {
+                out.extend_from_slice(&milliseconds.to_le_bytes());
+                DATETIME
+            })
+        }
+        ["$binary"] => {
+            let Some(JsonValue::JsonObject(inner)) = first else {
+                return Err(invalid("$binary"));
+            };
+            let data = text_of(member(inner, "base64")).and_then(base64_decode);
+            let subtype = text_of(member(inner, "subType"))
+                .filter(|subtype| subtype.len() <= 2)
+                .and_then(|subtype| u8::from_str_radix(subtype, 16).ok());
+            match (data, subtype, inner.len()) {
+                (Some(data), Some(subtype), 2) => {
+                    binary(out, &data, subtype)?;
+                    Some(BINARY)
+                }
+                _ => None,
+            }
+        }
+        ["$regularExpression"] => {
+            let Some(JsonValue::JsonObject(inner)) = first else {
+                return Err(invalid("$regularExpression"));
+            };
+            let pattern = text_of(member(inner, "pattern"));
+            let options = text_of(member(inner, "options"));
+            match (pattern, options, inner.len()) {
+                (Some(pattern), Some(options), 2) => {
+                    // the options are stored in alphabetical order
+                    let mut options: Vec<char> = options.chars().collect();
+                    options.sort_unstable();
+                    cstring(out, pattern)?;
+                    cstring(out, &options.into_iter().collect::<String>())?;
+                    Some(REGEX)
+                }
+                _ => None,
+            }
+        }
+        ["$timestamp"] => {
+            let Some(JsonValue::JsonObject(inner)) = first else {
+                return Err(invalid("$timestamp"));
+            };
+            match (u32_of(member(inner, "t")), u32_of(member(inner, "i"))) {
+                (Some(time), Some(increment)) if inner.len() == 2 => {
+                    out.extend_from_slice(&increment.to_le_bytes());
+                    out.extend_from_slice(&time.to_le_bytes());
+                    Some(TIMESTAMP)
+                }
+                _ => None,
+            }
+        }
+        ["$dbPointer"] => {
+            let Some(JsonValue::JsonObject(inner)) = first else {
+                return Err(invalid("$dbPointer"));
+            };
+            let namespace = text_of(member(inner, "$ref"));
+            let id = match member(inner, "$id") {
+                Some(JsonValue::JsonObject(id)) => match id.as_slice() {
+                    [JsonPair(key, JsonValue::String(hex))] if key == "$oid" => object_id(hex),
+                    _ => None,
+                },
+                _ => None,
+            };
+            match (namespace, id, inner.len()) {
+                (Some(namespace), Some(id), 2) => {
+                    string(out, namespace)?;
+                    out.extend_from_slice(&id);
+                    Some(DB_POINTER)
+                }
+                _ => None,
+            }
+        }
+        ["$minKey"] => (first == Some(&JsonValue::Number(1.0))).then_some(MIN_KEY),
+        ["$maxKey"] => (first == Some(&JsonValue::Number(1.0))).then_some(MAX_KEY),
+        ["$undefined"] => (first == Some(&JsonValue::Boolean(true))).then_some(UNDEFINED),
+        _ => return Ok(None),
+    };
+
+    match written {
+        Some(element_type) => Ok(Some(element_type)),
+        None => Err(invalid(keys[0])),
+    }
+}
+
+fn invalid(key: &str) -> BsonError {
+    BsonError::InvalidExtendedJson(key.to_string())
+}
+
+fn binary(out: &mut Vec<u8>, data: &[u8], subtype: u8) -> Result<(), BsonError> {
+    if subtype == OLD_BINARY {
+        out.extend_from_slice(&length_at(data.len() + 4, out.len())?.to_le_bytes());
+        out.push(subtype);
+        out.extend_from_slice(&(data.len() as i32).to_le_bytes());
+    } else {
+        out.extend_from_slice(&length_at(data.len(), out.len())?.to_le_bytes());
+        out.push(subtype);
+    }
+    out.extend_from_slice(data);
+    Ok(())
+}
+
+fn object_id(hex: &str) -> Option<[u8; 12]> {
+    if hex.len() != 24 || !hex.is_ascii() {
+        return None;
+    }
+    let mut bytes = [0; 12];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * index..2 * index + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+impl From<UnexpectedEnd> for BsonError {
+    fn from(UnexpectedEnd(offset): UnexpectedEnd) -> Self {
+        BsonError::UnexpectedEnd(offset)
+    }
+}
+
+struct Decoder<'a> {
+    cursor: Cursor<'a>,
+    mode: ExtendedJsonMode,
+}
+
+impl Decoder<'_> {
+    fn i32(&mut self) -> Result<i32, BsonError> {
+        Ok(i32::from_le_bytes(self.cursor.fixed()?))
+    }
+
+    fn i64(&mut self) -> Result<i64, BsonError> {
+        Ok(i64::from_le_bytes(self.cursor.fixed()?))
+    }
+
+    // A length that must leave room for `minimum` bytes and fit in the data
+    fn length(&mut self, minimum: i32) -> Result<usize, BsonError> {
+        let start = self.cursor.offset;
+        let length = self.i32()?;
+        if length < minimum || start + length as usize > self.cursor.bytes.len() {
+            return Err(BsonError::InvalidLength(length as i64, start));
+        }
+        Ok(length as usize)
+    }
+
+    fn cstring(&mut self) -> Result<String, BsonError> {
+        let start = self.cursor.offset;
+        let Some(length) = self.cursor.bytes[start..]
+            .iter()
+            .position(|byte| *byte == 0)
+        else {
+            return Err(BsonError::UnexpectedEnd(self.cursor.bytes.len()));
+        };
+        let bytes = self.cursor.take(length + 1)?;
+        utf8(&bytes[..length], start)
+    }
+
+    fn string(&mut self) -> Result<String, BsonError> {
+        let start = self.cursor.offset;
+        let length = self.length(1)?;
+        let bytes = self.cursor.take(length)?;
+        if bytes[length - 1] != 0 {
+            return Err(BsonError::InvalidLength(length as i64, start));
+        }
+        utf8(&bytes[..length - 1], start)
+    }
+
+    fn document(&mut self, depth: usize) -> Result<Vec<JsonPair>, BsonError> {
+        if depth > MAX_DEPTH {
+            return Err(BsonError::TooDeep(MAX_DEPTH));
+        }
+
+        let start = self.cursor.offset;
+        let length = self.length(5)?;
+        let end = start + length;
+        let mut members = Vec::new();
+        loop {
+            if self.cursor.offset >= end {
+                return Err(BsonError::InvalidLength(length as i64, start));
+            }
+            let type_start = self.cursor.offset;
+            let element_type = self.cursor.fixed::<1>()?[0];
+            if element_type == 0 {
+                break;
+            }
+            let key = self.cstring()?;
+            let value = self.element(element_type, type_start, depth)?;
+            members.push(JsonPair(key, value));
+        }
+
+        if self.cursor.offset != end {
+            return Err(BsonError::InvalidLength(length as i64, start));
+        }
+        Ok(members)
+    }
+
+    fn element(
+        &mut self,
+        element_type: u8,
+        start: usize,
+        depth: usize,
+    ) -> Result<JsonValue, BsonError> {
+        let canonical = self.mode == ExtendedJsonMode::Canonical;
+        let value = match element_type {
+            DOUBLE => {
+                let double = f64::from_le_bytes(self.cursor.fixed()?);
+                let relaxed = double as f32;
+                if canonical || !relaxed.is_finite() {
+                    wrapper("$numberDouble", JsonValue::String(format_double(double)))
+                } else {
+                    JsonValue::Number(relaxed)
+                }
+            }
+            STRING => JsonValue::String(self.string()?),
+            DOCUMENT => JsonValue::JsonObject(self.document(depth + 1)?),
+            // the keys of an array are its indices
+            ARRAY => JsonValue::JsonArray(
+                self.document(depth + 1)?
+                    .into_iter()
+                    .map(|JsonPair(_, element)| element)
+                    .collect(),
+            ),
+            BINARY => {
+                let length = self.length(0)?;
+                let subtype = self.cursor.fixed::<1>()?[0];
+                let mut data = self.cursor.take(length)?;
+                if subtype == OLD_BINARY {
+                    let inner = data
+                        .get(..4)
+                        .map(|bytes| i32::from_le_bytes(bytes.try_into().expect("4 bytes")));
+                    if inner != Some(length as i32 - 4) {
+                        return Err(BsonError::InvalidLength(length as i64, start));
+                    }
+                    data = &data[4..];
+                }
+                wrapper(
+                    "$binary",
+                    JsonValue::JsonObject(vec![
+                        JsonPair("base64".to_string(), JsonValue::String(base64_encode(data))),
+                        JsonPair(
+                            "subType".to_string(),
+                            JsonValue::String(format!("{subtype:02x}")),
+                        ),
+                    ]),
+                )
+            }
+            UNDEFINED => wrapper("$undefined", JsonValue::Boolean(true)),
+            OBJECT_ID => oid(self.cursor.fixed::<12>()?),
+            BOOLEAN => match self.cursor.fixed::<1>()?[0] {
+                0 => JsonValue::Boolean(false),
+                1 => JsonValue::Boolean(true),
+                byte => return Err(BsonError::InvalidBoolean(byte, start)),
+            },
+            DATETIME => {
+                let milliseconds = self.i64()?;
+                match format_date(milliseconds).filter(|_| !canonical) {
+                    Some(date) => wrapper("$date", JsonValue::String(date)),
+                    None => wrapper("$date", number_long(milliseconds)),
+                }
+            }
+            NULL => JsonValue::Null,
+            REGEX => {
+                let pattern = self.cstring()?;
+                let options = self.cstring()?;
+                wrapper(
+                    "$regularExpression",
+                    JsonValue::JsonObject(vec![
+                        JsonPair("pattern".to_string(), JsonValue::String(pattern)),
+                        JsonPair("options".to_string(), JsonValue::String(options)),
+                    ]),
+                )
+            }
+            DB_POINTER => {
+                let namespace = self.string()?;
+                let id = oid(self.cursor.fixed::<12>()?);
+                wrapper(
+                    "$dbPointer",
+                    JsonValue::JsonObject(vec![
+                        JsonPair("$ref".to_string(), JsonValue::String(namespace)),
+                        JsonPair("$id".to_string(), id),
+                    ]),
+                )
+            }
+            CODE => wrapper("$code", JsonValue::String(self.string()?)),
+            SYMBOL => wrapper("$symbol", JsonValue::String(self.string()?)),
+            CODE_WITH_SCOPE => {
+                let scope_start = self.cursor.offset;
+                let length = self.length(14)?;
+                let code = self.string()?;
+                let scope = self.document(depth + 1)?;
+                if self.cursor.offset != scope_start + length {
+                    return Err(BsonError::InvalidLength(length as i64, scope_start));
+                }
+                JsonValue::JsonObject(vec![
+                    JsonPair("$code".to_string(), JsonValue::String(code)),
+                    JsonPair("$scope".to_string(), JsonValue::JsonObject(scope)),
+                ])
+            }
+            INT32 => {
+                let integer = self.i32()?;
+                if canonical {
+                    wrapper("$numberInt", JsonValue::String(integer.to_string()))
+                } else {
+                    JsonValue::Number(integer as f32)
+                }
+            }
+            TIMESTAMP => {
+                let increment = u32::from_le_bytes(self.cursor.fixed()?);
+                let time = u32::from_le_bytes(self.cursor.fixed()?);
+                wrapper(
+                    "$timestamp",
+                    JsonValue::JsonObject(vec![
+                        JsonPair("t".to_string(), JsonValue::Number(time as f32)),
+                        JsonPair("i".to_string(), JsonValue::Number(increment as f32)),
+                    ]),
+                )
+            }
+            INT64 => {
+                let integer = self.i64()?;
+                if canonical {
+                    number_long(integer)
+                } else {
+                    JsonValue::Number(integer as f32)
+                }
+            }
+            DECIMAL128 => {
+                let bits = u128::from_le_bytes(self.cursor.fixed()?);
+                wrapper("$numberDecimal", JsonValue::String(format_decimal(bits)))
+            }
+            MIN_KEY => wrapper("$minKey", JsonValue::Number(1.0)),
+            MAX_KEY => wrapper("$maxKey", JsonValue::Number(1.0)),
+            _ => return Err(BsonError::UnknownType(element_type, start)),
+        };
+        Ok(value)
+    }
+}
+
+fn utf8(bytes: &[u8], start: usize) -> Result<String, BsonError> {
+    std::str::from_utf8(bytes)
+        .map(str::to_string)
+        .map_err(|_| BsonError::InvalidUtf8(start))
+}
+
+fn wrapper(key: &str, value: JsonValue) -> JsonValue {
+    JsonValue::JsonObject(vec![JsonPair(key.to_string(), value)])
+}
+
+fn number_long(integer: i64) -> JsonValue {
+    wrapper("$numberLong", JsonValue::String(integer.to_string()))
+}
+
+fn oid(bytes: [u8; 12]) -> JsonValue {
+    let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    wrapper("$oid", JsonValue::String(hex))
+}
+
+// Integral doubles keep a ".0", very large and very small ones use an exponent
+fn format_double(double: f64) -> String {
+    if double.is_nan() {
+        return "NaN".to_string();
+    }
+    if double.is_infinite() {
+        let sign = if double < 0.0 { "-" } else { "" };
+        return format!("{sign}Infinity");
+    }
+
+    let magnitude = double.abs();
+    if magnitude != 0.0 && !(1e-6..1e16).contains(&magnitude) {
+        let text = format!("{double:E}");
+        return match text.split_once('E') {
+            Some((mantissa, exponent)) if !exponent.starts_with('-') => {
+                format!("{mantissa}E+{exponent}")
+            }
+            _ => text,
+        };
+    }
+    if double.fract() == 0.0 {
+        format!("{double:.1}")
+    } else {
+        format!("{double}")
+    }
+}
+
+fn parse_double(text: &str) -> Option<f64> {
+    match text {
+        "Infinity" => Some(f64::INFINITY),
+        "-Infinity" => Some(f64::NEG_INFINITY),
+        "NaN" => Some(f64::NAN),
+        // Rust also reads "inf" and "nan", extended json does not
+        _ if text.contains(|ch: char| ch.is_ascii_alphabetic() && ch != 'e' && ch != 'E') => None,
+        _ => text.parse().ok(),
+    }
+}
+
+// The ISO-8601 form of the dates from 1970 to 9999, with milliseconds when there are some
+fn format_date(milliseconds: i64) -> Option<String> {
+    let days = milliseconds.div_euclid(86_400_000);
+    let time = milliseconds.rem_euclid(86_400_000);
+    let (year, month, day) = civil_from_days(days);
+    if !(1970..=9999).contains(&year) {
+        return None;
+    }
+
+    let (hours, minutes) = (time / 3_600_000, time / 60_000 % 60);
+    let (seconds, millis) = (time / 1000 % 60, time % 1000);
+    let fraction = if millis == 0 {
+        String::new()
+    } else {
+        format!(".{millis:03}")
+    };
+    Some(format!(
+        "{year:04}-{month:02}-{day:02}T{hours:02}:{minutes:02}:{seconds:02}{fraction}Z"
+    ))
+}
+
+// YYYY-MM-DDTHH:MM:SS with optional fractional seconds, then Z or an offset
+fn parse_date(text: &str) -> Option<i64> {
+    let bytes = text.as_bytes();
+    let number = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = text.get(range)?;
+        digits
+            .bytes()
+            .all(|byte| byte.is_ascii_digit())
+            .then(|| digits.parse().ok())?
+    };
+    let separators = [(4, b'-'), (7, b'-'), (10, b'T'), (13, b':'), (16, b':')];
+    if separators
+        .iter()
+        .any(|(index, separator)| bytes.get(*index) != Some(separator))
+    {
+        return None;
+    }
+
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hours, minutes, seconds) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hours > 23
+        || minutes > 59
+        || seconds > 60
+    {
+        return None;
+    }
+
+    let mut rest = &text[19..];
+    let mut millis = 0;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let length = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if length == 0 {
+            return None;
+        }
+        // milliseconds, further digits are dropped
+        let digits = format!("{:0<3}", &fraction[..length.min(3)]);
+        millis = digits.parse::<i64>().ok()?;
+        rest = &fraction[length..];
+    }
+
+    let offset_minutes = match rest.as_bytes() {
+        [b'Z'] => 0,
+        [sign @ (b'+' | b'-'), ..] => {
+            let offset = rest[1..].replace(':', "");
+            if offset.len() != 4 || !offset.bytes().all(|byte| byte.is_ascii_digit()) {
+                return None;
+            }
+            let minutes =
+                offset[..2].parse::<i64>().ok()? * 60 + offset[2..].parse::<i64>().ok()?;
+            if *sign == b'+' {
+                minutes
+            } else {
+                -minutes
+            }
+        }
+        _ => return None,
+    };
+
+    let days = days_from_civil(year, month, day);
+    let seconds = days * 86_400 + hours * 3600 + (minutes - offset_minutes) * 60 + seconds;
+    Some(seconds * 1000 + millis)
+}
+
+// Days since 1970-01-01 in the proleptic Gregorian calendar
+// (http://howardhinnant.github.io/date_algorithms.html)
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// IEEE 754 decimal128 in its binary integer encoding: a 113 bit coefficient and a
+// 14 bit exponent biased by 6176
+const DECIMAL_BIAS: i64 = 6176;
+const DECIMAL_MAX_EXPONENT: i64 = 6111;
+const DECIMAL_MAX_DIGITS: usize = 34;
+
+fn format_decimal(bits: u128) -> String {
+    let sign = if bits >> 127 == 1 { "-" } else { "" };
+    match (bits >> 122) & 0x1f {
+        0x1f => return "NaN".to_string(),
+        0x1e => return format!("{sign}Infinity"),
+        _ => {}
+    }
+
+    let (exponent, coefficient) = if (bits >> 125) & 0b11 == 0b11 {
+        // the coefficients of this form are all too large, they stand for zero
+        ((bits >> 111) & 0x3fff, 0)
+    } else {
+        ((bits >> 113) & 0x3fff, bits & ((1 << 113) - 1))
+    };
+    let exponent = exponent as i64 - DECIMAL_BIAS;
+    let coefficient = if coefficient >= 10u128.pow(DECIMAL_MAX_DIGITS as u32) {
+        0
+    } else {
+        coefficient
+    };
+
+    // the to-scientific-string rules of the decimal arithmetic specification
+    let digits = coefficient.to_string();
+    let adjusted = exponent + digits.len() as i64 - 1;
+    let text = if exponent <= 0 && adjusted >= -6 {
+        let fraction = (-exponent) as usize;
+        if fraction == 0 {
+            digits
+        } else if digits.len() > fraction {
+            let (whole, decimals) = digits.split_at(digits.len() - fraction);
+            format!("{whole}.{decimals}")
+        } else {
+            format!("0.{}{digits}", "0".repeat(fraction - digits.len()))
+        }
+    } else {
+        let (first, rest) = digits.split_at(1);
+        let point = if rest.is_empty() { "" } else { "." };
+        let exponent_sign = if adjusted >= 0 { "+" } else { "" };
+        format!("{first}{point}{rest}E{exponent_sign}{adjusted}")
+    };
+    format!("{sign}{text}")
+}
+
+// Only exact values are accepted, nothing is rounded
+fn parse_decimal(text: &str) -> Option<u128> {
+    let (negative, unsigned) = match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let sign = (negative as u128) << 127;
+    if unsigned.eq_ignore_ascii_case("infinity") || unsigned.eq_ignore_ascii_case("inf") {
+        return Some(sign | 0x1e << 122);
+    }
+    if unsigned.eq_ignore_ascii_case("nan") {
+        return Some(0x1f << 122);
+    }
+
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(index) => (
+            &unsigned[..index],
+            unsigned[index + 1..].parse::<i64>().ok()?,
+        ),
+        None => (unsigned, 0),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if whole.is_empty() && fraction.is_empty()
+        || !whole
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|byte| byte.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let mut digits = format!("{whole}{fraction}")
+        .trim_start_matches('0')
+        .to_string();
+    let mut exponent = exponent.checked_sub(fraction.len() as i64)?;
+    // trailing zeros move into the exponent while the coefficient is too long or
+    // the exponent too small
+    while digits.ends_with('0') && (digits.len() > DECIMAL_MAX_DIGITS || exponent < -DECIMAL_BIAS) {
+        digits.pop();
+        exponent += 1;
+    }
+    // and back out of it when the exponent is too large
+    while exponent > DECIMAL_MAX_EXPONENT && !digits.is_empty() && digits.len() < DECIMAL_MAX_DIGITS
+    {
+        digits.push('0');
+        exponent -= 1;
+    }
+    if digits.is_empty() {
+        exponent = exponent.clamp(-DECIMAL_BIAS, DECIMAL_MAX_EXPONENT);
+    }
+    if digits.len() > DECIMAL_MAX_DIGITS
+        || !(-DECIMAL_BIAS..=DECIMAL_MAX_EXPONENT).contains(&exponent)
+    {
+        return None;
+    }
+
+    let coefficient: u128 = if digits.is_empty() {
+        0
+    } else {
+        digits.parse().ok()?
+    };
+    Some(sign | ((exponent + DECIMAL_BIAS) as u128) << 113 | coefficient)
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut text = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let triple = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                text.push(BASE64[(triple >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let trimmed = text.trim_end_matches('=');
+    if text.len() - trimmed.len() > 2 {
+        return None;
+    }
+
+    let mut data = Vec::new();
+    let mut accumulator = 0u32;
+    let mut bits = 0;
+    for byte in trimmed.bytes() {
+        let value = BASE64.iter().position(|symbol| *symbol == byte)? as u32;
+        accumulator = accumulator << 6 | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            data.push((accumulator >> bits) as u8);
+            accumulator &= (1 << bits) - 1;
+        }
+    }
+    Some(data)
+}
+
+#[cfg(test)]
+pub mod bson_tests {
+    use super::*;
+    use crate::json::binary::binary_tests::hex;
+    use crate::json::syntactic_analyzer::parse_str;
+    use pretty_assertions::assert_eq;
+
+    fn json(text: &str) -> JsonValue {
+        parse_str(text).unwrap()
+    }
+
+    #[test]
+    pub fn test_bson_documents() {
+        // the examples of bsonspec.org
+        let hello = b"\x16\x00\x00\x00\x02hello\x00\x06\x00\x00\x00world\x00\x00";
+        assert_eq!(to_bson(&json(r#"{"hello": "world"}"#)).unwrap(), hello);
+        assert_eq!(
+            from_bson(hello, ExtendedJsonMode::Relaxed).unwrap(),
+            json(r#"{"hello": "world"}"#)
+        );
+
+        let awesome = b"\x31\x00\x00\x00\x04BSON\x00\x26\x00\x00\x00\x02\x30\x00\x08\x00\x00\x00awesome\x00\x01\x31\x00\x33\x33\x33\x33\x33\x33\x14\x40\x10\x32\x00\xc2\x07\x00\x00\x00\x00";
+        let value = json(r#"{"BSON": ["awesome", {"$numberDouble": "5.05"}, 1986]}"#);
+        assert_eq!(to_bson(&value).unwrap(), awesome);
+        assert_eq!(
+            from_bson(awesome, ExtendedJsonMode::Canonical).unwrap(),
+            json(r#"{"BSON": ["awesome", {"$numberDouble": "5.05"}, {"$numberInt": "1986"}]}"#)
+        );
+        assert_eq!(
+            from_bson(awesome, ExtendedJsonMode::Relaxed).unwrap(),
+            json(r#"{"BSON": ["awesome", 5.05, 1986]}"#)
+        );
+    }
+
+    #[test]
+    pub fn test_extended_json_modes() {
+        let canonical = json(
+            r#"{
+                "_id": {"$oid": "56e1fc72e0c917e9c4714161"},
+                "created": {"$date": {"$numberLong": "1356351330501"}},
+                "ancient": {"$date": {"$numberLong": "-284643869501"}},
+                "count": {"$numberLong": "42"},
+                "small": {"$numberInt": "-7"},
+                "ratio": {"$numberDouble": "0.5"},
+                "big": {"$numberDouble": "1.2345678921232E+18"},
+                "missing": {"$numberDouble": "-Infinity"},
+                "price": {"$numberDecimal": "12.50"},
+                "data": {"$binary": {"base64": "AQIDBA==", "subType": "80"}},
+                "legacy": {"$binary": {"base64": "//8=", "subType": "02"}},
+                "pattern": {"$regularExpression": {"pattern": "^a", "options": "i"}},
+                "clock": {"$timestamp": {"t": 1234, "i": 5}},
+                "code": {"$code": "x = 1"},
+                "scoped": {"$code": "x", "$scope": {"x": {"$numberInt": "1"}}},
+                "symbol": {"$symbol": "sym"},
+                "pointer": {"$dbPointer": {"$ref": "db.coll", "$id": {"$oid": "56e1fc72e0c917e9c4714161"}}},
+                "low": {"$minKey": 1},
+                "high": {"$maxKey": 1},
+                "nothing": {"$undefined": true},
+                "plain": {"list": [true, null, "text"]}
+            }"#,
+        );
+
+        let bytes = to_bson(&canonical).unwrap();
+        assert_eq!(
+            from_bson(&bytes, ExtendedJsonMode::Canonical).unwrap(),
+            canonical
+        );
+
+        let JsonValue::JsonObject(relaxed) = from_bson(&bytes, ExtendedJsonMode::Relaxed).unwrap()
+        else {
+            panic!("a document is an object");
+        };
+        let relaxed_member = |key| member(&relaxed, key).unwrap().clone();
+        assert_eq!(
+            relaxed_member("created"),
+            json(r#"{"$date": "2012-12-24T12:15:30.501Z"}"#)
+        );
+        assert_eq!(
+            relaxed_member("ancient"),
+            json(r#"{"$date": {"$numberLong": "-284643869501"}}"#)
+        );
+        assert_eq!(relaxed_member("count"), JsonValue::Number(42.0));
+        assert_eq!(relaxed_member("small"), JsonValue::Number(-7.0));
+        assert_eq!(relaxed_member("ratio"), JsonValue::Number(0.5));
+        assert_eq!(
+            relaxed_member("missing"),
+            json(r#"{"$numberDouble": "-Infinity"}"#)
+        );
+        assert_eq!(
+            relaxed_member("_id"),
+            json(r#"{"$oid": "56e1fc72e0c917e9c4714161"}"#)
+        );
+
+        // relaxed extended json reads back as the same BSON, but for the numbers
+        let relaxed = JsonValue::JsonObject(relaxed);
+        assert_eq!(
+            to_extended_json(&relaxed, ExtendedJsonMode::Relaxed).unwrap(),
+            relaxed
+        );
+        assert_eq!(
+            to_extended_json(
+                &json(r#"{"d": {"$date": "2012-12-24T13:15:30.501+01:00"}}"#),
+                ExtendedJsonMode::Canonical
+            )
+            .unwrap(),
+            json(r#"{"d": {"$date": {"$numberLong": "1356351330501"}}}"#)
+        );
+    }
+
+    #[test]
+    pub fn test_number_formats() {
+        assert_eq!(format_double(1.0), "1.0");
+        assert_eq!(format_double(-0.0), "-0.0");
+        assert_eq!(format_double(1.0001220703125), "1.0001220703125");
+        assert_eq!(format_double(1.2345678921232e18), "1.2345678921232E+18");
+        assert_eq!(format_double(-1.2345678921232e-18), "-1.2345678921232E-18");
+        assert_eq!(parse_double("inf"), None);
+
+        for decimal in [
+            "0",
+            "0.1",
+            "-1.00E-8",
+            "1E+3",
+            "1.234567890123456789012345678901234E+6144",
+            "Infinity",
+            "-Infinity",
+            "NaN",
+            "0.000001",
+            "1.5E-7",
+        ] {
+            assert_eq!(format_decimal(parse_decimal(decimal).unwrap()), decimal);
+        }
+        assert_eq!(
+            parse_decimal("0.1").unwrap().to_le_bytes(),
+            hex("01000000000000000000000000003E30").as_slice()
+        );
+        assert_eq!(format_decimal(parse_decimal("1000e-2").unwrap()), "10.00");
+        assert_eq!(parse_decimal("12345678901234567890123456789012345"), None);
+        assert_eq!(
+            parse_decimal("1e6112"),
+            Some(parse_decimal("10e6111").unwrap())
+        );
+        assert_eq!(parse_decimal("1.2.3"), None);
+
+        assert_eq!(base64_encode(b"BSON!"), "QlNPTiE=");
+        assert_eq!(base64_decode("QlNPTiE=").unwrap(), b"BSON!");
+        assert_eq!(base64_decode("QlNPTiE"), None);
+    }
+
+    #[test]
+    pub fn test_bson_errors() {
+        let mode = ExtendedJsonMode::Canonical;
+        assert_eq!(to_bson(&json("[1]")), Err(BsonError::NotADocument));
+        assert_eq!(
+            to_bson(&json(r#"{"a\u0000": 1}"#)),
+            Err(BsonError::KeyWithNul("a\0".to_string()))
+        );
+        assert_eq!(
+            to_bson(&json(r#"{"id": {"$oid": "xyz"}}"#)),
+            Err(BsonError::InvalidExtendedJson("$oid".to_string()))
+        );
+        assert_eq!(
+            to_bson(&json(r#"{"n": {"$numberLong": 12}}"#)),
+            Err(BsonError::InvalidExtendedJson("$numberLong".to_string()))
+        );
+        // other keys starting with a dollar make ordinary documents
+        assert!(to_bson(&json(r#"{"q": {"$gt": 1}}"#)).is_ok());
+
+        let errors = [
+            ("050000", BsonError::UnexpectedEnd(3)),
+            ("0400000000", BsonError::InvalidLength(4, 0)),
+            ("0600000000", BsonError::InvalidLength(6, 0)),
+            ("0500000001", BsonError::UnexpectedEnd(5)),
+            ("0800000020610000", BsonError::UnknownType(0x20, 4)),
+            ("090000000861000200", BsonError::InvalidBoolean(2, 4)),
+            ("0e00000002610002000000ff0000", BsonError::InvalidUtf8(7)),
+            ("0c0000000261000900000061", BsonError::InvalidLength(9, 7)),
+            ("050000000000", BsonError::TrailingBytes(1)),
+        ];
+        for (bytes, error) in errors {
+            assert_eq!(from_bson(&hex(bytes), mode), Err(error), "{bytes}");
+        }
+    }
+
+    // Nothing that long can be allocated here, so only the length check is tested
+    #[test]
+    pub fn test_encoded_lengths() {
+        let largest = i32::MAX as usize;
+        assert_eq!(length_at(largest, 4), Ok(i32::MAX));
+        assert_eq!(
+            length_at(largest + 1, 4),
+            Err(BsonError::InvalidLength(largest as i64 + 1, 4))
+        );
+    }
+
+    #[test]
+    pub fn test_decode_lengths() {
+        let mode = ExtendedJsonMode::Relaxed;
+        let errors = [
+            // documents longer than the data, shorter than their members, or negative
+            ("0a00000000", BsonError::InvalidLength(10, 0)),
+            ("ffffffff00", BsonError::InvalidLength(-1, 0)),
+            ("050000000861000100", BsonError::InvalidLength(5, 0)),
+            ("060000000000", BsonError::InvalidLength(6, 0)),
+            // an embedded document taking the terminator of its parent
+            ("0d000000036100060000000000", BsonError::InvalidLength(6, 7)),
+            // strings without room for their terminator, or not ending with it
+            ("0c000000026100000000000000", BsonError::InvalidLength(0, 7)),
+            (
+                "0e0000000261000200000061620000",
+                BsonError::InvalidLength(2, 7),
+            ),
+            (
+                "0e000000026100ffffffff00000000",
+                BsonError::InvalidLength(-1, 7),
+            ),
+            // an int32 cut short, though the document length matches the data
+            ("090000001061000100", BsonError::UnexpectedEnd(9)),
+            // a code with scope whose total length disagrees with its parts
+            (
+                "170000000f61000e000000020000007800050000000000",
+                BsonError::InvalidLength(14, 7),
+            ),
+        ];
+        for (bytes, error) in errors {
+            assert_eq!(from_bson(&hex(bytes), mode), Err(error), "{bytes}");
+        }
+    }
+
+    #[test]
+    pub fn test_decode_cstrings() {
+        let mode = ExtendedJsonMode::Relaxed;
+        // a key, then the options of a regular expression, missing their terminator
+        assert_eq!(
+            from_bson(&hex("0800000008616263"), mode),
+            Err(BsonError::UnexpectedEnd(8))
+        );
+        assert_eq!(
+            from_bson(&hex("0a0000000b6100610062"), mode),
+            Err(BsonError::UnexpectedEnd(10))
+        );
+        assert_eq!(
+            from_bson(&hex("0a00000008ff00000000"), mode),
+            Err(BsonError::InvalidUtf8(5))
+        );
+    }
+
+    #[test]
+    pub fn test_old_binary() {
+        let mode = ExtendedJsonMode::Canonical;
+        // the old subtype repeats the length of the data inside it
+        let bytes = hex("13000000056100060000000202000000ffff00");
+        let value = json(r#"{"a": {"$binary": {"base64": "//8=", "subType": "02"}}}"#);
+        assert_eq!(from_bson(&bytes, mode).unwrap(), value);
+        assert_eq!(to_bson(&value).unwrap(), bytes);
+
+        let errors = [
+            // the inner length disagrees with the outer one
+            (
+                "13000000056100060000000203000000ffff00",
+                BsonError::InvalidLength(6, 4),
+            ),
+            // the data is too short to hold an inner length
+            (
+                "0f0000000561000200000002ffff00",
+                BsonError::InvalidLength(2, 4),
+            ),
+        ];
+        for (bytes, error) in errors {
+            assert_eq!(from_bson(&hex(bytes), mode), Err(error), "{bytes}");
+        }
+    }
+
+    #[test]
+    pub fn test_depth_limit() {
+        // documents nested `levels` deep under the key "a"
+        let nested = |levels| {
+            let mut bytes = hex("0500000000");
+            for _ in 0..levels {
+                let mut body = vec![DOCUMENT, b'a', 0];
+                body.append(&mut bytes);
+                body.push(0);
+                bytes = ((body.len() + 4) as i32).to_le_bytes().to_vec();
+                bytes.append(&mut body);
+            }
+            bytes
+        };
+        let mode = ExtendedJsonMode::Relaxed;
+
+        // unoptimized, the decoder needs more stack at the limit than a test thread has
+        let at_limit = std::thread::Builder::new()
+            .stack_size(16 << 20)
+            .spawn(move || {
+                assert_eq!(
+                    from_bson(&nested(MAX_DEPTH + 1), mode),
+                    Err(BsonError::TooDeep(MAX_DEPTH))
+                );
+                let deepest = from_bson(&nested(MAX_DEPTH), mode).unwrap();
+                assert_eq!(to_bson(&deepest).unwrap(), nested(MAX_DEPTH));
+                let deeper = JsonValue::JsonObject(vec![JsonPair("a".to_string(), deepest)]);
+                assert_eq!(to_bson(&deeper), Err(BsonError::TooDeep(MAX_DEPTH)));
+            })
+            .unwrap();
+        at_limit.join().unwrap();
+    }
+}
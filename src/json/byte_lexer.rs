@@ -0,0 +1,733 @@
+use std::borrow::Cow;
+
+use super::events::{EventError, Position};
+use super::scanner::Lexeme;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NumberState {
+    Minus,
+    Zero,
+    Integer,
+    Dot,
+    Fraction,
+    Exponent,
+    ExponentSign,
+    ExponentDigits,
+}
+
+impl NumberState {
+    fn start(byte: u8) -> NumberState {
+        match byte {
+            b'-' => NumberState::Minus,
+            b'0' => NumberState::Zero,
+            _ => NumberState::Integer,
+        }
+    }
+
+    fn is_complete(self) -> bool {
+        matches!(
+            self,
+            NumberState::Zero
+                | NumberState::Integer
+                | NumberState::Fraction
+                | NumberState::ExponentDigits
+        )
+    }
+
+    fn next(self, byte: u8) -> Option<NumberState> {
+        match (self, byte) {
+            (NumberState::Minus, b'0') => Some(NumberState::Zero),
+            (NumberState::Minus, b'1'..=b'9') => Some(NumberState::Integer),
+            (NumberState::Integer, b'0'..=b'9') => Some(NumberState::Integer),
+            (NumberState::Zero | NumberState::Integer, b'.') => Some(NumberState::Dot),
+            (NumberState::Dot | NumberState::Fraction, b'0'..=b'9') => Some(NumberState::Fraction),
+            (NumberState::Zero | NumberState::Integer | NumberState::Fraction, b'e' | b'E') => {
+                Some(NumberState::Exponent)
+            }
+            (NumberState::Exponent, b'+' | b'-') => Some(NumberState::ExponentSign),
+            (
+                NumberState::Exponent | NumberState::ExponentSign | NumberState::ExponentDigits,
+                b'0'..=b'9',
+            ) => Some(NumberState::ExponentDigits),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Between,
+    String,
+    Escape,
+    // collecting the hex digits of \uXXXX, `high` is a pending high surrogate
+    Unicode {
+        value: u32,
+        digits: u8,
+        high: Option<u32>,
+    },
+    // after a high surrogate, waiting for the "\u" of the low one
+    LowSurrogateBackslash(u32),
+    LowSurrogateU(u32),
+    Number(NumberState),
+    Literal {
+        text: &'static [u8],
+        matched: usize,
+    },
+    // a '/' that has to start a comment
+    Slash,
+    LineComment,
+    BlockComment,
+    // a '*' inside a block comment, possibly closing it
+    BlockStar,
+    // an unexpected character of several bytes, read whole to be reported
+    Unexpected {
+        length: usize,
+    },
+}
+
+// The length of the UTF-8 sequence a byte starts, None when it cannot start one
+fn utf8_length(byte: u8) -> Option<usize> {
+    match byte {
+        0x00..=0x7f => Some(1),
+        0xc2..=0xdf => Some(2),
+        0xe0..=0xef => Some(3),
+        0xf0..=0xf4 => Some(4),
+        _ => None,
+    }
+}
+
+// A resumable lexer working on raw bytes. Input can be handed over in pieces of any
+// size: a lexeme split between two pieces (long strings, escapes, numbers, or a
+// multi-byte UTF-8 character) simply continues where the previous piece stopped.
+// Only the lexeme being read is buffered.
+#[derive(Debug, Clone)]
+pub struct ByteLexer {
+    state: State,
+    buffer: Vec<u8>,
+    start: Position,
+    escape_start: Position,
+    offset: usize,
+    line: usize,
+    line_start: usize,
+    comments: bool,
+    // the comments read since the last take_comments, when they are kept
+    kept_comments: Option<Vec<String>>,
+}
+
+impl Default for ByteLexer {
+    fn default() -> Self {
+        ByteLexer {
+            state: State::Between,
+            buffer: Vec::new(),
+            start: Position::default(),
+            escape_start: Position::default(),
+            offset: 0,
+            line: 1,
+            line_start: 0,
+            comments: false,
+            kept_comments: None,
+        }
+    }
+}
+
+type LexResult = Result<Option<(Lexeme<'static>, Position)>, EventError>;
+
+impl ByteLexer {
+    pub fn new() -> ByteLexer {
+        ByteLexer::default()
+    }
+
+    // Skips `// line` and `/* block */` comments between lexemes
+    pub fn with_comments() -> ByteLexer {
+        ByteLexer {
+            comments: true,
+            ..ByteLexer::default()
+        }
+    }
+
+    // Records the comments skipped from now on, to be collected with take_comments
+    pub fn keep_comments(mut self) -> ByteLexer {
+        self.kept_comments = Some(Vec::new());
+        self
+    }
+
+    // The comments skipped since the last call, delimiters included
+    pub fn take_comments(&mut self) -> Vec<String> {
+        self.kept_comments
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    pub fn position(&self) -> Position {
+        Position {
+            offset: self.offset,
+            line: self.line,
+            column: self.offset - self.line_start + 1,
+        }
+    }
+
+    // true when no lexeme is partially read
+    pub fn is_between_lexemes(&self) -> bool {
+        self.state == State::Between
+    }
+
+    fn advance(&mut self, byte: u8) {
+        self.offset += 1;
+        if byte == b'\n' {
+            self.line += 1;
+            self.line_start = self.offset;
+        }
+    }
+
+    fn emit(&mut self, lexeme: Lexeme<'static>) -> LexResult {
+        self.state = State::Between;
+        Ok(Some((lexeme, self.start)))
+    }
+
+    fn take_buffer(&mut self) -> Result<String, EventError> {
+        String::from_utf8(std::mem::take(&mut self.buffer))
+            .map_err(|_| EventError::InvalidUtf8(self.start))
+    }
+
+    // Reads from `input` until a lexeme is complete or the input is used up.
+    // Returns how many bytes were consumed, the rest has to be passed in again.
+    pub fn next_lexeme(&mut self, input: &[u8]) -> (usize, LexResult) {
+        let mut index = 0;
+        while index < input.len() {
+            let byte = input[index];
+            let position = self.position();
+
+            match self.state {
+                State::Between => match byte {
+                    b' ' | b'\t' | b'\r' | b'\n' => {
+                        self.advance(byte);
+                        index += 1;
+                    }
+                    b'{' | b'}' | b'[' | b']' | b':' | b',' => {
+                        let lexeme = match byte {
+                            b'{' => Lexeme::LeftBrace,
+                            b'}' => Lexeme::RightBrace,
+                            b'[' => Lexeme::LeftBracket,
+                            b']' => Lexeme::RightBracket,
+                            b':' => Lexeme::Colon,
+                            _ => Lexeme::Comma,
+                        };
+                        self.advance(byte);
+                        return (index + 1, Ok(Some((lexeme, position))));
+                    }
+                    b'"' => {
+                        self.start = position;
+                        self.buffer.clear();
+                        self.state = State::String;
+                        self.advance(byte);
+                        index += 1;
+                    }
+                    b'-' | b'0'..=b'9' => {
+                        self.start = position;
+                        self.buffer.clear();
+                        self.buffer.push(byte);
+                        self.state = State::Number(NumberState::start(byte));
+                        self.advance(byte);
+                        index += 1;
+                    }
+                    b'/' if self.comments => {
+                        self.start = position;
+                        self.buffer.clear();
+                        self.buffer.push(byte);
+                        self.state = State::Slash;
+                        self.advance(byte);
+                        index += 1;
+                    }
+                    b't' | b'f' | b'n' => {
+                        let text: &'static [u8] = match byte {
+                            b't' => b"true",
+                            b'f' => b"false",
+                            _ => b"null",
+                        };
+                        self.start = position;
+                        self.state = State::Literal { text, matched: 1 };
+                        self.advance(byte);
+                        index += 1;
+                    }
+                    _ => match utf8_length(byte) {
+                        Some(1) => {
+                            let error = EventError::UnexpectedCharacter(byte as char, position);
+                            return (index, Err(error));
+                        }
+                        // the character is reported as the scanner does, once it is whole
+                        Some(length) => {
+                            self.start = position;
+                            self.buffer.clear();
+                            self.buffer.push(byte);
+                            self.state = State::Unexpected { length };
+                            self.advance(byte);
+                            index += 1;
+                        }
+                        None => return (index, Err(EventError::InvalidUtf8(position))),
+                    },
+                },
+                State::Unexpected { length } => {
+                    self.buffer.push(byte);
+                    self.advance(byte);
+                    index += 1;
+                    if self.buffer.len() < length {
+                        continue;
+                    }
+
+                    let decoded = std::str::from_utf8(&self.buffer).ok();
+                    let error = match decoded.and_then(|text| text.chars().next()) {
+                        Some(ch) => EventError::UnexpectedCharacter(ch, self.start),
+                        None => EventError::InvalidUtf8(self.start),
+                    };
+                    return (index, Err(error));
+                }
+                State::String => {
+                    // copy everything up to the next quote, escape or control character at once
+                    let chunk_end = input[index..]
+                        .iter()
+                        .position(|byte| *byte == b'"' || *byte == b'\\' || *byte < 0x20)
+                        .map_or(input.len(), |found| index + found);
+                    self.buffer.extend_from_slice(&input[index..chunk_end]);
+                    self.offset += chunk_end - index;
+                    index = chunk_end;
+
+                    let Some(&byte) = input.get(index) else {
+                        break;
+                    };
+                    let position = self.position();
+                    self.advance(byte);
+                    index += 1;
+
+                    match byte {
+                        b'"' => {
+                            return match self.take_buffer() {
+                                Ok(string) => {
+                                    (index, self.emit(Lexeme::String(Cow::Owned(string))))
+                                }
+                                Err(error) => (index, Err(error)),
+                            };
+                        }
+                        b'\\' => {
+                            self.escape_start = position;
+                            self.state = State::Escape;
+                        }
+                        _ => return (index, Err(EventError::InvalidString(position))),
+                    }
+                }
+                State::Escape => {
+                    let escaped = match byte {
+                        b'"' => Some(b'"'),
+                        b'\\' => Some(b'\\'),
+                        b'/' => Some(b'/'),
+                        b'b' => Some(0x08),
+                        b'f' => Some(0x0c),
+                        b'n' => Some(b'\n'),
+                        b'r' => Some(b'\r'),
+                        b't' => Some(b'\t'),
+                        b'u' => None,
+                        _ => return (index, Err(EventError::InvalidEscape(self.escape_start))),
+                    };
+
+                    self.state = match escaped {
+                        Some(escaped) => {
+                            self.buffer.push(escaped);
+                            State::String
+                        }
+                        None => State::Unicode {
+                            value: 0,
+                            digits: 0,
+                            high: None,
+                        },
+                    };
+                    self.advance(byte);
+                    index += 1;
+                }
+                State::Unicode {
+                    value,
+                    digits,
+                    high,
+                } => {
+                    let Some(digit) = (byte as char).to_digit(16) else {
+                        return (index, Err(EventError::InvalidEscape(self.escape_start)));
+                    };
+                    self.advance(byte);
+                    index += 1;
+
+                    let value = value * 16 + digit;
+                    if digits < 3 {
+                        self.state = State::Unicode {
+                            value,
+                            digits: digits + 1,
+                            high,
+                        };
+                        continue;
+                    }
+
+                    let code = match (high, value) {
+                        (None, 0xD800..=0xDBFF) => {
+                            self.state = State::LowSurrogateBackslash(value);
+                            continue;
+                        }
+                        (None, 0xDC00..=0xDFFF) => None,
+                        (None, code) => Some(code),
+                        (Some(high), 0xDC00..=0xDFFF) => {
+                            Some(0x10000 + ((high - 0xD800) << 10) + (value - 0xDC00))
+                        }
+                        (Some(_), _) => None,
+                    };
+
+                    let Some(ch) = code.and_then(char::from_u32) else {
+                        return (index, Err(EventError::InvalidEscape(self.escape_start)));
+                    };
+                    let mut encoded = [0; 4];
+                    self.buffer
+                        .extend_from_slice(ch.encode_utf8(&mut encoded).as_bytes());
+                    self.state = State::String;
+                }
+                State::LowSurrogateBackslash(high) => {
+                    if byte != b'\\' {
+                        return (index, Err(EventError::InvalidEscape(self.escape_start)));
+                    }
+                    self.state = State::LowSurrogateU(high);
+                    self.advance(byte);
+                    index += 1;
+                }
+                State::LowSurrogateU(high) => {
+                    if byte != b'u' {
+                        return (index, Err(EventError::InvalidEscape(self.escape_start)));
+                    }
+                    self.state = State::Unicode {
+                        value: 0,
+                        digits: 0,
+                        high: Some(high),
+                    };
+                    self.advance(byte);
+                    index += 1;
+                }
+                State::Number(number_state) => match number_state.next(byte) {
+                    Some(next) => {
+                        self.buffer.push(byte);
+                        self.state = State::Number(next);
+                        self.advance(byte);
+                        index += 1;
+                    }
+                    // the byte after a number belongs to the next lexeme, it is not consumed
+                    None => return (index, self.end_number(number_state, byte.is_ascii_digit())),
+                },
+                State::Literal { text, matched } => {
+                    if byte != text[matched] {
+                        return (index, Err(EventError::InvalidLiteral(self.start)));
+                    }
+                    self.advance(byte);
+                    index += 1;
+
+                    if matched + 1 < text.len() {
+                        self.state = State::Literal {
+                            text,
+                            matched: matched + 1,
+                        };
+                        continue;
+                    }
+
+                    let lexeme = match text {
+                        b"true" => Lexeme::True,
+                        b"false" => Lexeme::False,
+                        _ => Lexeme::Null,
+                    };
+                    return (index, self.emit(lexeme));
+                }
+                State::Slash => {
+                    self.state = match byte {
+                        b'/' => State::LineComment,
+                        b'*' => State::BlockComment,
+                        _ => return (index, Err(EventError::UnexpectedCharacter('/', self.start))),
+                    };
+                    self.buffer.push(byte);
+                    self.advance(byte);
+                    index += 1;
+                }
+                // the line break ending a line comment is read as whitespace
+                State::LineComment if byte == b'\n' => {
+                    if let Err(error) = self.end_comment() {
+                        return (index, Err(error));
+                    }
+                }
+                State::LineComment | State::BlockComment | State::BlockStar => {
+                    self.state = match (self.state, byte) {
+                        (State::BlockStar, b'/') => State::Between,
+                        (State::LineComment, _) => State::LineComment,
+                        (_, b'*') => State::BlockStar,
+                        _ => State::BlockComment,
+                    };
+                    self.buffer.push(byte);
+                    self.advance(byte);
+                    index += 1;
+
+                    if self.state == State::Between {
+                        if let Err(error) = self.end_comment() {
+                            return (index, Err(error));
+                        }
+                    }
+                }
+            }
+        }
+
+        (index, Ok(None))
+    }
+
+    fn end_comment(&mut self) -> Result<(), EventError> {
+        self.state = State::Between;
+        // a line comment ending with \r\n
+        if self.buffer.last() == Some(&b'\r') {
+            self.buffer.pop();
+        }
+        let comment = self.take_buffer()?;
+        if let Some(comments) = self.kept_comments.as_mut() {
+            comments.push(comment);
+        }
+        Ok(())
+    }
+
+    // Called once the input is exhausted, flushes a trailing number
+    pub fn finish(&mut self) -> LexResult {
+        match self.state {
+            State::Between => Ok(None),
+            State::Number(number_state) => self.end_number(number_state, false),
+            State::Literal { .. } => Err(EventError::InvalidLiteral(self.start)),
+            State::Slash => Err(EventError::UnexpectedCharacter('/', self.start)),
+            State::Unexpected { .. } => Err(EventError::InvalidUtf8(self.start)),
+            State::LineComment => self.end_comment().map(|_| None),
+            State::BlockComment | State::BlockStar => {
+                Err(EventError::UnterminatedComment(self.start))
+            }
+            _ => Err(EventError::UnexpectedEnd(self.position())),
+        }
+    }
+
+    fn end_number(&mut self, number_state: NumberState, followed_by_digit: bool) -> LexResult {
+        // a digit right after a complete number means a leading zero, e.g. 01
+        if !number_state.is_complete() || followed_by_digit {
+            self.state = State::Between;
+            return Err(EventError::InvalidNumber(self.start));
+        }
+
+        let number = self.take_buffer()?;
+        self.emit(Lexeme::Number(Cow::Owned(number)))
+    }
+}
+
+#[cfg(test)]
+pub mod byte_lexer_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    // Hands the input over in pieces of `size` bytes
+    fn lex_pieces(
+        lexer: &mut ByteLexer,
+        input: &[u8],
+        size: usize,
+    ) -> Result<Vec<(Lexeme<'static>, Position)>, EventError> {
+        let mut lexemes = Vec::new();
+        for piece in input.chunks(size) {
+            let mut rest = piece;
+            while !rest.is_empty() {
+                let (consumed, result) = lexer.next_lexeme(rest);
+                rest = &rest[consumed..];
+                lexemes.extend(result?);
+            }
+        }
+        lexemes.extend(lexer.finish()?);
+        Ok(lexemes)
+    }
+
+    fn lex(input: &str, size: usize) -> Result<Vec<Lexeme<'static>>, EventError> {
+        let lexemes = lex_pieces(&mut ByteLexer::new(), input.as_bytes(), size)?;
+        Ok(lexemes.into_iter().map(|(lexeme, _)| lexeme).collect())
+    }
+
+    fn position(offset: usize, line: usize, column: usize) -> Position {
+        Position {
+            offset,
+            line,
+            column,
+        }
+    }
+
+    fn owned(text: &str) -> Cow<'static, str> {
+        Cow::Owned(text.to_string())
+    }
+
+    #[test]
+    pub fn test_lexemes_in_pieces() {
+        let input = "{\"é\\n\": [0, -1.5E+3, true],\n \"\\ud83d\\ude00\": [false, null]}";
+        let expected = vec![
+            Lexeme::LeftBrace,
+            Lexeme::String(owned("é\n")),
+            Lexeme::Colon,
+            Lexeme::LeftBracket,
+            Lexeme::Number(owned("0")),
+            Lexeme::Comma,
+            Lexeme::Number(owned("-1.5E+3")),
+            Lexeme::Comma,
+            Lexeme::True,
+            Lexeme::RightBracket,
+            Lexeme::Comma,
+            Lexeme::String(owned("😀")),
+            Lexeme::Colon,
+            Lexeme::LeftBracket,
+            Lexeme::False,
+            Lexeme::Comma,
+            Lexeme::Null,
+            Lexeme::RightBracket,
+            Lexeme::RightBrace,
+        ];
+
+        for size in [1, 2, 3, 5, input.len()] {
+            assert_eq!(lex(input, size).unwrap(), expected, "pieces of {size}");
+        }
+    }
+
+    #[test]
+    pub fn test_positions() {
+        let mut lexer = ByteLexer::new();
+        let lexemes = lex_pieces(&mut lexer, "[\"é\",\n  12]".as_bytes(), 1).unwrap();
+        let positions: Vec<Position> = lexemes.into_iter().map(|(_, position)| position).collect();
+
+        assert_eq!(
+            positions,
+            vec![
+                position(0, 1, 1),
+                position(1, 1, 2),
+                position(5, 1, 6),
+                position(9, 2, 3),
+                position(11, 2, 5),
+            ]
+        );
+        assert_eq!(lexer.position(), position(12, 2, 6));
+        assert!(lexer.is_between_lexemes());
+    }
+
+    #[test]
+    pub fn test_numbers() {
+        // a number only ends with the byte after it, or with the input
+        let mut lexer = ByteLexer::new();
+        assert_eq!(lexer.next_lexeme(b"12"), (2, Ok(None)));
+        assert!(!lexer.is_between_lexemes());
+        assert_eq!(
+            lexer.next_lexeme(b"3]"),
+            (
+                1,
+                Ok(Some((Lexeme::Number(owned("123")), position(0, 1, 1))))
+            )
+        );
+        assert_eq!(lex("42", 1).unwrap(), vec![Lexeme::Number(owned("42"))]);
+
+        for input in ["01", "1.", "-", "1e", "1e+", "-a"] {
+            assert!(
+                matches!(lex(input, 1), Err(EventError::InvalidNumber(_))),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_errors() {
+        for (input, expected) in [
+            (
+                "[x]",
+                EventError::UnexpectedCharacter('x', position(1, 1, 2)),
+            ),
+            ("[tru]", EventError::InvalidLiteral(position(1, 1, 2))),
+            ("[nul", EventError::InvalidLiteral(position(1, 1, 2))),
+            (r#"["\x"]"#, EventError::InvalidEscape(position(2, 1, 3))),
+            (
+                r#"["\udc00"]"#,
+                EventError::InvalidEscape(position(2, 1, 3)),
+            ),
+            (
+                r#"["\ud800A"]"#,
+                EventError::InvalidEscape(position(2, 1, 3)),
+            ),
+            ("[\"\t\"]", EventError::InvalidString(position(2, 1, 3))),
+            ("\"open", EventError::UnexpectedEnd(position(5, 1, 6))),
+            (
+                "[1] /",
+                EventError::UnexpectedCharacter('/', position(4, 1, 5)),
+            ),
+        ] {
+            for size in [1, 4] {
+                assert_eq!(lex(input, size), Err(expected.clone()), "{input}");
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_unexpected_characters_are_decoded() {
+        // the scanner reports the character itself, so does the byte lexer even when
+        // its bytes come in different pieces
+        for (input, ch) in [("[\n é]", 'é'), ("[✓]", '✓'), ("😀", '😀')] {
+            let offset = input.find(ch).unwrap();
+            let line_start = input[..offset].rfind('\n').map_or(0, |n| n + 1);
+            let line = input[..offset].matches('\n').count() + 1;
+            let expected = position(offset, line, offset - line_start + 1);
+
+            for size in [1, 2, 3] {
+                assert_eq!(
+                    lex(input, size),
+                    Err(EventError::UnexpectedCharacter(ch, expected)),
+                    "{input}"
+                );
+            }
+        }
+
+        let bytes = |input: &[u8]| lex_pieces(&mut ByteLexer::new(), input, 1);
+        // a byte that cannot start a character, an invalid continuation, a cut character
+        assert_eq!(
+            bytes(b"[\xff]"),
+            Err(EventError::InvalidUtf8(position(1, 1, 2)))
+        );
+        assert_eq!(
+            bytes(b"[\xc3]]"),
+            Err(EventError::InvalidUtf8(position(1, 1, 2)))
+        );
+        assert_eq!(
+            bytes(b"[\xe2\x9c"),
+            Err(EventError::InvalidUtf8(position(1, 1, 2)))
+        );
+        assert_eq!(
+            bytes(b"[\"\xff\"]"),
+            Err(EventError::InvalidUtf8(position(1, 1, 2)))
+        );
+    }
+
+    #[test]
+    pub fn test_comments() {
+        let input = b"// head\r\n[1, /* two\n*/ 2] /**/";
+
+        let mut plain = ByteLexer::new();
+        assert!(matches!(
+            lex_pieces(&mut plain, input, 4),
+            Err(EventError::UnexpectedCharacter('/', _))
+        ));
+
+        for size in [1, 2, 7] {
+            let mut lexer = ByteLexer::with_comments().keep_comments();
+            let lexemes = lex_pieces(&mut lexer, input, size).unwrap();
+            assert_eq!(lexemes.len(), 5);
+            assert_eq!(lexemes[3], (Lexeme::Number(owned("2")), position(23, 3, 4)));
+            assert_eq!(lexer.take_comments(), vec!["// head", "/* two\n*/", "/**/"]);
+            assert!(lexer.take_comments().is_empty());
+        }
+
+        // comments are skipped but not kept without keep_comments
+        let mut lexer = ByteLexer::with_comments();
+        assert_eq!(lex_pieces(&mut lexer, input, 3).unwrap().len(), 5);
+        assert!(lexer.take_comments().is_empty());
+
+        assert_eq!(
+            lex_pieces(&mut ByteLexer::with_comments(), b"[] /* open *", 2),
+            Err(EventError::UnterminatedComment(position(3, 1, 4)))
+        );
+    }
+}
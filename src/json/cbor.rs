@@ -0,0 +1,508 @@
+use thiserror::Error;
+
+use super::binary::{is_integral, Cursor, UnexpectedEnd};
+use super::syntactic_analyzer::{JsonPair, JsonValue};
+
+pub use super::binary::MAX_DEPTH;
+
+// Byte offsets point at the start of the offending item
+#[derive(Debug, Clone, Error, PartialEq)]
+pub enum CborError {
+    #[error("Unexpected end of the CBOR data at byte {0}")]
+    UnexpectedEnd(usize),
+
+    #[error("Invalid initial byte 0x{0:02x} at byte {1}")]
+    InvalidHeader(u8, usize),
+
+    #[error("Byte strings have no json representation (byte {0})")]
+    ByteString(usize),
+
+    #[error("Tag {0} has no json representation (byte {1})")]
+    Tag(u64, usize),
+
+    #[error("Undefined has no json representation (byte {0})")]
+    Undefined(usize),
+
+    #[error("Simple value {0} has no json representation (byte {1})")]
+    SimpleValue(u8, usize),
+
+    #[error("Map keys must be text strings (byte {0})")]
+    NonStringKey(usize),
+
+    #[error("Invalid UTF-8 in the text string at byte {0}")]
+    InvalidUtf8(usize),
+
+    #[error("Items are nested deeper than {0} levels")]
+    TooDeep(usize),
+
+    #[error("{0} bytes follow the CBOR item")]
+    TrailingBytes(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CborOptions {
+    // the deterministic encoding of RFC 8949 section 4.2: map keys sorted by their
+    // encoded bytes and floats in the shortest form that keeps their value
+    pub canonical: bool,
+}
+
+const FALSE: u8 = 0xf4;
+const TRUE: u8 = 0xf5;
+const NULL: u8 = 0xf6;
+const UNDEFINED: u8 = 0xf7;
+const FLOAT16: u8 = 0xf9;
+const FLOAT32: u8 = 0xfa;
+const FLOAT64: u8 = 0xfb;
+const BREAK: u8 = 0xff;
+
+// Encodes a value as CBOR (RFC 8949). Integral numbers become integers in their
+// shortest form, the other numbers floats. Lengths are always definite.
+pub fn to_cbor(value: &JsonValue, options: CborOptions) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode(&mut out, value, options);
+    out
+}
+
+fn encode(out: &mut Vec<u8>, value: &JsonValue, options: CborOptions) {
+    match value {
+        JsonValue::Null => out.push(NULL),
+        JsonValue::Boolean(boolean) => out.push(if *boolean { TRUE } else { FALSE }),
+        JsonValue::Number(number) => encode_number(out, *number, options),
+        JsonValue::String(string) => encode_text(out, string),
+        JsonValue::JsonArray(elements) => {
+            header(out, 4, elements.len() as u64);
+            for element in elements {
+                encode(out, element, options);
+            }
+        }
+        JsonValue::JsonObject(members) => {
+            header(out, 5, members.len() as u64);
+            if options.canonical {
+                let mut entries: Vec<(Vec<u8>, &JsonValue)> = members
+                    .iter()
+                    .map(|JsonPair(key, member)| {
+                        let mut encoded = Vec::new();
+                        encode_text(&mut encoded, key);
+                        (encoded, member)
+                    })
+                    .collect();
+                entries.sort_by(|left, right| left.0.cmp(&right.0));
+                for (key, member) in entries {
+                    out.extend_from_slice(&key);
+                    encode(out, member, options);
+                }
+            } else {
+                for JsonPair(key, member) in members {
+                    encode_text(out, key);
+                    encode(out, member, options);
+                }
+            }
+        }
+    }
+}
+
+fn encode_text(out: &mut Vec<u8>, text: &str) {
+    header(out, 3, text.len() as u64);
+    out.extend_from_slice(text.as_bytes());
+}
+
+fn encode_number(out: &mut Vec<u8>, number: f32, options: CborOptions) {
+    // 2^64, the first value out of the range of the integer arguments
+    const LIMIT: f64 = 18446744073709551616.0;
+
+    let wide = number as f64;
+    let integral = is_integral(number);
+    if integral && (0.0..LIMIT).contains(&wide) {
+        return header(out, 0, wide as u64);
+    }
+    if integral && (-LIMIT..0.0).contains(&wide) {
+        return header(out, 1, (-1.0 - wide) as u64);
+    }
+
+    match options.canonical.then(|| to_half(number)).flatten() {
+        Some(half) => {
+            out.push(FLOAT16);
+            out.extend_from_slice(&half.to_be_bytes());
+        }
+        None => {
+            out.push(FLOAT32);
+            out.extend_from_slice(&number.to_bits().to_be_bytes());
+        }
+    }
+}
+
+// The initial byte of an item and its argument, in the fewest bytes
+fn header(out: &mut Vec<u8>, major: u8, argument: u64) {
+    let major = major << 5;
+    match argument {
+        0..=23 => out.push(major | argument as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, argument as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(argument as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(argument as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&argument.to_be_bytes());
+        }
+    }
+}
+
+// The half precision float of the same value, if there is one. NaN becomes the
+// canonical quiet NaN.
+fn to_half(number: f32) -> Option<u16> {
+    let bits = number.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    if number.is_nan() {
+        return Some(0x7e00);
+    }
+    if number.is_infinite() {
+        return Some(sign | 0x7c00);
+    }
+    if number == 0.0 {
+        return Some(sign);
+    }
+    if exponent == 0 {
+        return None;
+    }
+
+    let exponent = exponent - 127;
+    match exponent {
+        -14..=15 if mantissa & 0x1fff == 0 => {
+            Some(sign | (((exponent + 15) as u16) << 10) | (mantissa >> 13) as u16)
+        }
+        -24..=-15 => {
+            // a subnormal half float counts units of 2^-24
+            let significand = 0x80_0000 | mantissa;
+            let shift = -(exponent + 1);
+            (significand & ((1 << shift) - 1) == 0).then(|| sign | (significand >> shift) as u16)
+        }
+        _ => None,
+    }
+}
+
+fn from_half(half: u16) -> f32 {
+    let sign = if half & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        31 if mantissa == 0.0 => sign * f32::INFINITY,
+        31 => f32::NAN,
+        _ => sign * (1024.0 + mantissa) * 2f32.powi(exponent - 25),
+    }
+}
+
+// Decodes one CBOR item. Integers and floats of every width become numbers, byte
+// strings, tags and the simple values other than false, true and null are refused.
+pub fn from_cbor(bytes: &[u8]) -> Result<JsonValue, CborError> {
+    let mut decoder = Decoder {
+        cursor: Cursor::new(bytes, 0),
+    };
+    let value = decoder.item(0)?;
+    match decoder.cursor.remaining() {
+        0 => Ok(value),
+        trailing => Err(CborError::TrailingBytes(trailing)),
+    }
+}
+
+impl From<UnexpectedEnd> for CborError {
+    fn from(UnexpectedEnd(offset): UnexpectedEnd) -> Self {
+        CborError::UnexpectedEnd(offset)
+    }
+}
+
+struct Decoder<'a> {
+    cursor: Cursor<'a>,
+}
+
+impl<'a> Decoder<'a> {
+    // Lengths past the range of usize cannot fit in the data either
+    fn take(&mut self, length: u64) -> Result<&'a [u8], CborError> {
+        let length = usize::try_from(length).unwrap_or(usize::MAX);
+        Ok(self.cursor.take(length)?)
+    }
+
+    // Consumes the break ending an indefinite length item, if it comes next
+    fn at_break(&mut self) -> Result<bool, CborError> {
+        let is_break = self.cursor.peek()? == BREAK;
+        if is_break {
+            self.cursor.offset += 1;
+        }
+        Ok(is_break)
+    }
+
+    fn argument(&mut self, initial: u8, start: usize) -> Result<u64, CborError> {
+        let width = match initial & 0x1f {
+            info @ 0..=23 => return Ok(info as u64),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => return Err(CborError::InvalidHeader(initial, start)),
+        };
+        let bytes = self.take(width)?;
+        Ok(bytes
+            .iter()
+            .fold(0, |argument, byte| (argument << 8) | *byte as u64))
+    }
+
+    // None for an indefinite length
+    fn length(&mut self, initial: u8, start: usize) -> Result<Option<u64>, CborError> {
+        if initial & 0x1f == 31 {
+            return Ok(None);
+        }
+        self.argument(initial, start).map(Some)
+    }
+
+    fn item(&mut self, depth: usize) -> Result<JsonValue, CborError> {
+        if depth > MAX_DEPTH {
+            return Err(CborError::TooDeep(MAX_DEPTH));
+        }
+
+        let start = self.cursor.offset;
+        let initial = self.cursor.byte()?;
+        match initial >> 5 {
+            0 => Ok(JsonValue::Number(self.argument(initial, start)? as f32)),
+            1 => Ok(JsonValue::Number(
+                -1.0 - self.argument(initial, start)? as f32,
+            )),
+            2 => Err(CborError::ByteString(start)),
+            3 => self.text(initial, start).map(JsonValue::String),
+            4 => {
+                let mut elements = Vec::new();
+                match self.length(initial, start)? {
+                    Some(count) => {
+                        for _ in 0..count {
+                            elements.push(self.item(depth + 1)?);
+                        }
+                    }
+                    None => {
+                        while !self.at_break()? {
+                            elements.push(self.item(depth + 1)?);
+                        }
+                    }
+                }
+                Ok(JsonValue::JsonArray(elements))
+            }
+            5 => {
+                let mut members = Vec::new();
+                match self.length(initial, start)? {
+                    Some(count) => {
+                        for _ in 0..count {
+                            members.push(self.member(depth + 1)?);
+                        }
+                    }
+                    None => {
+                        while !self.at_break()? {
+                            members.push(self.member(depth + 1)?);
+                        }
+                    }
+                }
+                Ok(JsonValue::JsonObject(members))
+            }
+            6 => Err(CborError::Tag(self.argument(initial, start)?, start)),
+            _ => self.simple(initial, start),
+        }
+    }
+
+    fn member(&mut self, depth: usize) -> Result<JsonPair, CborError> {
+        let start = self.cursor.offset;
+        let initial = self.cursor.byte()?;
+        if initial >> 5 != 3 {
+            return Err(CborError::NonStringKey(start));
+        }
+        let key = self.text(initial, start)?;
+        Ok(JsonPair(key, self.item(depth)?))
+    }
+
+    // A text string, made of definite length chunks when its length is indefinite
+    fn text(&mut self, initial: u8, start: usize) -> Result<String, CborError> {
+        let Some(length) = self.length(initial, start)? else {
+            let mut text = String::new();
+            while !self.at_break()? {
+                let chunk_start = self.cursor.offset;
+                let chunk = self.cursor.byte()?;
+                if chunk >> 5 != 3 || chunk & 0x1f == 31 {
+                    return Err(CborError::InvalidHeader(chunk, chunk_start));
+                }
+                text.push_str(&self.text(chunk, chunk_start)?);
+            }
+            return Ok(text);
+        };
+
+        let bytes = self.take(length)?;
+        std::str::from_utf8(bytes)
+            .map(str::to_string)
+            .map_err(|_| CborError::InvalidUtf8(start))
+    }
+
+    fn simple(&mut self, initial: u8, start: usize) -> Result<JsonValue, CborError> {
+        match initial {
+            FALSE => Ok(JsonValue::Boolean(false)),
+            TRUE => Ok(JsonValue::Boolean(true)),
+            NULL => Ok(JsonValue::Null),
+            UNDEFINED => Err(CborError::Undefined(start)),
+            FLOAT16 => {
+                let bits = u16::from_be_bytes(self.cursor.fixed()?);
+                Ok(JsonValue::Number(from_half(bits)))
+            }
+            FLOAT32 => Ok(JsonValue::Number(f32::from_be_bytes(self.cursor.fixed()?))),
+            FLOAT64 => {
+                let number = f64::from_be_bytes(self.cursor.fixed()?);
+                Ok(JsonValue::Number(number as f32))
+            }
+            0xf8 => Err(CborError::SimpleValue(self.cursor.byte()?, start)),
+            0xe0..=0xf3 => Err(CborError::SimpleValue(initial & 0x1f, start)),
+            _ => Err(CborError::InvalidHeader(initial, start)),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod cbor_tests {
+    use super::*;
+    use crate::json::binary::binary_tests::hex;
+    use crate::json::syntactic_analyzer::parse_str;
+    use pretty_assertions::assert_eq;
+
+    fn canonical(json: &str) -> Vec<u8> {
+        to_cbor(&parse_str(json).unwrap(), CborOptions { canonical: true })
+    }
+
+    // The examples of RFC 8949 appendix A that json can represent
+    #[test]
+    pub fn test_rfc_examples() {
+        let examples = [
+            ("0", "00"),
+            ("23", "17"),
+            ("24", "1818"),
+            ("100", "1864"),
+            ("1000", "1903e8"),
+            ("1000000", "1a000f4240"),
+            ("-1", "20"),
+            ("-100", "3863"),
+            ("-1000", "3903e7"),
+            ("1.5", "f93e00"),
+            ("65504", "19ffe0"),
+            ("5.960464477539063e-8", "f90001"),
+            ("0.00006103515625", "f90400"),
+            ("-4.1", "fac0833333"),
+            ("false", "f4"),
+            ("true", "f5"),
+            ("null", "f6"),
+            (r#""""#, "60"),
+            (r#""IETF""#, "6449455446"),
+            (r#""ü""#, "62c3bc"),
+            ("[]", "80"),
+            ("[1, [2, 3], [4, 5]]", "8301820203820405"),
+            ("{}", "a0"),
+            (r#"{"a": 1, "b": [2, 3]}"#, "a26161016162820203"),
+        ];
+
+        for (json, cbor) in examples {
+            assert_eq!(canonical(json), hex(cbor), "{json}");
+            assert_eq!(from_cbor(&hex(cbor)).unwrap(), parse_str(json).unwrap());
+        }
+
+        assert_eq!(
+            to_cbor(
+                &JsonValue::Number(f32::INFINITY),
+                CborOptions { canonical: true }
+            ),
+            hex("f97c00")
+        );
+        assert_eq!(
+            to_cbor(
+                &JsonValue::Number(f32::NAN),
+                CborOptions { canonical: true }
+            ),
+            hex("f97e00")
+        );
+    }
+
+    #[test]
+    pub fn test_canonical_encoding() {
+        let value = parse_str(r#"{"b": 1.5, "aa": -0.0, "a": 2}"#).unwrap();
+
+        assert_eq!(
+            to_cbor(&value, CborOptions::default()),
+            hex("a36162fa3fc00000626161fa80000000616102")
+        );
+        // "a" < "b" < "aa" once the length is encoded in front of the key
+        assert_eq!(
+            to_cbor(&value, CborOptions { canonical: true }),
+            hex("a36161026162f93e00626161f98000")
+        );
+        assert_eq!(
+            from_cbor(&to_cbor(&value, CborOptions { canonical: true })).unwrap(),
+            parse_str(r#"{"a": 2, "b": 1.5, "aa": -0.0}"#).unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_decode() {
+        // indefinite lengths
+        assert_eq!(
+            from_cbor(&hex("7f657374726561646d696e67ff")).unwrap(),
+            JsonValue::String("streaming".to_string())
+        );
+        assert_eq!(
+            from_cbor(&hex("9f018202039f0405ffff")).unwrap(),
+            parse_str("[1, [2, 3], [4, 5]]").unwrap()
+        );
+        assert_eq!(
+            from_cbor(&hex("bf61610161629f0203ffff")).unwrap(),
+            parse_str(r#"{"a": 1, "b": [2, 3]}"#).unwrap()
+        );
+
+        // wider encodings than needed
+        assert_eq!(
+            from_cbor(&hex("1b000000e8d4a51000")).unwrap(),
+            JsonValue::Number(1e12)
+        );
+        assert_eq!(
+            from_cbor(&hex("fb3ff199999999999a")).unwrap(),
+            JsonValue::Number(1.1)
+        );
+        assert_eq!(
+            from_cbor(&hex("f97bff")).unwrap(),
+            JsonValue::Number(65504.0)
+        );
+    }
+
+    #[test]
+    pub fn test_decode_errors() {
+        let errors = [
+            ("4401020304", CborError::ByteString(0)),
+            (
+                "c074323031332d30332d32315432303a30343a30305a",
+                CborError::Tag(0, 0),
+            ),
+            ("8201f7", CborError::Undefined(2)),
+            ("f0", CborError::SimpleValue(16, 0)),
+            ("f8ff", CborError::SimpleValue(255, 0)),
+            ("a10102", CborError::NonStringKey(1)),
+            ("62c3", CborError::UnexpectedEnd(2)),
+            ("9f01", CborError::UnexpectedEnd(2)),
+            ("61ff", CborError::InvalidUtf8(0)),
+            ("1c", CborError::InvalidHeader(0x1c, 0)),
+            ("7f01ff", CborError::InvalidHeader(0x01, 1)),
+            ("ff", CborError::InvalidHeader(0xff, 0)),
+            ("0000", CborError::TrailingBytes(1)),
+        ];
+        for (cbor, error) in errors {
+            assert_eq!(from_cbor(&hex(cbor)), Err(error), "{cbor}");
+        }
+
+        let nested = vec![0x81; MAX_DEPTH + 1];
+        assert_eq!(from_cbor(&nested), Err(CborError::TooDeep(MAX_DEPTH)));
+    }
+}
@@ -0,0 +1,150 @@
+use std::io::{BufReader, Read};
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use super::events::{EventError, Grammar, LexemeSource};
+use super::scanner::Scanner;
+use super::stream::StreamLexer;
+use super::syntactic_analyzer::{JsonValue, ValueBuilder};
+
+// Iterates over JSON values written back to back, with or without whitespace in
+// between: `{"a":1}{"b":2} 3 "x"`. Every value comes with its byte range in the
+// input. Iteration ends at the first error, offset() then tells how far the input
+// was read successfully.
+#[derive(Debug)]
+pub struct ConcatenatedValues<'a, S = Scanner<'a>> {
+    source: S,
+    grammar: Grammar,
+    offset: usize,
+    finished: bool,
+    marker: PhantomData<&'a str>,
+}
+
+impl<'a> ConcatenatedValues<'a> {
+    pub fn new(input: &'a str) -> ConcatenatedValues<'a> {
+        ConcatenatedValues::from_source(Scanner::new(input))
+    }
+}
+
+impl<R: Read> ConcatenatedValues<'static, StreamLexer<BufReader<R>>> {
+    pub fn from_reader(reader: R) -> ConcatenatedValues<'static, StreamLexer<BufReader<R>>> {
+        ConcatenatedValues::from_source(StreamLexer::from_reader(reader))
+    }
+}
+
+impl<'a, S: LexemeSource<'a>> ConcatenatedValues<'a, S> {
+    pub fn from_source(source: S) -> ConcatenatedValues<'a, S> {
+        ConcatenatedValues {
+            source,
+            grammar: Grammar::new(),
+            offset: 0,
+            finished: false,
+            marker: PhantomData,
+        }
+    }
+
+    // The end of the last value read
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn next_value(&mut self) -> Result<Option<(JsonValue, Range<usize>)>, EventError> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        let result = self.read_value();
+        if !matches!(result, Ok(Some(_))) {
+            self.finished = true;
+        }
+        result
+    }
+
+    fn read_value(&mut self) -> Result<Option<(JsonValue, Range<usize>)>, EventError> {
+        let mut builder = ValueBuilder::new();
+        let mut start = None;
+        self.grammar.reset();
+
+        while let Some((lexeme, position)) = self.source.next_lexeme()? {
+            start.get_or_insert(position.offset);
+
+            let Some(event) = self.grammar.accept(lexeme, position)? else {
+                continue;
+            };
+            if let Some(value) = builder.push(event) {
+                self.offset = self.source.position().offset;
+                return Ok(Some((value, start.unwrap_or_default()..self.offset)));
+            }
+        }
+
+        // only whitespace after the last value is a clean end
+        match start {
+            None => Ok(None),
+            Some(_) => Err(EventError::UnexpectedEnd(self.source.position())),
+        }
+    }
+}
+
+impl<'a, S: LexemeSource<'a>> Iterator for ConcatenatedValues<'a, S> {
+    type Item = Result<(JsonValue, Range<usize>), EventError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_value().transpose()
+    }
+}
+
+#[cfg(test)]
+pub mod concatenated_tests {
+    use super::*;
+    use crate::json::syntactic_analyzer::parse_str;
+    use pretty_assertions::assert_eq;
+
+    const INPUT: &str = "{\"a\":1}{\"b\":[2]} 3\n\"x\"[]  ";
+
+    #[test]
+    pub fn test_values_with_ranges() {
+        let values: Vec<(JsonValue, Range<usize>)> =
+            ConcatenatedValues::new(INPUT).map(Result::unwrap).collect();
+
+        let ranges: Vec<&str> = values
+            .iter()
+            .map(|(_, range)| &INPUT[range.clone()])
+            .collect();
+        assert_eq!(ranges, vec!["{\"a\":1}", "{\"b\":[2]}", "3", "\"x\"", "[]"]);
+
+        for (value, range) in &values {
+            assert_eq!(value, &parse_str(&INPUT[range.clone()]).unwrap());
+        }
+    }
+
+    #[test]
+    pub fn test_reader_gives_the_same_ranges() {
+        let from_str: Vec<_> = ConcatenatedValues::new(INPUT).collect();
+        let from_reader: Vec<_> = ConcatenatedValues::from_reader(INPUT.as_bytes()).collect();
+
+        assert_eq!(from_reader, from_str);
+    }
+
+    #[test]
+    pub fn test_offset_where_parsing_stopped() {
+        let input = "[1] {\"a\": 2} {\"b\" 3} [4]";
+        let mut values = ConcatenatedValues::new(input);
+
+        assert_eq!(values.next().unwrap().unwrap().1, 0..3);
+        assert_eq!(values.next().unwrap().unwrap().1, 4..12);
+        assert!(matches!(
+            values.next(),
+            Some(Err(EventError::UnexpectedToken("number", _)))
+        ));
+        assert!(values.next().is_none());
+        assert_eq!(values.offset(), 12);
+
+        let mut values = ConcatenatedValues::new("true [1, 2");
+        assert!(values.next().unwrap().is_ok());
+        assert!(matches!(
+            values.next(),
+            Some(Err(EventError::UnexpectedEnd(_)))
+        ));
+        assert_eq!(values.offset(), 4);
+    }
+}
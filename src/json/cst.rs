@@ -0,0 +1,750 @@
+use std::fmt;
+
+use thiserror::Error;
+
+use super::dialect::{number_value, Dialect};
+use super::events::{EventError, Position};
+use super::pointer::{escape_segment, parse_index, JsonPointer, PointerError};
+use super::scanner::{Lexeme, Scanner};
+use super::serializer;
+use super::syntactic_analyzer::{parse_str_with_dialect, JsonValue};
+
+#[derive(Debug, Clone, Error, PartialEq)]
+pub enum CstError {
+    #[error(transparent)]
+    Syntax(#[from] EventError),
+
+    #[error(transparent)]
+    Pointer(#[from] PointerError),
+}
+
+// A lossless syntax tree: besides the values it keeps the whitespace, the comments,
+// the commas and the spelling of every string and number, so that writing it back
+// gives the input byte for byte. Edits only rewrite the nodes they touch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cst {
+    dialect: Dialect,
+    // whitespace and comments around the root value
+    leading: String,
+    root: Node,
+    trailing: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    // a string, number or literal as it was written
+    Scalar(String),
+    Array(Container),
+    Object(Container),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Container {
+    items: Vec<Item>,
+    // between the last comma and the closing bracket, or everything inside an empty one
+    end: String,
+}
+
+// One element of an array or member of an object:
+// leading [key before_colon ':' after_colon] value before_comma [',']
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    leading: String,
+    key: Option<Key>,
+    value: Node,
+    before_comma: String,
+    comma: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Key {
+    // as written, quotes and escapes included
+    text: String,
+    name: String,
+    before_colon: String,
+    after_colon: String,
+}
+
+impl Cst {
+    pub fn parse(input: &str) -> Result<Cst, EventError> {
+        Cst::parse_with_dialect(input, Dialect::JSON)
+    }
+
+    pub fn parse_with_dialect(input: &str, dialect: Dialect) -> Result<Cst, EventError> {
+        let mut parser = Parser {
+            scanner: Scanner::with_dialect(input, dialect),
+            end: 0,
+        };
+
+        let (lexeme, position, leading) = parser.next()?;
+        let root = parser.value(lexeme, position)?;
+        if let Some((_, position)) = parser.scanner.next_lexeme()? {
+            return Err(EventError::TrailingCharacters(position));
+        }
+
+        Ok(Cst {
+            dialect,
+            leading: leading.to_string(),
+            root,
+            trailing: input[parser.end..].to_string(),
+        })
+    }
+
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    pub fn leading(&self) -> &str {
+        &self.leading
+    }
+
+    pub fn trailing(&self) -> &str {
+        &self.trailing
+    }
+
+    // The node at the pointer, numeric segments index arrays
+    pub fn get(&self, pointer: &str) -> Result<&Node, CstError> {
+        let pointer = JsonPointer::parse(pointer)?;
+        let mut node = &self.root;
+        for depth in 0..pointer.segments().len() {
+            let index = child_index(node, &pointer, depth)?;
+            node = &node.items()[index].value;
+        }
+        Ok(node)
+    }
+
+    // Builds the values, in the dialect the text was read in
+    pub fn value(&self) -> Result<JsonValue, EventError> {
+        parse_str_with_dialect(&self.to_string(), self.dialect)
+    }
+
+    // Replaces an existing value, keeping everything around it
+    pub fn set(&mut self, pointer: &str, value: &JsonValue) -> Result<(), CstError> {
+        let pointer = JsonPointer::parse(pointer)?;
+        let node = Node::from_value(value);
+        if pointer.is_root() {
+            self.root = node;
+            return Ok(());
+        }
+
+        let (container, index) = self.child_mut(&pointer, pointer.segments().len() - 1)?;
+        container.items[index].value = node;
+        Ok(())
+    }
+
+    // Adds a value following the JSON patch "add" rules: object members are replaced
+    // or appended, array elements are inserted before the index ("-" appends).
+    // The new item copies the layout of its neighbours.
+    pub fn insert(&mut self, pointer: &str, value: &JsonValue) -> Result<(), CstError> {
+        let pointer = JsonPointer::parse(pointer)?;
+        let Some((last, parent)) = pointer.segments().split_last() else {
+            self.root = Node::from_value(value);
+            return Ok(());
+        };
+
+        let node = self.node_mut(&pointer, parent.len())?;
+        let (container, index, key) = match node {
+            Node::Scalar(_) => return Err(not_a_container(&pointer, parent.len()).into()),
+            Node::Object(container) => {
+                if let Some(item) = container.items.iter_mut().find(|item| item.name() == last) {
+                    item.value = Node::from_value(value);
+                    return Ok(());
+                }
+                let key = container.new_key(last);
+                let index = container.items.len();
+                (container, index, Some(key))
+            }
+            Node::Array(container) => {
+                let index = if last == "-" {
+                    container.items.len()
+                } else {
+                    parse_index(last)?
+                };
+                if index > container.items.len() {
+                    return Err(PointerError::IndexOutOfBounds(index).into());
+                }
+                (container, index, None)
+            }
+        };
+
+        container.insert(index, key, Node::from_value(value));
+        Ok(())
+    }
+
+    // Removes a member or an element together with its comma and the comments on its line
+    pub fn remove(&mut self, pointer: &str) -> Result<(), CstError> {
+        let pointer = JsonPointer::parse(pointer)?;
+        let Some((_, parent)) = pointer.segments().split_last() else {
+            return Err(PointerError::RemoveRoot.into());
+        };
+
+        let (container, index) = self.child_mut(&pointer, parent.len())?;
+        container.remove(index);
+        Ok(())
+    }
+
+    // The node the first `depth` segments of the pointer lead to
+    fn node_mut(&mut self, pointer: &JsonPointer, depth: usize) -> Result<&mut Node, CstError> {
+        let mut node = &mut self.root;
+        for current in 0..depth {
+            let index = child_index(node, pointer, current)?;
+            node = &mut node.container_mut().expect("checked by child_index").items[index].value;
+        }
+        Ok(node)
+    }
+
+    // The container the first `depth` segments lead to, and the index of the child
+    // the next segment names
+    fn child_mut(
+        &mut self,
+        pointer: &JsonPointer,
+        depth: usize,
+    ) -> Result<(&mut Container, usize), CstError> {
+        let node = self.node_mut(pointer, depth)?;
+        let index = child_index(node, pointer, depth)?;
+        Ok((node.container_mut().expect("checked by child_index"), index))
+    }
+}
+
+impl fmt::Display for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.leading, self.root, self.trailing)
+    }
+}
+
+impl Node {
+    // Compact, as the serializer writes it
+    pub fn from_value(value: &JsonValue) -> Node {
+        Cst::parse(&serializer::to_string(value))
+            .expect("the serializer writes valid json")
+            .root
+    }
+
+    pub fn items(&self) -> &[Item] {
+        match self {
+            Node::Scalar(_) => &[],
+            Node::Array(container) | Node::Object(container) => &container.items,
+        }
+    }
+
+    fn container_mut(&mut self) -> Option<&mut Container> {
+        match self {
+            Node::Scalar(_) => None,
+            Node::Array(container) | Node::Object(container) => Some(container),
+        }
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (container, open, close) = match self {
+            Node::Scalar(text) => return f.write_str(text),
+            Node::Array(container) => (container, '[', ']'),
+            Node::Object(container) => (container, '{', '}'),
+        };
+
+        write!(f, "{open}")?;
+        for item in &container.items {
+            f.write_str(&item.leading)?;
+            if let Some(key) = &item.key {
+                write!(f, "{}{}:{}", key.text, key.before_colon, key.after_colon)?;
+            }
+            write!(f, "{}{}", item.value, item.before_comma)?;
+            if item.comma {
+                f.write_str(",")?;
+            }
+        }
+        write!(f, "{}{close}", container.end)
+    }
+}
+
+// Read access to the parts of the tree, trivia being the whitespace and comments
+// between the lexemes
+impl Container {
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+
+    pub fn end(&self) -> &str {
+        &self.end
+    }
+}
+
+impl Item {
+    pub fn leading(&self) -> &str {
+        &self.leading
+    }
+
+    pub fn key(&self) -> Option<&Key> {
+        self.key.as_ref()
+    }
+
+    pub fn value(&self) -> &Node {
+        &self.value
+    }
+
+    pub fn before_comma(&self) -> &str {
+        &self.before_comma
+    }
+
+    pub fn has_comma(&self) -> bool {
+        self.comma
+    }
+}
+
+impl Key {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn before_colon(&self) -> &str {
+        &self.before_colon
+    }
+
+    pub fn after_colon(&self) -> &str {
+        &self.after_colon
+    }
+}
+
+impl Item {
+    fn name(&self) -> &str {
+        self.key.as_ref().map_or("", |key| key.name.as_str())
+    }
+}
+
+impl Container {
+    // What goes in front of a new item: the line break and indentation of the
+    // items already there, or a single space between items on one line
+    fn separator(&self) -> String {
+        let leading = match self.items.as_slice() {
+            [] => return String::new(),
+            [only] => &only.leading,
+            [.., last] => &last.leading,
+        };
+        match split_line(leading) {
+            (_, rest) if !rest.is_empty() => rest.to_string(),
+            (line, _) if !line.is_empty() && line.trim().is_empty() => line.to_string(),
+            _ => " ".to_string(),
+        }
+    }
+
+    fn new_key(&self, name: &str) -> Key {
+        let (before_colon, after_colon) = self
+            .items
+            .last()
+            .and_then(|item| item.key.as_ref())
+            .map_or((String::new(), " ".to_string()), |key| {
+                (key.before_colon.clone(), key.after_colon.clone())
+            });
+        let mut text = String::new();
+        serializer::write_string(&mut text, name).expect("writing to a String does not fail");
+
+        Key {
+            text,
+            name: name.to_string(),
+            before_colon,
+            after_colon,
+        }
+    }
+
+    fn insert(&mut self, index: usize, key: Option<Key>, value: Node) {
+        let separator = self.separator();
+        let mut item = Item {
+            leading: String::new(),
+            key,
+            value,
+            before_comma: String::new(),
+            comma: true,
+        };
+
+        if index < self.items.len() {
+            // the new item takes the place of the one it goes before
+            item.leading = std::mem::replace(&mut self.items[index].leading, separator);
+        } else if let Some(last) = self.items.last_mut() {
+            // what followed the last item on its line stays there
+            let trailing = if last.comma {
+                std::mem::take(&mut self.end)
+            } else {
+                last.comma = true;
+                item.comma = false;
+                std::mem::take(&mut last.before_comma)
+            };
+            let (line, rest) = split_line(&trailing);
+            let break_line = line.contains("//") && !separator.contains('\n');
+            item.leading = format!("{line}{}{separator}", if break_line { "\n" } else { "" });
+            if item.comma {
+                self.end = rest.to_string();
+            } else {
+                item.before_comma = rest.to_string();
+            }
+        } else {
+            item.comma = false;
+            item.before_comma = std::mem::take(&mut self.end);
+        }
+
+        self.items.insert(index, item);
+    }
+
+    fn remove(&mut self, index: usize) {
+        let removed = self.items.remove(index);
+        let (line, _) = split_line(&removed.leading);
+
+        if index == self.items.len() && !removed.comma {
+            // the last item goes, the comma of the one before it too. Comments on
+            // the line before it stay, the blanks go.
+            let line = if line.trim().is_empty() { "" } else { line };
+            let tail = format!("{line}{}", removed.before_comma);
+            match self.items.last_mut() {
+                Some(previous) => {
+                    previous.comma = false;
+                    previous.before_comma.push_str(&tail);
+                }
+                None => self.end = tail,
+            }
+            return;
+        }
+
+        // what followed the removed item on its line goes with it, the blanks
+        // in front of a trailing comma too
+        let (next, line) = match self.items.get_mut(index) {
+            Some(next) => (&mut next.leading, line),
+            None if line.trim().is_empty() => (&mut self.end, ""),
+            None => (&mut self.end, line),
+        };
+        let (_, rest) = split_line(next);
+        *next = format!("{line}{rest}");
+    }
+}
+
+// Splits whitespace and comments at the first line break outside of a comment
+pub(crate) fn split_line(trivia: &str) -> (&str, &str) {
+    let mut offset = 0;
+    while offset < trivia.len() {
+        let rest = &trivia[offset..];
+        if rest.starts_with("//") {
+            offset += rest.find('\n').unwrap_or(rest.len());
+        } else if rest.starts_with("/*") {
+            offset += rest.find("*/").map_or(rest.len(), |end| end + 2);
+        } else if rest.starts_with(['\r', '\n']) {
+            break;
+        } else {
+            offset += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    // a line comment ends before its \r\n
+    let split = if trivia[..offset].ends_with('\r') {
+        offset - 1
+    } else {
+        offset
+    };
+    trivia.split_at(split)
+}
+
+// The index of the child named by the segment of the pointer at `depth`
+fn child_index(node: &Node, pointer: &JsonPointer, depth: usize) -> Result<usize, PointerError> {
+    let segment = &pointer.segments()[depth];
+    match node {
+        Node::Scalar(_) => Err(not_a_container(pointer, depth)),
+        Node::Object(container) => container
+            .items
+            .iter()
+            .position(|item| item.name() == segment)
+            .ok_or_else(|| not_found(pointer, depth + 1)),
+        Node::Array(container) => match parse_index(segment)? {
+            index if index >= container.items.len() => Err(PointerError::IndexOutOfBounds(index)),
+            index => Ok(index),
+        },
+    }
+}
+
+fn prefix(pointer: &JsonPointer, depth: usize) -> String {
+    pointer.segments()[..depth]
+        .iter()
+        .map(|segment| format!("/{}", escape_segment(segment)))
+        .collect()
+}
+
+fn not_found(pointer: &JsonPointer, depth: usize) -> PointerError {
+    PointerError::NotFound(prefix(pointer, depth))
+}
+
+fn not_a_container(pointer: &JsonPointer, depth: usize) -> PointerError {
+    PointerError::NotAContainer(prefix(pointer, depth))
+}
+
+struct Parser<'a> {
+    scanner: Scanner<'a>,
+    // where the last lexeme read ends
+    end: usize,
+}
+
+impl<'a> Parser<'a> {
+    // The next lexeme, with the whitespace and comments in front of it
+    fn next(&mut self) -> Result<(Lexeme<'a>, Position, &'a str), EventError> {
+        let (lexeme, position) = self
+            .scanner
+            .next_lexeme()?
+            .ok_or(EventError::UnexpectedEnd(self.scanner.position()))?;
+        let trivia = &self.scanner.input()[self.end..position.offset];
+        self.end = self.scanner.offset();
+        Ok((lexeme, position, trivia))
+    }
+
+    fn text(&self, position: Position) -> String {
+        self.scanner.input()[position.offset..self.end].to_string()
+    }
+
+    fn value(&mut self, lexeme: Lexeme<'a>, position: Position) -> Result<Node, EventError> {
+        match lexeme {
+            Lexeme::LeftBrace => Ok(Node::Object(self.container(true)?)),
+            Lexeme::LeftBracket => Ok(Node::Array(self.container(false)?)),
+            Lexeme::Number(number) if number_value(&number).is_none() => {
+                Err(EventError::InvalidNumber(position))
+            }
+            Lexeme::String(_) | Lexeme::Number(_) | Lexeme::True | Lexeme::False | Lexeme::Null => {
+                Ok(Node::Scalar(self.text(position)))
+            }
+            lexeme => Err(EventError::UnexpectedToken(lexeme.describe(), position)),
+        }
+    }
+
+    fn container(&mut self, is_object: bool) -> Result<Container, EventError> {
+        let close = if is_object {
+            Lexeme::RightBrace
+        } else {
+            Lexeme::RightBracket
+        };
+        let mut items: Vec<Item> = Vec::new();
+
+        loop {
+            let (lexeme, position, leading) = self.next()?;
+            let may_close = items.is_empty() || self.scanner.dialect().trailing_commas;
+            if lexeme == close && may_close {
+                return Ok(Container {
+                    items,
+                    end: leading.to_string(),
+                });
+            }
+
+            let (key, lexeme, position) = if is_object {
+                let name = match lexeme {
+                    Lexeme::String(name) | Lexeme::Identifier(name) => name.into_owned(),
+                    lexeme => match lexeme.keyword_name() {
+                        Some(name) if self.scanner.dialect().identifier_keys => name.to_string(),
+                        _ => return Err(EventError::UnexpectedToken(lexeme.describe(), position)),
+                    },
+                };
+                let text = self.text(position);
+                let before_colon = match self.next()? {
+                    (Lexeme::Colon, _, trivia) => trivia.to_string(),
+                    (lexeme, position, _) => {
+                        return Err(EventError::UnexpectedToken(lexeme.describe(), position))
+                    }
+                };
+                let (lexeme, position, after_colon) = self.next()?;
+                let key = Key {
+                    text,
+                    name,
+                    before_colon,
+                    after_colon: after_colon.to_string(),
+                };
+                (Some(key), lexeme, position)
+            } else {
+                (None, lexeme, position)
+            };
+
+            let value = self.value(lexeme, position)?;
+            let (lexeme, position, before_comma) = self.next()?;
+            let comma = match lexeme {
+                Lexeme::Comma => true,
+                lexeme if lexeme == close => false,
+                lexeme => return Err(EventError::UnexpectedToken(lexeme.describe(), position)),
+            };
+            items.push(Item {
+                leading: leading.to_string(),
+                key,
+                value,
+                before_comma: before_comma.to_string(),
+                comma,
+            });
+
+            if !comma {
+                return Ok(Container {
+                    items,
+                    end: String::new(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod cst_tests {
+    use super::*;
+    use crate::json::syntactic_analyzer::{parse_str, JsonPair};
+    use pretty_assertions::assert_eq;
+
+    const PACKAGE: &str = r#"{
+    "name":   "json_parser_rs",
+    "version" : "1.2.3",
+    "keywords": [ "json", "parser" ],
+    "scores": [1.50, 2e3, -0, "été"],
+    "nested": {"deep":{ "a" :true}}
+}
+"#;
+
+    fn edited(input: &str, edit: impl FnOnce(&mut Cst) -> Result<(), CstError>) -> String {
+        let mut cst = Cst::parse_with_dialect(input, Dialect::JSONC).unwrap();
+        edit(&mut cst).unwrap();
+        cst.to_string()
+    }
+
+    fn string(value: &str) -> JsonValue {
+        JsonValue::String(value.to_string())
+    }
+
+    #[test]
+    pub fn test_round_trip_is_byte_identical() {
+        let cst = Cst::parse(PACKAGE).unwrap();
+        assert_eq!(cst.to_string(), PACKAGE);
+        assert_eq!(cst.value().unwrap(), parse_str(PACKAGE).unwrap());
+
+        let commented = " // settings\r\n{ /* a */ \"a\" /* b */ : /* c */ [1 , 2 ,] , // d\r\n}\t";
+        let cst = Cst::parse_with_dialect(commented, Dialect::JSONC).unwrap();
+        assert_eq!(cst.to_string(), commented);
+
+        assert_eq!(Cst::parse(" 1.0e+2 ").unwrap().to_string(), " 1.0e+2 ");
+        assert_eq!(
+            Cst::parse(PACKAGE).unwrap().get("/scores/3").unwrap(),
+            &Node::Scalar(r#""été""#.to_string())
+        );
+    }
+
+    #[test]
+    pub fn test_set_only_touches_the_value() {
+        let output = edited(PACKAGE, |cst| cst.set("/version", &string("1.3.0")));
+        assert_eq!(output, PACKAGE.replace("1.2.3", "1.3.0"));
+
+        let output = edited(PACKAGE, |cst| {
+            cst.set("/nested/deep/a", &JsonValue::Boolean(false))?;
+            cst.set(
+                "/keywords/1",
+                &JsonValue::JsonObject(vec![JsonPair("b".to_string(), JsonValue::Null)]),
+            )
+        });
+        assert_eq!(
+            output,
+            PACKAGE
+                .replace(":true}}", ":false}}")
+                .replace(r#""parser" ]"#, r#"{"b":null} ]"#)
+        );
+
+        assert_eq!(edited("[1]", |cst| cst.set("", &string("x"))), r#""x""#);
+    }
+
+    #[test]
+    pub fn test_insert_copies_the_layout() {
+        let input = "{\n  \"a\": 1,\n  \"b\": 2 // two\n}\n";
+        assert_eq!(
+            edited(input, |cst| cst.insert("/c", &JsonValue::Number(3.0))),
+            "{\n  \"a\": 1,\n  \"b\": 2, // two\n  \"c\": 3\n}\n"
+        );
+        assert_eq!(
+            edited(input, |cst| cst.insert("/a", &JsonValue::Number(0.0))),
+            "{\n  \"a\": 0,\n  \"b\": 2 // two\n}\n"
+        );
+
+        let input = "[\n    1,\n    2,\n]";
+        assert_eq!(
+            edited(input, |cst| cst.insert("/-", &JsonValue::Number(3.0))),
+            "[\n    1,\n    2,\n    3,\n]"
+        );
+        assert_eq!(
+            edited(input, |cst| cst.insert("/0", &JsonValue::Number(0.0))),
+            "[\n    0,\n    1,\n    2,\n]"
+        );
+
+        assert_eq!(
+            edited(r#"{"a": [1, 2]}"#, |cst| cst.insert("/a/1", &string("x"))),
+            r#"{"a": [1, "x", 2]}"#
+        );
+        assert_eq!(
+            edited(r#"{"a": 1}"#, |cst| cst.insert("/b", &JsonValue::Null)),
+            r#"{"a": 1, "b": null}"#
+        );
+        assert_eq!(
+            edited("{ }", |cst| cst.insert("/b", &JsonValue::Null)),
+            r#"{"b": null }"#
+        );
+    }
+
+    #[test]
+    pub fn test_remove_keeps_the_neighbours() {
+        let input = "[\n  0, // zero\n  1, // one\n  2\n]";
+        assert_eq!(
+            edited(input, |cst| cst.remove("/1")),
+            "[\n  0, // zero\n  2\n]"
+        );
+        assert_eq!(
+            edited(input, |cst| cst.remove("/2")),
+            "[\n  0, // zero\n  1 // one\n]"
+        );
+        assert_eq!(
+            edited(input, |cst| cst.remove("/0")),
+            "[\n  1, // one\n  2\n]"
+        );
+
+        assert_eq!(
+            edited(r#"{"a": 1, "b": 2, "c": 3}"#, |cst| cst.remove("/b")),
+            r#"{"a": 1, "c": 3}"#
+        );
+        assert_eq!(
+            edited(r#"{"a": 1, "b": 2}"#, |cst| cst.remove("/a")),
+            r#"{"b": 2}"#
+        );
+        assert_eq!(edited("[ 1 ]", |cst| cst.remove("/0")), "[ ]");
+        assert_eq!(edited("[1, 2,]", |cst| cst.remove("/1")), "[1,]");
+        assert_eq!(edited("[1, 2]", |cst| cst.remove("/1")), "[1]");
+    }
+
+    #[test]
+    pub fn test_errors() {
+        let mut cst = Cst::parse(PACKAGE).unwrap();
+
+        assert_eq!(
+            cst.set("/missing/x", &JsonValue::Null),
+            Err(CstError::Pointer(PointerError::NotFound(
+                "/missing".to_string()
+            )))
+        );
+        assert_eq!(
+            cst.remove("/keywords/2"),
+            Err(CstError::Pointer(PointerError::IndexOutOfBounds(2)))
+        );
+        assert_eq!(
+            cst.insert("/name/x", &JsonValue::Null),
+            Err(CstError::Pointer(PointerError::NotAContainer(
+                "/name".to_string()
+            )))
+        );
+        assert_eq!(
+            cst.remove(""),
+            Err(CstError::Pointer(PointerError::RemoveRoot))
+        );
+        assert_eq!(cst.to_string(), PACKAGE);
+
+        assert!(matches!(
+            Cst::parse("[1,]"),
+            Err(EventError::UnexpectedToken("']'", _))
+        ));
+        assert!(matches!(
+            Cst::parse("[1] 2"),
+            Err(EventError::TrailingCharacters(_))
+        ));
+        assert!(matches!(Cst::parse(""), Err(EventError::UnexpectedEnd(_))));
+    }
+}
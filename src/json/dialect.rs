@@ -0,0 +1,239 @@
+// The syntax extensions the scanner and the grammar accept on top of strict JSON.
+// Dialect::JSON enables none of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Dialect {
+    // `// line` and `/* block */` comments
+    pub comments: bool,
+    // a comma after the last member or element
+    pub trailing_commas: bool,
+    // object keys written as identifiers, without quotes
+    pub identifier_keys: bool,
+    pub single_quoted_strings: bool,
+    // hexadecimal, a leading '+', a leading or trailing decimal point, Infinity and NaN
+    pub extended_numbers: bool,
+    // the ECMAScript escapes (\v, \0, \xHH, \' and any other escaped character
+    // standing for itself) and lines continued by a backslash before the line break
+    pub extended_escapes: bool,
+    // vertical tab, form feed, no-break space, BOM and the Unicode separators
+    pub extended_whitespace: bool,
+    // Hjson (https://hjson.github.io) is line based and has a parser of its own,
+    // hjson::parse_str. parse_str_with_dialect hands such input over to it, the scanner
+    // reports EventError::UnsupportedDialect rather than read it as JSONC.
+    pub hjson: bool,
+}
+
+impl Dialect {
+    pub const JSON: Dialect = Dialect {
+        comments: false,
+        trailing_commas: false,
+        identifier_keys: false,
+        single_quoted_strings: false,
+        extended_numbers: false,
+        extended_escapes: false,
+        extended_whitespace: false,
+        hjson: false,
+    };
+
+    // JSON with comments, as read by editors for their settings files. Trailing commas
+    // are accepted too, Dialect { trailing_commas: false, ..Dialect::JSONC } rejects them.
+    pub const JSONC: Dialect = Dialect {
+        comments: true,
+        trailing_commas: true,
+        ..Dialect::JSON
+    };
+
+    // https://spec.json5.org
+    pub const JSON5: Dialect = Dialect {
+        comments: true,
+        trailing_commas: true,
+        identifier_keys: true,
+        single_quoted_strings: true,
+        extended_numbers: true,
+        extended_escapes: true,
+        extended_whitespace: true,
+        hjson: false,
+    };
+
+    // quoteless strings and keys, optional commas, `#` comments and ''' strings
+    pub const HJSON: Dialect = Dialect {
+        comments: true,
+        trailing_commas: true,
+        single_quoted_strings: true,
+        hjson: true,
+        ..Dialect::JSON
+    };
+}
+
+// Converts the spelling of a number lexeme, in any dialect, to its value
+pub fn number_value(text: &str) -> Option<f32> {
+    let (negative, unsigned) = match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+
+    let magnitude = if let Some(hex) = unsigned
+        .strip_prefix("0x")
+        .or_else(|| unsigned.strip_prefix("0X"))
+    {
+        if hex.is_empty() {
+            return None;
+        }
+        hex.chars().try_fold(0f64, |value, ch| {
+            Some(value * 16.0 + ch.to_digit(16)? as f64)
+        })? as f32
+    } else if unsigned == "Infinity" {
+        f32::INFINITY
+    } else if unsigned == "NaN" {
+        f32::NAN
+    } else if unsigned.starts_with(|ch: char| ch.is_ascii_digit() || ch == '.') {
+        unsigned.parse::<f32>().ok()?
+    } else {
+        return None;
+    };
+
+    Some(if negative { -magnitude } else { magnitude })
+}
+
+#[cfg(test)]
+pub mod dialect_tests {
+    use super::*;
+    use crate::json::cst::Cst;
+    use crate::json::events::{EventError, EventReader, Position};
+    use crate::json::formatter::{format, FormatOptions};
+    use crate::json::syntactic_analyzer::{parse_str, parse_str_with_dialect, JsonPair, JsonValue};
+    use pretty_assertions::assert_eq;
+
+    fn json5(input: &str) -> Result<JsonValue, EventError> {
+        parse_str_with_dialect(input, Dialect::JSON5)
+    }
+
+    #[test]
+    pub fn test_json5_document() {
+        let input = r#"// https://spec.json5.org example
+{
+  // comments
+  unquoted: 'and you can quote me on that',
+  singleQuotes: 'I can use "double quotes" here',
+  lineBreaks: "Look, Mom! \
+No \\n's!",
+  hexadecimal: 0xdecaf,
+  leadingDecimalPoint: .8675309, andTrailing: 8675309.,
+  positiveSign: +1,
+  trailingComma: 'in objects', andIn: ['arrays',],
+  "backwardsCompatible": "with JSON",
+  /* block
+     comment */ $_ident1: [-Infinity, '\v\x41'],
+  null: 1, true: 2, NaN: 3, Infinity: 4, if: 5,
+}"#;
+        let mut expected = parse_str(
+            r#"{
+  "unquoted": "and you can quote me on that",
+  "singleQuotes": "I can use \"double quotes\" here",
+  "lineBreaks": "Look, Mom! No \\n's!",
+  "hexadecimal": 912559,
+  "leadingDecimalPoint": 0.8675309, "andTrailing": 8675309,
+  "positiveSign": 1,
+  "trailingComma": "in objects", "andIn": ["arrays"],
+  "backwardsCompatible": "with JSON"
+}"#,
+        )
+        .unwrap();
+        if let JsonValue::JsonObject(members) = &mut expected {
+            members.push(JsonPair(
+                "$_ident1".to_string(),
+                JsonValue::JsonArray(vec![
+                    JsonValue::Number(f32::NEG_INFINITY),
+                    JsonValue::String("\u{b}A".to_string()),
+                ]),
+            ));
+            // keys may be reserved words
+            for (index, name) in ["null", "true", "NaN", "Infinity", "if"].iter().enumerate() {
+                members.push(JsonPair(
+                    name.to_string(),
+                    JsonValue::Number(index as f32 + 1.0),
+                ));
+            }
+        }
+
+        assert_eq!(json5(input).unwrap(), expected);
+    }
+
+    #[test]
+    pub fn test_json5_is_not_json() {
+        for input in [
+            "{a: 1}",
+            "{null: 1}",
+            "['x']",
+            "[1,]",
+            "[0x10]",
+            "[.5]",
+            "[+1]",
+            "[NaN]",
+            "[1] // comment",
+        ] {
+            assert!(parse_str(input).is_err(), "{input}");
+            assert!(json5(input).is_ok(), "{input}");
+        }
+    }
+
+    #[test]
+    pub fn test_json5_errors() {
+        let at = |offset, column| Position {
+            offset,
+            line: 1,
+            column,
+        };
+
+        assert_eq!(
+            json5("[1,,]"),
+            Err(EventError::UnexpectedToken("','", at(3, 4)))
+        );
+        assert_eq!(
+            json5("{a: b}"),
+            Err(EventError::UnexpectedToken("identifier", at(4, 5)))
+        );
+        assert_eq!(json5("[.]"), Err(EventError::InvalidNumber(at(1, 2))));
+        assert_eq!(json5("[0x]"), Err(EventError::InvalidNumber(at(1, 2))));
+        assert_eq!(json5("['\\1']"), Err(EventError::InvalidEscape(at(2, 3))));
+        assert_eq!(
+            json5("[1] /* open"),
+            Err(EventError::UnterminatedComment(at(4, 5)))
+        );
+    }
+
+    #[test]
+    pub fn test_hjson_is_not_scanned() {
+        let mut reader = EventReader::with_dialect("{a: b}", Dialect::HJSON);
+        assert_eq!(reader.next_event(), Err(EventError::UnsupportedDialect));
+        assert_eq!(
+            Cst::parse_with_dialect("{a: b}", Dialect::HJSON).err(),
+            Some(EventError::UnsupportedDialect)
+        );
+
+        let options = FormatOptions {
+            dialect: Dialect::HJSON,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format("{a: b}", options),
+            Err(EventError::UnsupportedDialect)
+        );
+
+        // parse_str_with_dialect hands it over to the Hjson parser instead
+        assert!(parse_str_with_dialect("{a: b\n}", Dialect::HJSON).is_ok());
+    }
+
+    #[test]
+    pub fn test_number_value() {
+        assert_eq!(number_value("-12.5e1"), Some(-125.0));
+        assert_eq!(number_value("0x1F"), Some(31.0));
+        assert_eq!(number_value("-0XfF"), Some(-255.0));
+        assert_eq!(number_value("+.5"), Some(0.5));
+        assert_eq!(number_value("5."), Some(5.0));
+        assert_eq!(number_value("-Infinity"), Some(f32::NEG_INFINITY));
+        assert!(number_value("NaN").is_some_and(f32::is_nan));
+        assert_eq!(number_value("0x"), None);
+        assert_eq!(number_value("inf"), None);
+    }
+}
@@ -0,0 +1,378 @@
+use std::fmt;
+
+use super::patch::lcs_table;
+use super::pointer::JsonPointer;
+use super::syntactic_analyzer::{JsonPair, JsonValue};
+
+pub mod colors {
+    pub const RED: &str = "\x1b[31m";
+    pub const GREEN: &str = "\x1b[32m";
+    pub const YELLOW: &str = "\x1b[33m";
+    pub const RESET: &str = "\x1b[0m";
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ArrayMatching {
+    // elements are compared position by position
+    #[default]
+    Index,
+    // elements are aligned on their longest common subsequence first, so an insertion
+    // in the middle of an array is reported as one addition
+    Lcs,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DiffOptions {
+    pub array_matching: ArrayMatching,
+    // numbers closer to each other than this are considered equal
+    pub float_tolerance: Option<f32>,
+}
+
+// Removed entries are located in the old document, added and changed entries in the new one
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<'a> {
+    Added {
+        path: JsonPointer,
+        value: &'a JsonValue,
+    },
+    Removed {
+        path: JsonPointer,
+        value: &'a JsonValue,
+    },
+    Changed {
+        path: JsonPointer,
+        from: &'a JsonValue,
+        to: &'a JsonValue,
+    },
+}
+
+impl Change<'_> {
+    pub fn path(&self) -> &JsonPointer {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Changed { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for Change<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { path, value } => write!(f, "+ {}: {}", display_path(path), value),
+            Change::Removed { path, value } => write!(f, "- {}: {}", display_path(path), value),
+            Change::Changed { path, from, to } => {
+                write!(f, "~ {}: {} -> {}", display_path(path), from, to)
+            }
+        }
+    }
+}
+
+fn display_path(path: &JsonPointer) -> String {
+    if path.is_root() {
+        "(root)".to_owned()
+    } else {
+        path.to_string()
+    }
+}
+
+pub fn diff<'a>(from: &'a JsonValue, to: &'a JsonValue) -> Vec<Change<'a>> {
+    diff_with_options(from, to, &DiffOptions::default())
+}
+
+pub fn diff_with_options<'a>(
+    from: &'a JsonValue,
+    to: &'a JsonValue,
+    options: &DiffOptions,
+) -> Vec<Change<'a>> {
+    let mut changes = Vec::new();
+    Differ { options }.diff_values(JsonPointer::root(), from, to, &mut changes);
+    changes
+}
+
+// One line per change, in the order they were found
+pub fn render(changes: &[Change]) -> String {
+    changes.iter().map(|change| format!("{change}\n")).collect()
+}
+
+// Same as render, with additions in green, removals in red and changes in yellow
+pub fn render_colored(changes: &[Change]) -> String {
+    changes
+        .iter()
+        .map(|change| {
+            let color = match change {
+                Change::Added { .. } => colors::GREEN,
+                Change::Removed { .. } => colors::RED,
+                Change::Changed { .. } => colors::YELLOW,
+            };
+            format!("{color}{change}{}\n", colors::RESET)
+        })
+        .collect()
+}
+
+struct Differ<'o> {
+    options: &'o DiffOptions,
+}
+
+impl Differ<'_> {
+    fn same(&self, from: &JsonValue, to: &JsonValue) -> bool {
+        match (from, to) {
+            (JsonValue::Number(from), JsonValue::Number(to)) => {
+                match self.options.float_tolerance {
+                    Some(tolerance) => (from - to).abs() <= tolerance,
+                    None => from == to,
+                }
+            }
+            (JsonValue::JsonArray(from), JsonValue::JsonArray(to)) => {
+                from.len() == to.len()
+                    && from
+                        .iter()
+                        .zip(to.iter())
+                        .all(|(from, to)| self.same(from, to))
+            }
+            (JsonValue::JsonObject(from), JsonValue::JsonObject(to)) => {
+                from.len() == to.len()
+                    && from.iter().all(|JsonPair(key, value)| {
+                        to.iter()
+                            .find(|pair| pair.0 == *key)
+                            .is_some_and(|JsonPair(_, other)| self.same(value, other))
+                    })
+            }
+            (from, to) => from == to,
+        }
+    }
+
+    fn diff_values<'a>(
+        &self,
+        path: JsonPointer,
+        from: &'a JsonValue,
+        to: &'a JsonValue,
+        changes: &mut Vec<Change<'a>>,
+    ) {
+        if self.same(from, to) {
+            return;
+        }
+
+        match (from, to) {
+            (JsonValue::JsonObject(from_members), JsonValue::JsonObject(to_members)) => {
+                for JsonPair(key, value) in from_members.iter() {
+                    let member_path = path.clone().with(key.as_str());
+                    match to_members.iter().find(|pair| pair.0 == *key) {
+                        Some(JsonPair(_, other)) => {
+                            self.diff_values(member_path, value, other, changes)
+                        }
+                        None => changes.push(Change::Removed {
+                            path: member_path,
+                            value,
+                        }),
+                    }
+                }
+
+                for JsonPair(key, value) in to_members.iter() {
+                    if !from_members.iter().any(|pair| pair.0 == *key) {
+                        changes.push(Change::Added {
+                            path: path.clone().with(key.as_str()),
+                            value,
+                        });
+                    }
+                }
+            }
+            (JsonValue::JsonArray(from_elements), JsonValue::JsonArray(to_elements)) => {
+                match self.options.array_matching {
+                    ArrayMatching::Index => {
+                        self.diff_arrays_by_index(path, from_elements, to_elements, changes)
+                    }
+                    ArrayMatching::Lcs => {
+                        self.diff_arrays_by_lcs(path, from_elements, to_elements, changes)
+                    }
+                }
+            }
+            _ => changes.push(Change::Changed { path, from, to }),
+        }
+    }
+
+    fn diff_arrays_by_index<'a>(
+        &self,
+        path: JsonPointer,
+        from: &'a [JsonValue],
+        to: &'a [JsonValue],
+        changes: &mut Vec<Change<'a>>,
+    ) {
+        for index in 0..from.len().max(to.len()) {
+            let element_path = path.clone().with_index(index);
+            match (from.get(index), to.get(index)) {
+                (Some(from), Some(to)) => self.diff_values(element_path, from, to, changes),
+                (Some(value), None) => changes.push(Change::Removed {
+                    path: element_path,
+                    value,
+                }),
+                (None, Some(value)) => changes.push(Change::Added {
+                    path: element_path,
+                    value,
+                }),
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn diff_arrays_by_lcs<'a>(
+        &self,
+        path: JsonPointer,
+        from: &'a [JsonValue],
+        to: &'a [JsonValue],
+        changes: &mut Vec<Change<'a>>,
+    ) {
+        let table = lcs_table(from, to, |from, to| self.same(from, to));
+
+        let (mut i, mut j) = (0, 0);
+        while i < from.len() || j < to.len() {
+            if i < from.len() && j < to.len() && self.same(&from[i], &to[j]) {
+                i += 1;
+                j += 1;
+            } else if i < from.len() && j < to.len() && table[i + 1][j + 1] == table[i][j] {
+                self.diff_values(path.clone().with_index(j), &from[i], &to[j], changes);
+                i += 1;
+                j += 1;
+            } else if j < to.len() && (i == from.len() || table[i][j + 1] >= table[i + 1][j]) {
+                changes.push(Change::Added {
+                    path: path.clone().with_index(j),
+                    value: &to[j],
+                });
+                j += 1;
+            } else {
+                changes.push(Change::Removed {
+                    path: path.clone().with_index(i),
+                    value: &from[i],
+                });
+                i += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod diff_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn string(value: &str) -> JsonValue {
+        JsonValue::String(value.to_string())
+    }
+
+    fn numbers(values: &[f32]) -> JsonValue {
+        JsonValue::JsonArray(values.iter().map(|n| JsonValue::Number(*n)).collect())
+    }
+
+    fn object(members: Vec<(&str, JsonValue)>) -> JsonValue {
+        JsonValue::JsonObject(
+            members
+                .into_iter()
+                .map(|(key, value)| JsonPair(key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    fn pointer(path: &str) -> JsonPointer {
+        JsonPointer::parse(path).unwrap()
+    }
+
+    #[test]
+    pub fn test_diff_objects() {
+        let from = object(vec![
+            ("name", string("service")),
+            ("replicas", JsonValue::Number(2.0)),
+            ("debug", JsonValue::Boolean(true)),
+        ]);
+        let to = object(vec![
+            ("replicas", JsonValue::Number(3.0)),
+            ("name", string("service")),
+            ("region", string("eu")),
+        ]);
+
+        assert_eq!(
+            diff(&from, &to),
+            vec![
+                Change::Changed {
+                    path: pointer("/replicas"),
+                    from: &JsonValue::Number(2.0),
+                    to: &JsonValue::Number(3.0),
+                },
+                Change::Removed {
+                    path: pointer("/debug"),
+                    value: &JsonValue::Boolean(true),
+                },
+                Change::Added {
+                    path: pointer("/region"),
+                    value: &string("eu"),
+                },
+            ]
+        );
+        assert_eq!(diff(&from, &from), vec![]);
+    }
+
+    #[test]
+    pub fn test_diff_arrays_by_index() {
+        let from = numbers(&[1.0, 2.0, 3.0]);
+        let to = numbers(&[0.0, 1.0, 2.0, 3.0]);
+
+        assert_eq!(diff(&from, &to).len(), 4);
+    }
+
+    #[test]
+    pub fn test_diff_arrays_by_lcs() {
+        let from = numbers(&[1.0, 2.0, 3.0]);
+        let to = numbers(&[0.0, 1.0, 3.0]);
+        let options = DiffOptions {
+            array_matching: ArrayMatching::Lcs,
+            ..DiffOptions::default()
+        };
+
+        assert_eq!(
+            diff_with_options(&from, &to, &options),
+            vec![
+                Change::Added {
+                    path: pointer("/0"),
+                    value: &JsonValue::Number(0.0),
+                },
+                Change::Removed {
+                    path: pointer("/1"),
+                    value: &JsonValue::Number(2.0),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_diff_with_float_tolerance() {
+        let from = object(vec![("ratio", JsonValue::Number(0.3))]);
+        let to = object(vec![("ratio", JsonValue::Number(0.30001))]);
+
+        assert_eq!(diff(&from, &to).len(), 1);
+
+        let options = DiffOptions {
+            float_tolerance: Some(0.001),
+            ..DiffOptions::default()
+        };
+        assert_eq!(diff_with_options(&from, &to, &options), vec![]);
+    }
+
+    #[test]
+    pub fn test_render() {
+        let from = object(vec![("a", numbers(&[1.0])), ("b", JsonValue::Null)]);
+        let to = object(vec![("a", numbers(&[2.0])), ("c", string("new"))]);
+        let changes = diff(&from, &to);
+
+        assert_eq!(
+            render(&changes),
+            "~ /a/0: 1 -> 2\n- /b: null\n+ /c: \"new\"\n"
+        );
+        assert_eq!(
+            render_colored(&changes[..1]),
+            "\x1b[33m~ /a/0: 1 -> 2\x1b[0m\n"
+        );
+        assert_eq!(
+            render(&diff(&JsonValue::Null, &JsonValue::Boolean(true))),
+            "~ (root): null -> true\n"
+        );
+    }
+}
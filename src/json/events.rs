@@ -0,0 +1,568 @@
+use std::{borrow::Cow, fmt, marker::PhantomData};
+
+use thiserror::Error;
+
+use super::dialect::{number_value, Dialect};
+use super::scanner::{Lexeme, Scanner};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    // byte offset from the start of the input
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, Error, PartialEq)]
+pub enum EventError {
+    #[error("Unexpected character '{0}' at {1}")]
+    UnexpectedCharacter(char, Position),
+
+    #[error("Unexpected {0} at {1}")]
+    UnexpectedToken(&'static str, Position),
+
+    #[error("Unexpected end of input at {0}")]
+    UnexpectedEnd(Position),
+
+    #[error("Control character in string at {0}")]
+    InvalidString(Position),
+
+    #[error("Invalid escape sequence at {0}")]
+    InvalidEscape(Position),
+
+    #[error("Invalid number at {0}")]
+    InvalidNumber(Position),
+
+    #[error("Invalid literal at {0}")]
+    InvalidLiteral(Position),
+
+    #[error("Unexpected characters after the json value at {0}")]
+    TrailingCharacters(Position),
+
+    #[error("Unterminated comment at {0}")]
+    UnterminatedComment(Position),
+
+    #[error("Invalid UTF-8 in string at {0}")]
+    InvalidUtf8(Position),
+
+    #[error("Could not read the input: {0}")]
+    Io(String),
+
+    #[error("Hjson is read by hjson::parse_str, not by the scanner")]
+    UnsupportedDialect,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'a> {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    Key(Cow<'a, str>),
+    String(Cow<'a, str>),
+    Number(f32),
+    Bool(bool),
+    Null,
+}
+
+impl Event<'_> {
+    pub fn into_owned(self) -> Event<'static> {
+        match self {
+            Event::StartObject => Event::StartObject,
+            Event::EndObject => Event::EndObject,
+            Event::StartArray => Event::StartArray,
+            Event::EndArray => Event::EndArray,
+            Event::Key(key) => Event::Key(Cow::Owned(key.into_owned())),
+            Event::String(string) => Event::String(Cow::Owned(string.into_owned())),
+            Event::Number(number) => Event::Number(number),
+            Event::Bool(boolean) => Event::Bool(boolean),
+            Event::Null => Event::Null,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    Object,
+    Array,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Value,
+    FirstValueOrEnd,
+    FirstKeyOrEnd,
+    Key,
+    Colon,
+    CommaOrEnd,
+    Done,
+}
+
+// The JSON grammar as a state machine over lexemes. It does not care where the
+// lexemes come from, so every reader in the crate shares it. Memory use is bounded
+// by the nesting stack.
+#[derive(Debug, Clone)]
+pub struct Grammar {
+    stack: Vec<Container>,
+    state: State,
+    trailing_commas: bool,
+    identifier_keys: bool,
+    optional_commas: bool,
+}
+
+impl Default for Grammar {
+    fn default() -> Self {
+        Grammar {
+            stack: Vec::new(),
+            state: State::Value,
+            trailing_commas: false,
+            identifier_keys: false,
+            optional_commas: false,
+        }
+    }
+}
+
+impl Grammar {
+    pub fn new() -> Grammar {
+        Grammar::default()
+    }
+
+    pub fn with_dialect(dialect: Dialect) -> Grammar {
+        Grammar {
+            trailing_commas: dialect.trailing_commas,
+            identifier_keys: dialect.identifier_keys,
+            ..Grammar::default()
+        }
+    }
+
+    // Members and elements may follow each other without a comma in between, as in the
+    // token streams of the lexer
+    pub fn with_optional_commas() -> Grammar {
+        Grammar {
+            optional_commas: true,
+            ..Grammar::default()
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    // true once a complete value was read
+    pub fn is_complete(&self) -> bool {
+        self.state == State::Done
+    }
+
+    // true before the first lexeme of a value
+    pub fn is_idle(&self) -> bool {
+        self.state == State::Value && self.stack.is_empty()
+    }
+
+    // Starts over, ready for the next value in a stream of documents
+    pub fn reset(&mut self) {
+        self.stack.clear();
+        self.state = State::Value;
+    }
+
+    pub fn accept<'a>(
+        &mut self,
+        lexeme: Lexeme<'a>,
+        position: Position,
+    ) -> Result<Option<Event<'a>>, EventError> {
+        let unexpected = |lexeme: &Lexeme| EventError::UnexpectedToken(lexeme.describe(), position);
+
+        match self.state {
+            State::Done => Err(EventError::TrailingCharacters(position)),
+            State::Value => self.value(lexeme, position).map(Some),
+            State::FirstValueOrEnd => match lexeme {
+                Lexeme::RightBracket => Ok(Some(self.close(Container::Array))),
+                lexeme => self.value(lexeme, position).map(Some),
+            },
+            State::FirstKeyOrEnd | State::Key => match lexeme {
+                Lexeme::RightBrace if self.state == State::FirstKeyOrEnd => {
+                    Ok(Some(self.close(Container::Object)))
+                }
+                Lexeme::String(key) | Lexeme::Identifier(key) => {
+                    self.state = State::Colon;
+                    Ok(Some(Event::Key(key)))
+                }
+                lexeme => match lexeme.keyword_name() {
+                    Some(name) if self.identifier_keys => {
+                        self.state = State::Colon;
+                        Ok(Some(Event::Key(Cow::Borrowed(name))))
+                    }
+                    _ => Err(unexpected(&lexeme)),
+                },
+            },
+            State::Colon => match lexeme {
+                Lexeme::Colon => {
+                    self.state = State::Value;
+                    Ok(None)
+                }
+                lexeme => Err(unexpected(&lexeme)),
+            },
+            State::CommaOrEnd => match (lexeme, self.stack.last()) {
+                // with trailing commas the container may end after any comma
+                (Lexeme::Comma, Some(Container::Object)) => {
+                    self.state = if self.trailing_commas {
+                        State::FirstKeyOrEnd
+                    } else {
+                        State::Key
+                    };
+                    Ok(None)
+                }
+                (Lexeme::Comma, Some(Container::Array)) => {
+                    self.state = if self.trailing_commas {
+                        State::FirstValueOrEnd
+                    } else {
+                        State::Value
+                    };
+                    Ok(None)
+                }
+                (Lexeme::RightBrace, Some(Container::Object)) => {
+                    Ok(Some(self.close(Container::Object)))
+                }
+                (Lexeme::RightBracket, Some(Container::Array)) => {
+                    Ok(Some(self.close(Container::Array)))
+                }
+                (lexeme, Some(container)) if self.optional_commas => {
+                    self.state = match container {
+                        Container::Object => State::Key,
+                        Container::Array => State::Value,
+                    };
+                    self.accept(lexeme, position)
+                }
+                (lexeme, _) => Err(unexpected(&lexeme)),
+            },
+        }
+    }
+
+    // Checks that the input did not stop in the middle of a value
+    pub fn finish(&self, position: Position) -> Result<(), EventError> {
+        if self.state == State::Done {
+            Ok(())
+        } else {
+            Err(EventError::UnexpectedEnd(position))
+        }
+    }
+
+    fn value<'a>(
+        &mut self,
+        lexeme: Lexeme<'a>,
+        position: Position,
+    ) -> Result<Event<'a>, EventError> {
+        let event = match lexeme {
+            Lexeme::LeftBrace => {
+                self.stack.push(Container::Object);
+                self.state = State::FirstKeyOrEnd;
+                return Ok(Event::StartObject);
+            }
+            Lexeme::LeftBracket => {
+                self.stack.push(Container::Array);
+                self.state = State::FirstValueOrEnd;
+                return Ok(Event::StartArray);
+            }
+            Lexeme::String(string) => Event::String(string),
+            Lexeme::Number(number) => {
+                Event::Number(number_value(&number).ok_or(EventError::InvalidNumber(position))?)
+            }
+            Lexeme::NumberValue(number) => Event::Number(number),
+            Lexeme::True => Event::Bool(true),
+            Lexeme::False => Event::Bool(false),
+            Lexeme::Null => Event::Null,
+            lexeme => return Err(EventError::UnexpectedToken(lexeme.describe(), position)),
+        };
+
+        self.after_value();
+        Ok(event)
+    }
+
+    fn close(&mut self, container: Container) -> Event<'static> {
+        self.stack.pop();
+        self.after_value();
+        match container {
+            Container::Object => Event::EndObject,
+            Container::Array => Event::EndArray,
+        }
+    }
+
+    fn after_value(&mut self) {
+        self.state = if self.stack.is_empty() {
+            State::Done
+        } else {
+            State::CommaOrEnd
+        };
+    }
+}
+
+// Anything lexemes can be pulled from: the Scanner over a &str, or the byte
+// lexers reading from streams.
+pub trait LexemeSource<'a> {
+    fn next_lexeme(&mut self) -> Result<Option<(Lexeme<'a>, Position)>, EventError>;
+    fn position(&self) -> Position;
+}
+
+impl<'a> LexemeSource<'a> for Scanner<'a> {
+    fn next_lexeme(&mut self) -> Result<Option<(Lexeme<'a>, Position)>, EventError> {
+        Scanner::next_lexeme(self)
+    }
+
+    fn position(&self) -> Position {
+        Scanner::position(self)
+    }
+}
+
+// Pull parser, over a &str by default. Strings without escapes are then borrowed
+// from the input and nothing but the nesting stack is allocated while reading.
+#[derive(Debug, Clone)]
+pub struct EventReader<'a, S = Scanner<'a>> {
+    source: S,
+    grammar: Grammar,
+    finished: bool,
+    marker: PhantomData<Lexeme<'a>>,
+}
+
+impl<'a> EventReader<'a> {
+    pub fn new(input: &'a str) -> EventReader<'a> {
+        EventReader::from_source(Scanner::new(input))
+    }
+
+    pub fn with_dialect(input: &'a str, dialect: Dialect) -> EventReader<'a> {
+        EventReader::from_scanner(Scanner::with_dialect(input, dialect))
+    }
+
+    // Reads the lexemes of a scanner set up by the caller, in its dialect
+    pub fn from_scanner(scanner: Scanner<'a>) -> EventReader<'a> {
+        let grammar = Grammar::with_dialect(scanner.dialect());
+        EventReader::with_grammar(scanner, grammar)
+    }
+}
+
+impl<'a, S: LexemeSource<'a>> EventReader<'a, S> {
+    pub fn from_source(source: S) -> EventReader<'a, S> {
+        EventReader::with_grammar(source, Grammar::new())
+    }
+
+    pub fn with_grammar(source: S, grammar: Grammar) -> EventReader<'a, S> {
+        EventReader {
+            source,
+            grammar,
+            finished: false,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    pub fn source_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    pub fn depth(&self) -> usize {
+        self.grammar.depth()
+    }
+
+    pub fn position(&self) -> Position {
+        self.source.position()
+    }
+
+    pub fn next_event(&mut self) -> Result<Option<(Event<'a>, Position)>, EventError> {
+        while !self.finished {
+            let next = self
+                .source
+                .next_lexeme()
+                .inspect_err(|_| self.finished = true)?;
+
+            let Some((lexeme, position)) = next else {
+                self.finished = true;
+                self.grammar.finish(self.source.position())?;
+                break;
+            };
+
+            let event = self
+                .grammar
+                .accept(lexeme, position)
+                .inspect_err(|_| self.finished = true)?;
+            if let Some(event) = event {
+                return Ok(Some((event, position)));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl<'a, S: LexemeSource<'a>> Iterator for EventReader<'a, S> {
+    type Item = Result<(Event<'a>, Position), EventError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+// Callback flavour of the pull parser
+pub fn for_each_event<'a, F>(input: &'a str, mut callback: F) -> Result<(), EventError>
+where
+    F: FnMut(Event<'a>, Position),
+{
+    let mut reader = EventReader::new(input);
+    while let Some((event, position)) = reader.next_event()? {
+        callback(event, position);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod events_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn events(input: &str) -> Result<Vec<Event<'_>>, EventError> {
+        EventReader::new(input)
+            .map(|result| result.map(|(event, _)| event))
+            .collect()
+    }
+
+    #[test]
+    pub fn test_events_of_nested_document() {
+        let input = r#"{"a": [1, true, null], "b": {"c": "d"}}"#;
+
+        assert_eq!(
+            events(input).unwrap(),
+            vec![
+                Event::StartObject,
+                Event::Key(Cow::Borrowed("a")),
+                Event::StartArray,
+                Event::Number(1.0),
+                Event::Bool(true),
+                Event::Null,
+                Event::EndArray,
+                Event::Key(Cow::Borrowed("b")),
+                Event::StartObject,
+                Event::Key(Cow::Borrowed("c")),
+                Event::String(Cow::Borrowed("d")),
+                Event::EndObject,
+                Event::EndObject,
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_events_of_scalar_roots() {
+        assert_eq!(events(" 42 ").unwrap(), vec![Event::Number(42.0)]);
+        assert_eq!(events("-1.5e2").unwrap(), vec![Event::Number(-150.0)]);
+        assert_eq!(
+            events("[]").unwrap(),
+            vec![Event::StartArray, Event::EndArray]
+        );
+    }
+
+    #[test]
+    pub fn test_event_positions() {
+        let input = "{\n  \"key\": [\n    false\n  ]\n}";
+        let positions: Vec<(usize, usize)> = EventReader::new(input)
+            .map(|result| {
+                let (_, position) = result.unwrap();
+                (position.line, position.column)
+            })
+            .collect();
+
+        assert_eq!(
+            positions,
+            vec![(1, 1), (2, 3), (2, 10), (3, 5), (4, 3), (5, 1)]
+        );
+    }
+
+    #[test]
+    pub fn test_strings_are_borrowed_unless_escaped() {
+        let input = r#"["plain", "esc\"aped é 😀"]"#;
+        let events = events(input).unwrap();
+
+        assert!(matches!(events[1], Event::String(Cow::Borrowed("plain"))));
+        assert_eq!(
+            events[2],
+            Event::String(Cow::Owned("esc\"aped é 😀".to_string()))
+        );
+        assert!(matches!(events[2], Event::String(Cow::Owned(_))));
+    }
+
+    #[test]
+    pub fn test_syntax_errors() {
+        let at = |offset, column| Position {
+            offset,
+            line: 1,
+            column,
+        };
+
+        assert_eq!(events(""), Err(EventError::UnexpectedEnd(at(0, 1))));
+        assert_eq!(events("{"), Err(EventError::UnexpectedEnd(at(1, 2))));
+        assert_eq!(
+            events(r#"{"a" 1}"#),
+            Err(EventError::UnexpectedToken("number", at(5, 6)))
+        );
+        assert_eq!(
+            events(r#"{"a":1,}"#),
+            Err(EventError::UnexpectedToken("'}'", at(7, 8)))
+        );
+        assert_eq!(
+            events("[1 2]"),
+            Err(EventError::UnexpectedToken("number", at(3, 4)))
+        );
+        assert_eq!(events("[01]"), Err(EventError::InvalidNumber(at(1, 2))));
+        assert_eq!(events("[1.]"), Err(EventError::InvalidNumber(at(1, 2))));
+        assert_eq!(events("[tru]"), Err(EventError::InvalidLiteral(at(1, 2))));
+        assert_eq!(
+            events("['a']"),
+            Err(EventError::UnexpectedCharacter('\'', at(1, 2)))
+        );
+        assert_eq!(
+            events(r#"["\x"]"#),
+            Err(EventError::InvalidEscape(at(2, 3)))
+        );
+        assert_eq!(events("[\"\t\"]"), Err(EventError::InvalidString(at(2, 3))));
+        assert_eq!(
+            events(r#"["\ud800"]"#),
+            Err(EventError::InvalidEscape(at(2, 3)))
+        );
+        assert_eq!(
+            events("{} {}"),
+            Err(EventError::TrailingCharacters(at(3, 4)))
+        );
+    }
+
+    #[test]
+    pub fn test_reader_stops_after_error() {
+        let mut reader = EventReader::new("[1, }");
+
+        assert_eq!(reader.next().unwrap().unwrap().0, Event::StartArray);
+        assert_eq!(reader.next().unwrap().unwrap().0, Event::Number(1.0));
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    pub fn test_for_each_event() {
+        let mut depth = 0;
+        let mut max_depth = 0;
+        for_each_event("[[[]], [[[]]]]", |event, _| {
+            match event {
+                Event::StartArray => depth += 1,
+                Event::EndArray => depth -= 1,
+                _ => {}
+            }
+            max_depth = max_depth.max(depth);
+        })
+        .unwrap();
+
+        assert_eq!(max_depth, 4);
+    }
+}
@@ -0,0 +1,333 @@
+use super::cst::{split_line, Container, Cst, Item, Node};
+use super::dialect::Dialect;
+use super::events::EventError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub indent_width: usize,
+    // arrays of scalars are kept on one line when they fit in it
+    pub max_line_width: usize,
+    pub final_newline: bool,
+    pub sort_keys: bool,
+    // comments are kept when the dialect allows them, as Dialect::JSONC does
+    pub dialect: Dialect,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            indent_width: 2,
+            max_line_width: 80,
+            final_newline: true,
+            sort_keys: false,
+            dialect: Dialect::JSON,
+        }
+    }
+}
+
+// Reformats a json text: one member per line, arrays on one line when they fit,
+// strings and numbers spelled as they were. Formatting twice gives the same text.
+pub fn format(input: &str, options: FormatOptions) -> Result<String, EventError> {
+    let cst = Cst::parse_with_dialect(input, options.dialect)?;
+    let mut formatter = Formatter {
+        options,
+        out: String::new(),
+    };
+
+    for comment in comments(cst.leading()) {
+        formatter.out.push_str(comment);
+        formatter.out.push('\n');
+    }
+    formatter.node(cst.root(), 0);
+
+    let (line, rest) = split_line(cst.trailing());
+    formatter.trailing(&comments(line), 0);
+    for comment in comments(rest) {
+        formatter.newline(0);
+        formatter.out.push_str(comment);
+    }
+
+    if options.final_newline {
+        formatter.out.push('\n');
+    }
+    Ok(formatter.out)
+}
+
+// A member or an element with the comments that go on the lines before it and
+// after it on its line
+#[derive(Debug)]
+struct Entry<'a> {
+    item: &'a Item,
+    leading: Vec<&'a str>,
+    trailing: Vec<&'a str>,
+}
+
+// Sorts out the comments of the trivia between the items. Those on the line of an
+// item follow it, the others go before the next item or the closing bracket.
+fn entries(container: &Container) -> (Vec<Entry<'_>>, Vec<&str>) {
+    let items = container.items();
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut end = Vec::new();
+
+    for (index, item) in items.iter().enumerate() {
+        let (line, rest) = split_line(item.leading());
+        let mut leading = match entries.last_mut() {
+            Some(previous) => {
+                previous.trailing.extend(comments(line));
+                Vec::new()
+            }
+            None => comments(line),
+        };
+        leading.extend(comments(rest));
+        if let Some(key) = item.key() {
+            leading.extend(comments(key.before_colon()));
+            leading.extend(comments(key.after_colon()));
+        }
+
+        let trailing = if index + 1 == items.len() && !item.has_comma() {
+            let (line, rest) = split_line(item.before_comma());
+            end.extend(comments(rest));
+            comments(line)
+        } else {
+            comments(item.before_comma())
+        };
+        entries.push(Entry {
+            item,
+            leading,
+            trailing,
+        });
+    }
+
+    let (line, rest) = split_line(container.end());
+    match entries.last_mut() {
+        Some(last) => last.trailing.extend(comments(line)),
+        None => end.extend(comments(line)),
+    }
+    end.extend(comments(rest));
+    (entries, end)
+}
+
+// The comments of some trivia, line comments without their line break
+fn comments(trivia: &str) -> Vec<&str> {
+    let mut comments = Vec::new();
+    let mut offset = 0;
+    while offset < trivia.len() {
+        let rest = &trivia[offset..];
+        let length = if rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if rest.starts_with("/*") {
+            rest.find("*/").map_or(rest.len(), |end| end + 2)
+        } else {
+            offset += rest.chars().next().map_or(1, char::len_utf8);
+            continue;
+        };
+        comments.push(rest[..length].trim_end_matches('\r'));
+        offset += length;
+    }
+    comments
+}
+
+struct Formatter {
+    options: FormatOptions,
+    out: String,
+}
+
+impl Formatter {
+    fn node(&mut self, node: &Node, depth: usize) {
+        let (container, open, close) = match node {
+            Node::Scalar(text) => return self.out.push_str(text),
+            Node::Array(container) => (container, '[', ']'),
+            Node::Object(container) => (container, '{', '}'),
+        };
+
+        if let Some(compact) = compact(node) {
+            // room for the comma that may follow
+            if self.column() + compact.chars().count() < self.options.max_line_width {
+                return self.out.push_str(&compact);
+            }
+        }
+
+        let (mut entries, end) = entries(container);
+        if self.options.sort_keys {
+            entries.sort_by(|left, right| name(left.item).cmp(name(right.item)));
+        }
+
+        self.out.push(open);
+        let count = entries.len();
+        for (index, entry) in entries.into_iter().enumerate() {
+            for comment in entry.leading {
+                self.newline(depth + 1);
+                self.out.push_str(comment);
+            }
+            self.newline(depth + 1);
+            if let Some(key) = entry.item.key() {
+                self.out.push_str(key.text());
+                self.out.push_str(": ");
+            }
+            self.node(entry.item.value(), depth + 1);
+            if index + 1 < count {
+                self.out.push(',');
+            }
+            self.trailing(&entry.trailing, depth + 1);
+        }
+        for comment in end {
+            self.newline(depth + 1);
+            self.out.push_str(comment);
+        }
+        self.newline(depth);
+        self.out.push(close);
+    }
+
+    // Comments after a value on its line, a line comment ends the line
+    fn trailing(&mut self, comments: &[&str], depth: usize) {
+        let mut line_ended = false;
+        for comment in comments {
+            if line_ended {
+                self.newline(depth);
+            } else {
+                self.out.push(' ');
+            }
+            self.out.push_str(comment);
+            line_ended = comment.starts_with("//");
+        }
+    }
+
+    fn newline(&mut self, depth: usize) {
+        self.out.push('\n');
+        let width = depth * self.options.indent_width;
+        self.out.extend(std::iter::repeat_n(' ', width));
+    }
+
+    fn column(&self) -> usize {
+        let line_start = self.out.rfind('\n').map_or(0, |index| index + 1);
+        self.out[line_start..].chars().count()
+    }
+}
+
+fn name(item: &Item) -> &str {
+    item.key().map_or("", |key| key.name())
+}
+
+// The value on one line, for arrays holding neither objects nor comments
+fn compact(node: &Node) -> Option<String> {
+    match node {
+        Node::Scalar(text) => Some(text.clone()),
+        Node::Object(container) if container.items().is_empty() => comments(container.end())
+            .is_empty()
+            .then(|| "{}".to_string()),
+        Node::Object(_) => None,
+        Node::Array(container) => {
+            let (entries, end) = entries(container);
+            let mut elements = Vec::new();
+            for entry in entries {
+                if !entry.leading.is_empty() || !entry.trailing.is_empty() {
+                    return None;
+                }
+                elements.push(compact(entry.item.value())?);
+            }
+            end.is_empty().then(|| format!("[{}]", elements.join(", ")))
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod formatter_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn formatted(input: &str, options: FormatOptions) -> String {
+        let output = format(input, options).unwrap();
+        assert_eq!(format(&output, options).unwrap(), output, "not idempotent");
+        output
+    }
+
+    #[test]
+    pub fn test_format() {
+        let input = r#"{"name":"json","list":[1,2.50,"x"],"nested":{"deep":[{"a":null}],"empty":{},"none":[]}}"#;
+
+        assert_eq!(
+            formatted(input, FormatOptions::default()),
+            r#"{
+  "name": "json",
+  "list": [1, 2.50, "x"],
+  "nested": {
+    "deep": [
+      {
+        "a": null
+      }
+    ],
+    "empty": {},
+    "none": []
+  }
+}
+"#
+        );
+
+        assert_eq!(formatted("  true ", FormatOptions::default()), "true\n");
+        assert!(format("[1,]", FormatOptions::default()).is_err());
+    }
+
+    #[test]
+    pub fn test_format_options() {
+        let input = r#"{"b": [1, 2, 3], "a": {"d": 1, "c": 2}}"#;
+        let options = FormatOptions {
+            indent_width: 4,
+            max_line_width: 12,
+            final_newline: false,
+            sort_keys: true,
+            ..FormatOptions::default()
+        };
+
+        assert_eq!(
+            formatted(input, options),
+            r#"{
+    "a": {
+        "c": 2,
+        "d": 1
+    },
+    "b": [
+        1,
+        2,
+        3
+    ]
+}"#
+        );
+
+        // `    "b": [1, 2, 3],` is 19 columns wide
+        let width = |max_line_width| FormatOptions {
+            max_line_width,
+            ..options
+        };
+        assert!(formatted(r#"{"b": [1, 2, 3], "c": 0}"#, width(19)).contains("[1, 2, 3],"));
+        assert!(!formatted(r#"{"b": [1, 2, 3], "c": 0}"#, width(18)).contains("[1, 2, 3],"));
+    }
+
+    #[test]
+    pub fn test_format_keeps_comments() {
+        let input =
+            "// settings\n{ \"b\": [1, /* two */ 2], // bee\n  \"a\": 1 /* one */\n  // the end\n}";
+        let options = FormatOptions {
+            dialect: Dialect::JSONC,
+            sort_keys: true,
+            ..FormatOptions::default()
+        };
+
+        assert_eq!(
+            formatted(input, options),
+            r#"// settings
+{
+  "a": 1, /* one */
+  "b": [
+    1, /* two */
+    2
+  ] // bee
+  // the end
+}
+"#
+        );
+        assert_eq!(
+            formatted("[ // first\n1, 2,\n] // root", options),
+            "[\n  // first\n  1,\n  2\n] // root\n"
+        );
+    }
+}
@@ -0,0 +1,543 @@
+use std::fmt::{self, Write};
+
+use super::dialect::{number_value, Dialect};
+use super::events::{EventError, Position};
+use super::scanner::{Lexeme, Scanner};
+use super::serializer::{write_number, write_string};
+use super::syntactic_analyzer::{JsonPair, JsonValue};
+
+// The quoted strings of Hjson, in double or single quotes with the json escapes
+const QUOTED: Dialect = Dialect {
+    single_quoted_strings: true,
+    ..Dialect::JSON
+};
+
+// Parses an Hjson text (https://hjson.github.io/syntax.html). Strings without quotes
+// run to the end of their line, commas between members and elements are optional,
+// `#`, `//` and `/* */` start comments and the braces of a root object may be left out.
+pub fn parse_str(input: &str) -> Result<JsonValue, EventError> {
+    let mut parser = Parser {
+        input,
+        offset: 0,
+        line: 1,
+        line_start: 0,
+    };
+
+    parser.skip_blanks()?;
+    // an empty text is an empty object without braces
+    let value = if parser.peek().is_none() || parser.at_braceless_object() {
+        parser.object(false)?
+    } else {
+        parser.value()?
+    };
+
+    parser.skip_blanks()?;
+    if parser.peek().is_some() {
+        return Err(EventError::TrailingCharacters(parser.position()));
+    }
+    Ok(value)
+}
+
+#[derive(Debug, Clone)]
+struct Parser<'a> {
+    input: &'a str,
+    offset: usize,
+    line: usize,
+    line_start: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn position(&self) -> Position {
+        Position {
+            offset: self.offset,
+            line: self.line,
+            column: self.offset - self.line_start + 1,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.offset..]
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.offset).copied()
+    }
+
+    fn advance(&mut self, length: usize) {
+        let skipped = &self.input[self.offset..self.offset + length];
+        if let Some(last_break) = skipped.rfind('\n') {
+            self.line += skipped.matches('\n').count();
+            self.line_start = self.offset + last_break + 1;
+        }
+        self.offset += length;
+    }
+
+    fn unexpected(&self) -> EventError {
+        match self.rest().chars().next() {
+            Some(ch) => EventError::UnexpectedCharacter(ch, self.position()),
+            None => EventError::UnexpectedEnd(self.position()),
+        }
+    }
+
+    // Whitespace, line breaks and comments
+    fn skip_blanks(&mut self) -> Result<(), EventError> {
+        loop {
+            let rest = self.rest();
+            match rest.as_bytes().first() {
+                Some(b' ' | b'\t' | b'\r' | b'\n') => self.advance(1),
+                Some(b'#') => self.advance(line_length(rest)),
+                Some(b'/') if rest.starts_with("//") => self.advance(line_length(rest)),
+                Some(b'/') if rest.starts_with("/*") => match rest[2..].find("*/") {
+                    Some(end) => self.advance(end + 4),
+                    None => return Err(EventError::UnterminatedComment(self.position())),
+                },
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    // A root starting with a key and a colon
+    fn at_braceless_object(&self) -> bool {
+        let mut lookahead = self.clone();
+        lookahead.key().is_ok() && lookahead.skip_blanks().is_ok() && lookahead.peek() == Some(b':')
+    }
+
+    fn value(&mut self) -> Result<JsonValue, EventError> {
+        self.skip_blanks()?;
+        match self.peek() {
+            Some(b'{') => {
+                self.advance(1);
+                self.object(true)
+            }
+            Some(b'[') => {
+                self.advance(1);
+                self.array()
+            }
+            Some(b'\'') if self.rest().starts_with("'''") => {
+                self.multiline().map(JsonValue::String)
+            }
+            Some(b'"' | b'\'') => self.quoted().map(JsonValue::String),
+            Some(b'}' | b']' | b',' | b':') | None => Err(self.unexpected()),
+            Some(_) => Ok(self.quoteless()),
+        }
+    }
+
+    fn object(&mut self, braces: bool) -> Result<JsonValue, EventError> {
+        let mut members = Vec::new();
+        loop {
+            self.skip_blanks()?;
+            match self.peek() {
+                Some(b'}') if braces => {
+                    self.advance(1);
+                    return Ok(JsonValue::JsonObject(members));
+                }
+                None if !braces => return Ok(JsonValue::JsonObject(members)),
+                _ => {}
+            }
+
+            let key = self.key()?;
+            self.skip_blanks()?;
+            if self.peek() != Some(b':') {
+                return Err(self.unexpected());
+            }
+            self.advance(1);
+            members.push(JsonPair(key, self.value()?));
+
+            self.skip_blanks()?;
+            if self.peek() == Some(b',') {
+                self.advance(1);
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue, EventError> {
+        let mut elements = Vec::new();
+        loop {
+            self.skip_blanks()?;
+            if self.peek() == Some(b']') {
+                self.advance(1);
+                return Ok(JsonValue::JsonArray(elements));
+            }
+
+            elements.push(self.value()?);
+
+            self.skip_blanks()?;
+            if self.peek() == Some(b',') {
+                self.advance(1);
+            }
+        }
+    }
+
+    // A quoted key, or the characters up to the colon without blanks or punctuation
+    fn key(&mut self) -> Result<String, EventError> {
+        if let Some(b'"' | b'\'') = self.peek() {
+            return self.quoted();
+        }
+
+        let rest = self.rest();
+        let length = rest
+            .find(|ch: char| ch == ':' || ch.is_whitespace() || "{}[],".contains(ch))
+            .unwrap_or(rest.len());
+        if length == 0 {
+            return Err(self.unexpected());
+        }
+        self.advance(length);
+        Ok(rest[..length].to_string())
+    }
+
+    fn quoted(&mut self) -> Result<String, EventError> {
+        let mut scanner = Scanner::at(self.input, self.position()).in_dialect(QUOTED);
+        match scanner.next_lexeme()? {
+            Some((Lexeme::String(string), _)) => {
+                self.advance(scanner.offset() - self.offset);
+                Ok(string.into_owned())
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    // A number or a literal when only a separator or a comment follows it on its
+    // line, otherwise a string running to the end of the line
+    fn quoteless(&mut self) -> JsonValue {
+        let rest = self.rest();
+        let line = rest[..line_length(rest)].trim_end();
+
+        let mut scanner = Scanner::at(self.input, self.position());
+        if let Ok(Some((lexeme, _))) = scanner.next_lexeme() {
+            let length = scanner.offset() - self.offset;
+            let after = line.get(length..).unwrap_or_default().trim_start();
+            let separated = after.is_empty()
+                || after.starts_with([',', ']', '}', '#'])
+                || after.starts_with("//")
+                || after.starts_with("/*");
+            let value = match lexeme {
+                Lexeme::Number(text) => number_value(&text).map(JsonValue::Number),
+                Lexeme::True => Some(JsonValue::Boolean(true)),
+                Lexeme::False => Some(JsonValue::Boolean(false)),
+                Lexeme::Null => Some(JsonValue::Null),
+                _ => None,
+            };
+            if let (true, Some(value)) = (separated, value) {
+                self.advance(length);
+                return value;
+            }
+        }
+
+        self.advance(line.len());
+        JsonValue::String(line.to_string())
+    }
+
+    // A ''' string. The indentation of its opening quotes is removed from every
+    // line, as are a blank first line and the line break before the closing quotes.
+    fn multiline(&mut self) -> Result<String, EventError> {
+        let indent = self.position().column - 1;
+        self.advance(3);
+
+        let rest = self.rest();
+        let first_line = &rest[..line_length(rest)];
+        if first_line.trim().is_empty() && first_line.len() < rest.len() {
+            self.advance(first_line.len() + 1);
+        }
+
+        let rest = self.rest();
+        let Some(end) = rest.find("'''") else {
+            self.advance(rest.len());
+            return Err(EventError::UnexpectedEnd(self.position()));
+        };
+
+        let mut text = String::new();
+        for (index, line) in rest[..end].split('\n').enumerate() {
+            if index > 0 {
+                text.push('\n');
+            }
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let blanks = line
+                .bytes()
+                .take(indent)
+                .take_while(|byte| *byte == b' ' || *byte == b'\t')
+                .count();
+            text.push_str(&line[blanks..]);
+        }
+        if let Some(last_break) = text.rfind('\n') {
+            if text[last_break + 1..].trim().is_empty() {
+                text.truncate(last_break);
+            }
+        }
+
+        self.advance(end + 3);
+        Ok(text)
+    }
+}
+
+fn line_length(rest: &str) -> usize {
+    rest.find('\n').unwrap_or(rest.len())
+}
+
+// Writes a value as Hjson: one member or element per line without commas, keys and
+// strings without quotes whenever they would be read back the same
+pub fn to_hjson_string(value: &JsonValue) -> String {
+    let mut out = String::new();
+    write_hjson(&mut out, value).expect("writing to a String does not fail");
+    out
+}
+
+pub fn write_hjson<W: Write>(out: &mut W, value: &JsonValue) -> fmt::Result {
+    write_value(out, value, 0)
+}
+
+fn write_value<W: Write>(out: &mut W, value: &JsonValue, depth: usize) -> fmt::Result {
+    match value {
+        JsonValue::String(string) => write_hjson_string(out, string, depth),
+        JsonValue::Number(number) => write_number(out, *number),
+        JsonValue::Boolean(boolean) => write!(out, "{boolean}"),
+        JsonValue::Null => out.write_str("null"),
+        JsonValue::JsonArray(elements) if elements.is_empty() => out.write_str("[]"),
+        JsonValue::JsonObject(members) if members.is_empty() => out.write_str("{}"),
+        JsonValue::JsonArray(elements) => {
+            out.write_char('[')?;
+            for element in elements {
+                newline(out, depth + 1)?;
+                write_value(out, element, depth + 1)?;
+            }
+            newline(out, depth)?;
+            out.write_char(']')
+        }
+        JsonValue::JsonObject(members) => {
+            out.write_char('{')?;
+            for JsonPair(key, member) in members {
+                newline(out, depth + 1)?;
+                if is_quoteless_key(key) {
+                    out.write_str(key)?;
+                } else {
+                    write_string(out, key)?;
+                }
+                out.write_char(':')?;
+
+                match member {
+                    // the ''' go on a line of their own, further indented
+                    JsonValue::String(string) if is_multiline(string) => {
+                        newline(out, depth + 2)?;
+                        write_value(out, member, depth + 2)?;
+                    }
+                    _ => {
+                        out.write_char(' ')?;
+                        write_value(out, member, depth + 1)?;
+                    }
+                }
+            }
+            newline(out, depth)?;
+            out.write_char('}')
+        }
+    }
+}
+
+fn write_hjson_string<W: Write>(out: &mut W, string: &str, depth: usize) -> fmt::Result {
+    if is_quoteless_string(string) {
+        return out.write_str(string);
+    }
+    if !is_multiline(string) {
+        return write_string(out, string);
+    }
+
+    out.write_str("'''")?;
+    for line in string.split('\n') {
+        if line.is_empty() {
+            out.write_char('\n')?;
+        } else {
+            newline(out, depth)?;
+            out.write_str(line)?;
+        }
+    }
+    newline(out, depth)?;
+    out.write_str("'''")
+}
+
+fn newline<W: Write>(out: &mut W, depth: usize) -> fmt::Result {
+    write!(out, "\n{:width$}", "", width = depth * 2)
+}
+
+fn starts_like_a_comment_or_punctuation(text: &str) -> bool {
+    text.starts_with(['{', '}', '[', ']', ',', ':', '"', '\'', '#'])
+        || text.starts_with("//")
+        || text.starts_with("/*")
+}
+
+fn is_quoteless_key(key: &str) -> bool {
+    !key.is_empty()
+        && !starts_like_a_comment_or_punctuation(key)
+        && !key
+            .chars()
+            .any(|ch| ch.is_whitespace() || ch.is_control() || "{}[],:".contains(ch))
+}
+
+// Strings that could be read back as numbers or literals keep their quotes
+fn is_quoteless_string(string: &str) -> bool {
+    !string.is_empty()
+        && string.trim() == string
+        && !starts_like_a_comment_or_punctuation(string)
+        && !string.starts_with(|ch: char| ch.is_ascii_digit() || ch == '-' || ch == '.')
+        && !["true", "false", "null"]
+            .iter()
+            .any(|literal| string.starts_with(literal))
+        && !string.chars().any(char::is_control)
+}
+
+fn is_multiline(string: &str) -> bool {
+    string.contains('\n')
+        && !string.contains("'''")
+        && !string
+            .chars()
+            .any(|ch| ch.is_control() && ch != '\n' && ch != '\t')
+        // the blanks at the start of the first line would be taken for indentation
+        && !string.starts_with([' ', '\t'])
+}
+
+#[cfg(test)]
+pub mod hjson_tests {
+    use super::*;
+    use crate::json::syntactic_analyzer::{parse_str as parse_json, parse_str_with_dialect};
+    use pretty_assertions::assert_eq;
+
+    const CONFIG: &str = r#"
+# a human edited configuration
+{
+  // the name of the service
+  name: billing service
+  port: 8080
+  "quoted key": 'single quoted'
+  ratio: 0.5, # a comma is allowed
+  debug: false // and so is a comment
+  tags: [
+    web
+    internal, with a comma
+    "quoted"
+  ]
+  empty: {}
+  description:
+    '''
+    Runs every night.
+      Sends invoices.
+    '''
+  note: 3 times
+  path: C:\tmp /* not a comment */
+}
+"#;
+
+    #[test]
+    pub fn test_parse_hjson() {
+        let expected = parse_json(
+            r#"{
+  "name": "billing service",
+  "port": 8080,
+  "quoted key": "single quoted",
+  "ratio": 0.5,
+  "debug": false,
+  "tags": ["web", "internal, with a comma", "quoted"],
+  "empty": {},
+  "description": "Runs every night.\n  Sends invoices.",
+  "note": "3 times",
+  "path": "C:\\tmp /* not a comment */"
+}"#,
+        )
+        .unwrap();
+
+        assert_eq!(parse_str(CONFIG).unwrap(), expected);
+        assert_eq!(
+            parse_str_with_dialect(CONFIG, Dialect::HJSON).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    pub fn test_hjson_roots() {
+        assert_eq!(
+            parse_str("a: 1\nb: two\n").unwrap(),
+            parse_json(r#"{"a": 1, "b": "two"}"#).unwrap()
+        );
+        assert_eq!(
+            parse_str("# nothing\n").unwrap(),
+            JsonValue::JsonObject(vec![])
+        );
+        assert_eq!(
+            parse_str("  just text  ").unwrap(),
+            JsonValue::String("just text".to_string())
+        );
+        assert_eq!(
+            parse_str("[1, 2\n3]").unwrap(),
+            parse_json("[1, 2, 3]").unwrap()
+        );
+        assert_eq!(
+            parse_str("  '''\n  two\n  lines\n  '''").unwrap(),
+            JsonValue::String("two\nlines".to_string())
+        );
+    }
+
+    #[test]
+    pub fn test_hjson_errors() {
+        assert!(matches!(
+            parse_str("{a: 1"),
+            Err(EventError::UnexpectedEnd(_))
+        ));
+        assert!(matches!(
+            parse_str("{a 1}"),
+            Err(EventError::UnexpectedCharacter('1', _))
+        ));
+        assert!(matches!(
+            parse_str("[,]"),
+            Err(EventError::UnexpectedCharacter(',', _))
+        ));
+        assert!(matches!(
+            parse_str("{a: 1} /* open"),
+            Err(EventError::UnterminatedComment(_))
+        ));
+        assert!(matches!(
+            parse_str("{a: '''open"),
+            Err(EventError::UnexpectedEnd(_))
+        ));
+        assert!(matches!(
+            parse_str("[1] 2"),
+            Err(EventError::TrailingCharacters(_))
+        ));
+        // quoteless strings run to the end of the line, the bracket included
+        assert!(matches!(
+            parse_str("[a, b]"),
+            Err(EventError::UnexpectedEnd(_))
+        ));
+    }
+
+    #[test]
+    pub fn test_write_hjson() {
+        let value = parse_json(
+            r##"{"name": "billing service", "port": 8080, "with space": "", "number like": "42",
+                "text": "first\n  second\n", "list": [true, null, "#hash", ["nested"]], "empty": []}"##,
+        )
+        .unwrap();
+        let written = to_hjson_string(&value);
+
+        assert_eq!(
+            written,
+            r##"{
+  name: billing service
+  port: 8080
+  "with space": ""
+  "number like": "42"
+  text:
+    '''
+    first
+      second
+
+    '''
+  list: [
+    true
+    null
+    "#hash"
+    [
+      nested
+    ]
+  ]
+  empty: []
+}"##
+        );
+        assert_eq!(parse_str(&written).unwrap(), value);
+    }
+}
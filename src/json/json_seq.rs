@@ -0,0 +1,247 @@
+use std::io::{self, BufRead, Write};
+
+use thiserror::Error;
+
+use super::events::EventError;
+use super::push::ValuePushParser;
+use super::syntactic_analyzer::JsonValue;
+
+// RFC 7464 record separator, every JSON text of a sequence starts with it
+pub const RECORD_SEPARATOR: u8 = 0x1e;
+
+// Records are counted from 1
+#[derive(Debug, Clone, Error, PartialEq)]
+pub enum SequenceError {
+    #[error("Record {0} is truncated")]
+    Truncated(usize),
+
+    #[error("Record {0}: {1}")]
+    Syntax(usize, EventError),
+
+    #[error("Data found before the first record separator")]
+    MissingSeparator,
+
+    #[error("Could not read record {0}: {1}")]
+    Io(usize, String),
+}
+
+// Reads an application/json-seq stream. A malformed or truncated record is reported
+// and reading resumes at the next record separator.
+#[derive(Debug)]
+pub struct JsonSeqReader<R> {
+    reader: R,
+    record: usize,
+    buffer: Vec<u8>,
+    stopped: bool,
+}
+
+impl<R: BufRead> JsonSeqReader<R> {
+    pub fn new(reader: R) -> JsonSeqReader<R> {
+        JsonSeqReader {
+            reader,
+            record: 0,
+            buffer: Vec::new(),
+            stopped: false,
+        }
+    }
+
+    pub fn next_value(&mut self) -> Option<Result<JsonValue, SequenceError>> {
+        while !self.stopped {
+            self.buffer.clear();
+            let read = match self.reader.read_until(RECORD_SEPARATOR, &mut self.buffer) {
+                Ok(read) => read,
+                Err(error) => {
+                    self.stopped = true;
+                    return Some(Err(SequenceError::Io(self.record, error.to_string())));
+                }
+            };
+            if read == 0 {
+                self.stopped = true;
+                break;
+            }
+
+            let mut text = self.buffer.as_slice();
+            if text.last() == Some(&RECORD_SEPARATOR) {
+                text = &text[..text.len() - 1];
+            }
+
+            // what comes before the first separator is not a record
+            if self.record == 0 {
+                self.record = 1;
+                if text.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                return Some(Err(SequenceError::MissingSeparator));
+            }
+
+            let record = self.record;
+            self.record += 1;
+
+            // consecutive separators make empty records, which are skipped
+            if text.is_empty() {
+                continue;
+            }
+
+            let mut parser = ValuePushParser::new();
+            let value = match parser.feed(text) {
+                Ok(_) => parser.finish(),
+                Err(error) => Err(error),
+            };
+
+            // without the final line feed (whitespace may follow it) the writer was cut
+            // off. RFC 7464 only requires checking the texts that may look valid while
+            // missing their end: numbers, e.g. 12 instead of 123, true, false and null.
+            let terminated = text
+                .iter()
+                .rev()
+                .find(|byte| !matches!(byte, b' ' | b'\t' | b'\r'))
+                == Some(&b'\n');
+            return Some(match value {
+                Ok(JsonValue::Number(_) | JsonValue::Boolean(_) | JsonValue::Null)
+                | Err(EventError::UnexpectedEnd(_))
+                    if !terminated =>
+                {
+                    Err(SequenceError::Truncated(record))
+                }
+                value => value.map_err(|error| SequenceError::Syntax(record, error)),
+            });
+        }
+
+        None
+    }
+}
+
+impl<R: BufRead> Iterator for JsonSeqReader<R> {
+    type Item = Result<JsonValue, SequenceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_value()
+    }
+}
+
+#[derive(Debug)]
+pub struct JsonSeqWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> JsonSeqWriter<W> {
+    pub fn new(writer: W) -> JsonSeqWriter<W> {
+        JsonSeqWriter { writer }
+    }
+
+    pub fn write(&mut self, value: &JsonValue) -> io::Result<()> {
+        self.writer.write_all(&[RECORD_SEPARATOR])?;
+        writeln!(self.writer, "{value}")
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+pub fn to_json_seq_string<'a, I>(values: I) -> String
+where
+    I: IntoIterator<Item = &'a JsonValue>,
+{
+    values
+        .into_iter()
+        .map(|value| format!("\u{1e}{value}\n"))
+        .collect()
+}
+
+#[cfg(test)]
+pub mod json_seq_tests {
+    use super::*;
+    use crate::json::syntactic_analyzer::parse_str;
+    use pretty_assertions::assert_eq;
+
+    fn read(input: &str) -> Vec<Result<JsonValue, SequenceError>> {
+        JsonSeqReader::new(input.as_bytes()).collect()
+    }
+
+    #[test]
+    pub fn test_read_sequence() {
+        let input = "\u{1e}{\"a\": 1}\n\u{1e}\u{1e}[true]\n\u{1e}\"text\"\n";
+
+        assert_eq!(
+            read(input),
+            vec![
+                Ok(parse_str(r#"{"a": 1}"#).unwrap()),
+                Ok(parse_str("[true]").unwrap()),
+                Ok(JsonValue::String("text".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_recover_after_bad_records() {
+        let input = "\u{1e}{\"cut\": [1, \u{1e}123\u{1e}{\"a\" 1}\n\u{1e}456\n";
+
+        let results = read(input);
+        assert_eq!(results[0], Err(SequenceError::Truncated(1)));
+        // a number without its line feed may have lost digits
+        assert_eq!(results[1], Err(SequenceError::Truncated(2)));
+        assert!(matches!(
+            results[2],
+            Err(SequenceError::Syntax(
+                3,
+                EventError::UnexpectedToken("number", _)
+            ))
+        ));
+        assert_eq!(results[3], Ok(JsonValue::Number(456.0)));
+        assert_eq!(results.len(), 4);
+    }
+
+    #[test]
+    pub fn test_records_without_line_feed() {
+        let input = "\u{1e}{\"a\": 1}\u{1e}[true] \u{1e}\"text\"\u{1e}1\n  \u{1e}null\r\n\t\u{1e}true \u{1e}12\t";
+
+        assert_eq!(
+            read(input),
+            vec![
+                // other texts cannot parse once their end is missing
+                Ok(parse_str(r#"{"a": 1}"#).unwrap()),
+                Ok(parse_str("[true]").unwrap()),
+                Ok(JsonValue::String("text".to_string())),
+                Ok(JsonValue::Number(1.0)),
+                Ok(JsonValue::Null),
+                Err(SequenceError::Truncated(6)),
+                Err(SequenceError::Truncated(7)),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_data_before_first_separator() {
+        let results = read("garbage\u{1e}1\n");
+
+        assert_eq!(
+            results,
+            vec![
+                Err(SequenceError::MissingSeparator),
+                Ok(JsonValue::Number(1.0))
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_write_sequence() {
+        let values = vec![JsonValue::Null, parse_str(r#"{"k": [1, 2]}"#).unwrap()];
+
+        let mut writer = JsonSeqWriter::new(Vec::new());
+        for value in &values {
+            writer.write(value).unwrap();
+        }
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+
+        assert_eq!(output, "\u{1e}null\n\u{1e}{\"k\":[1,2]}\n");
+        assert_eq!(to_json_seq_string(&values), output);
+
+        let read_back: Vec<JsonValue> = read(&output).into_iter().map(Result::unwrap).collect();
+        assert_eq!(read_back, values);
+    }
+}
@@ -0,0 +1,476 @@
+use std::fmt::{self, Write};
+
+use super::dialect::Dialect;
+use super::events::{Event, EventError, EventReader, Position};
+use super::pointer::JsonPointer;
+use super::scanner::{Comment, Scanner};
+use super::serializer::{write_string, write_value};
+use super::syntactic_analyzer::{JsonPair, JsonValue, ValueBuilder};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JsoncOptions {
+    pub trailing_commas: bool,
+    // attach the comments to the members around them instead of dropping them
+    pub keep_comments: bool,
+}
+
+impl Default for JsoncOptions {
+    fn default() -> Self {
+        JsoncOptions {
+            trailing_commas: true,
+            keep_comments: false,
+        }
+    }
+}
+
+// Where a comment sits relative to the member or element it is attached to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    // on the lines before it, or between its key and its value
+    Before,
+    // on the same line, after it and its comma
+    After,
+    // on the lines after the last member of an object or array, or in an empty one
+    Inside,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachedComment {
+    // the root pointer stands for the whole document
+    pub pointer: JsonPointer,
+    pub placement: Placement,
+    // delimiters included
+    pub text: String,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsoncDocument {
+    pub value: JsonValue,
+    // in input order
+    pub comments: Vec<AttachedComment>,
+}
+
+impl JsoncDocument {
+    pub fn comments_at<'a>(
+        &'a self,
+        pointer: &'a JsonPointer,
+        placement: Placement,
+    ) -> impl Iterator<Item = &'a AttachedComment> + 'a {
+        self.comments
+            .iter()
+            .filter(move |comment| comment.pointer == *pointer && comment.placement == placement)
+    }
+}
+
+// An object or array being read, with the member or element the next comments
+// may belong to
+#[derive(Debug)]
+struct Frame {
+    pointer: JsonPointer,
+    is_object: bool,
+    length: usize,
+    current: Option<JsonPointer>,
+    // the last complete member or element, and the line it ends on
+    previous: Option<(JsonPointer, usize)>,
+}
+
+impl Frame {
+    // A comment right after the previous member, with the next token on a later line
+    fn trails(&self, comment: &Comment, next: Position) -> bool {
+        self.previous
+            .as_ref()
+            .is_some_and(|(_, line)| comment.position.line == *line)
+            && comment.end_line() < next.line
+    }
+}
+
+#[derive(Debug, Default)]
+struct Attacher {
+    stack: Vec<Frame>,
+    comments: Vec<AttachedComment>,
+}
+
+impl Attacher {
+    // Attaches the comments skipped before the event
+    fn event(&mut self, event: &Event, position: Position, comments: Vec<Comment>) {
+        let root = JsonPointer::root();
+
+        for comment in comments {
+            let (pointer, placement) = match (self.stack.last(), event) {
+                (None, _) => (root.clone(), Placement::Before),
+                (Some(frame), Event::EndObject | Event::EndArray) => match &frame.previous {
+                    Some((previous, line)) if comment.position.line == *line => {
+                        (previous.clone(), Placement::After)
+                    }
+                    _ => (frame.pointer.clone(), Placement::Inside),
+                },
+                (Some(frame), _) if frame.trails(&comment, position) => {
+                    let previous = frame.previous.as_ref().map(|(pointer, _)| pointer);
+                    (previous.cloned().unwrap_or_default(), Placement::After)
+                }
+                (Some(frame), event) => {
+                    let next = match event {
+                        Event::Key(key) => frame.pointer.clone().with(key.as_ref()),
+                        _ if frame.is_object => frame.current.clone().unwrap_or_default(),
+                        _ => frame.pointer.clone().with_index(frame.length),
+                    };
+                    (next, Placement::Before)
+                }
+            };
+            self.attach(pointer, placement, &comment);
+        }
+
+        match event {
+            Event::StartObject | Event::StartArray => {
+                let pointer = self.begin_value().unwrap_or(root);
+                self.stack.push(Frame {
+                    pointer,
+                    is_object: *event == Event::StartObject,
+                    length: 0,
+                    current: None,
+                    previous: None,
+                });
+            }
+            Event::Key(key) => {
+                if let Some(frame) = self.stack.last_mut() {
+                    frame.current = Some(frame.pointer.clone().with(key.as_ref()));
+                }
+            }
+            Event::EndObject | Event::EndArray => {
+                self.stack.pop();
+                self.end_value(position.line);
+            }
+            _ => {
+                self.begin_value();
+                self.end_value(position.line);
+            }
+        }
+    }
+
+    // The pointer of the value starting, None for the root
+    fn begin_value(&mut self) -> Option<JsonPointer> {
+        let frame = self.stack.last_mut()?;
+        if !frame.is_object {
+            frame.current = Some(frame.pointer.clone().with_index(frame.length));
+        }
+        frame.current.clone()
+    }
+
+    fn end_value(&mut self, line: usize) {
+        if let Some(frame) = self.stack.last_mut() {
+            frame.previous = frame.current.take().map(|pointer| (pointer, line));
+            frame.length += 1;
+        }
+    }
+
+    // The comments after the root value
+    fn finish(&mut self, comments: Vec<Comment>) {
+        for comment in comments {
+            self.attach(JsonPointer::root(), Placement::After, &comment);
+        }
+    }
+
+    fn attach(&mut self, pointer: JsonPointer, placement: Placement, comment: &Comment) {
+        self.comments.push(AttachedComment {
+            pointer,
+            placement,
+            text: comment.text.to_string(),
+            position: comment.position,
+        });
+    }
+}
+
+// Parses JSON with comments. Comments are dropped unless options.keep_comments is set.
+pub fn parse_jsonc(input: &str, options: &JsoncOptions) -> Result<JsoncDocument, EventError> {
+    let dialect = Dialect {
+        trailing_commas: options.trailing_commas,
+        ..Dialect::JSONC
+    };
+    let mut scanner = Scanner::with_dialect(input, dialect);
+    if options.keep_comments {
+        scanner = scanner.keep_comments();
+    }
+
+    let mut reader = EventReader::from_scanner(scanner);
+    let mut builder = ValueBuilder::new();
+    let mut attacher = Attacher::default();
+    let mut result = None;
+
+    while let Some((event, position)) = reader.next_event()? {
+        let comments = reader.source_mut().take_comments();
+        attacher.event(&event, position, comments);
+        if let Some(value) = builder.push(event) {
+            result = Some(value);
+        }
+    }
+    attacher.finish(reader.source_mut().take_comments());
+
+    Ok(JsoncDocument {
+        value: result.ok_or(EventError::UnexpectedEnd(reader.position()))?,
+        comments: attacher.comments,
+    })
+}
+
+// Writes the document indented by two spaces, each comment next to the member it
+// is attached to
+pub fn to_jsonc_string(document: &JsoncDocument) -> String {
+    let mut out = String::new();
+    write_jsonc(&mut out, document).expect("writing to a String does not fail");
+    out
+}
+
+pub fn write_jsonc<W: Write>(out: &mut W, document: &JsoncDocument) -> fmt::Result {
+    let root = JsonPointer::root();
+    write_before(out, document, &root, 0)?;
+    write_commented(out, document, &document.value, &root, 0)?;
+    write_after(out, document, &root, 0)
+}
+
+fn write_commented<W: Write>(
+    out: &mut W,
+    document: &JsoncDocument,
+    value: &JsonValue,
+    pointer: &JsonPointer,
+    indent: usize,
+) -> fmt::Result {
+    let (open, close, children): (char, char, Vec<(Option<&str>, &JsonValue)>) = match value {
+        JsonValue::JsonArray(elements) => (
+            '[',
+            ']',
+            elements.iter().map(|value| (None, value)).collect(),
+        ),
+        JsonValue::JsonObject(members) => (
+            '{',
+            '}',
+            members
+                .iter()
+                .map(|JsonPair(key, value)| (Some(key.as_str()), value))
+                .collect(),
+        ),
+        scalar => return write_value(out, scalar),
+    };
+
+    let mut inside = document.comments_at(pointer, Placement::Inside).peekable();
+    if children.is_empty() && inside.peek().is_none() {
+        return write!(out, "{open}{close}");
+    }
+
+    out.write_char(open)?;
+    for (index, (key, child)) in children.iter().enumerate() {
+        let child_pointer = match key {
+            Some(key) => pointer.clone().with(*key),
+            None => pointer.clone().with_index(index),
+        };
+        out.write_char('\n')?;
+        write_before(out, document, &child_pointer, indent + 1)?;
+        write_indent(out, indent + 1)?;
+        if let Some(key) = key {
+            write_string(out, key)?;
+            out.write_str(": ")?;
+        }
+        write_commented(out, document, child, &child_pointer, indent + 1)?;
+        if index + 1 < children.len() {
+            out.write_char(',')?;
+        }
+        write_after(out, document, &child_pointer, indent + 1)?;
+    }
+    for comment in inside {
+        out.write_char('\n')?;
+        write_indent(out, indent + 1)?;
+        out.write_str(&comment.text)?;
+    }
+    out.write_char('\n')?;
+    write_indent(out, indent)?;
+    out.write_char(close)
+}
+
+fn write_before<W: Write>(
+    out: &mut W,
+    document: &JsoncDocument,
+    pointer: &JsonPointer,
+    indent: usize,
+) -> fmt::Result {
+    for comment in document.comments_at(pointer, Placement::Before) {
+        write_indent(out, indent)?;
+        out.write_str(&comment.text)?;
+        out.write_char('\n')?;
+    }
+    Ok(())
+}
+
+// Same line comments, a line comment pushes the next one to a line of its own
+fn write_after<W: Write>(
+    out: &mut W,
+    document: &JsoncDocument,
+    pointer: &JsonPointer,
+    indent: usize,
+) -> fmt::Result {
+    let mut line_ended = false;
+    for comment in document.comments_at(pointer, Placement::After) {
+        if line_ended {
+            out.write_char('\n')?;
+            write_indent(out, indent)?;
+        } else {
+            out.write_char(' ')?;
+        }
+        out.write_str(&comment.text)?;
+        line_ended = !comment.text.starts_with("/*");
+    }
+    Ok(())
+}
+
+fn write_indent<W: Write>(out: &mut W, indent: usize) -> fmt::Result {
+    write!(out, "{:width$}", "", width = indent * 2)
+}
+
+#[cfg(test)]
+pub mod jsonc_tests {
+    use super::*;
+    use crate::json::syntactic_analyzer::parse_str;
+    use pretty_assertions::assert_eq;
+
+    const SETTINGS: &str = r#"// editor settings
+{
+    // font
+    "editor.fontSize": 14, // pixels
+    "editor.rulers": [80, /* wrap */ 120,],
+    "files.exclude": {
+        /* nothing yet */
+    },
+    "theme": /* default */ "dark"
+    // more to come
+}
+"#;
+
+    fn comments(document: &JsoncDocument) -> Vec<(String, Placement, &str)> {
+        document
+            .comments
+            .iter()
+            .map(|comment| {
+                (
+                    comment.pointer.to_string(),
+                    comment.placement,
+                    comment.text.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn test_parse_jsonc() {
+        let expected = parse_str(
+            r#"{"editor.fontSize": 14, "editor.rulers": [80, 120], "files.exclude": {}, "theme": "dark"}"#,
+        )
+        .unwrap();
+
+        let document = parse_jsonc(SETTINGS, &JsoncOptions::default()).unwrap();
+        assert_eq!(document.value, expected);
+        assert_eq!(document.comments, vec![]);
+
+        assert!(parse_str(SETTINGS).is_err());
+        assert!(matches!(
+            parse_jsonc(
+                SETTINGS,
+                &JsoncOptions {
+                    trailing_commas: false,
+                    ..JsoncOptions::default()
+                }
+            ),
+            Err(EventError::UnexpectedToken("']'", _))
+        ));
+        // JSONC is not JSON5
+        assert!(parse_jsonc("{key: 1}", &JsoncOptions::default()).is_err());
+        assert!(matches!(
+            parse_jsonc("[1] /* open", &JsoncOptions::default()),
+            Err(EventError::UnterminatedComment(_))
+        ));
+    }
+
+    #[test]
+    pub fn test_comments_are_attached() {
+        let options = JsoncOptions {
+            keep_comments: true,
+            ..JsoncOptions::default()
+        };
+        let document = parse_jsonc(SETTINGS, &options).unwrap();
+
+        assert_eq!(
+            comments(&document),
+            vec![
+                ("".to_string(), Placement::Before, "// editor settings"),
+                ("/editor.fontSize".to_string(), Placement::Before, "// font"),
+                (
+                    "/editor.fontSize".to_string(),
+                    Placement::After,
+                    "// pixels"
+                ),
+                (
+                    "/editor.rulers/1".to_string(),
+                    Placement::Before,
+                    "/* wrap */"
+                ),
+                (
+                    "/files.exclude".to_string(),
+                    Placement::Inside,
+                    "/* nothing yet */"
+                ),
+                ("/theme".to_string(), Placement::Before, "/* default */"),
+                ("".to_string(), Placement::Inside, "// more to come"),
+            ]
+        );
+        assert_eq!(
+            document.comments[2].position,
+            Position {
+                offset: 60,
+                line: 4,
+                column: 28
+            }
+        );
+
+        let document = parse_jsonc("[1, 2] // done\n// really", &options).unwrap();
+        assert_eq!(
+            comments(&document),
+            vec![
+                ("".to_string(), Placement::After, "// done"),
+                ("".to_string(), Placement::After, "// really"),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_comments_are_written_back() {
+        let options = JsoncOptions {
+            keep_comments: true,
+            ..JsoncOptions::default()
+        };
+        let document = parse_jsonc(SETTINGS, &options).unwrap();
+        let written = to_jsonc_string(&document);
+
+        assert_eq!(
+            written,
+            r#"// editor settings
+{
+  // font
+  "editor.fontSize": 14, // pixels
+  "editor.rulers": [
+    80,
+    /* wrap */
+    120
+  ],
+  "files.exclude": {
+    /* nothing yet */
+  },
+  /* default */
+  "theme": "dark"
+  // more to come
+}"#
+        );
+
+        // writing again what was read back gives the same text
+        let reread = parse_jsonc(&written, &options).unwrap();
+        assert_eq!(reread.value, document.value);
+        assert_eq!(comments(&reread), comments(&document));
+        assert_eq!(to_jsonc_string(&reread), written);
+    }
+}
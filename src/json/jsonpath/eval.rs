@@ -0,0 +1,385 @@
+use std::{borrow::Cow, cell::RefCell, collections::HashMap};
+
+use regex::Regex;
+
+use super::parser::{
+    Argument, Comparable, ComparisonOp, Function, FunctionExpr, LogicalExpr, Query, Segment,
+    Selector,
+};
+use super::{NormalizedPath, PathElement};
+use crate::json::syntactic_analyzer::{JsonPair, JsonValue};
+
+// Queries inside filters do not need normalized paths, so the evaluator is generic
+// over how (and whether) a node's location is tracked.
+pub trait Location: Clone {
+    fn name(&self, name: &str) -> Self;
+    fn index(&self, index: usize) -> Self;
+}
+
+impl Location for () {
+    fn name(&self, _name: &str) -> Self {}
+    fn index(&self, _index: usize) -> Self {}
+}
+
+impl Location for NormalizedPath {
+    fn name(&self, name: &str) -> Self {
+        self.child(PathElement::Name(name.to_owned()))
+    }
+
+    fn index(&self, index: usize) -> Self {
+        self.child(PathElement::Index(index))
+    }
+}
+
+// The result of a function extension, one variant per RFC 9535 function type
+enum FunctionResult<'v> {
+    Value(Option<Cow<'v, JsonValue>>),
+    Logical(bool),
+    Nodes(Vec<&'v JsonValue>),
+}
+
+pub struct Evaluator<'a> {
+    root: &'a JsonValue,
+    // compiled I-Regexp patterns, None when the pattern is invalid
+    regexes: RefCell<HashMap<(String, bool), Option<Regex>>>,
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(root: &'a JsonValue) -> Evaluator<'a> {
+        Evaluator {
+            root,
+            regexes: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn select<L: Location>(
+        &self,
+        query: &Query,
+        current: &'a JsonValue,
+        location: L,
+    ) -> Vec<(L, &'a JsonValue)> {
+        let start = if query.relative { current } else { self.root };
+        let mut nodes = vec![(location, start)];
+
+        for segment in query.segments.iter() {
+            let mut selected = Vec::new();
+            for (location, value) in nodes.into_iter() {
+                match segment {
+                    Segment::Child(selectors) => {
+                        self.apply_selectors(selectors, &location, value, &mut selected);
+                    }
+                    Segment::Descendant(selectors) => {
+                        let mut descendants = Vec::new();
+                        collect_descendants(location, value, &mut descendants);
+                        for (location, value) in descendants.into_iter() {
+                            self.apply_selectors(selectors, &location, value, &mut selected);
+                        }
+                    }
+                }
+            }
+            nodes = selected;
+        }
+
+        nodes
+    }
+
+    fn apply_selectors<L: Location>(
+        &self,
+        selectors: &[Selector],
+        location: &L,
+        value: &'a JsonValue,
+        selected: &mut Vec<(L, &'a JsonValue)>,
+    ) {
+        for selector in selectors.iter() {
+            match (selector, value) {
+                (Selector::Name(name), JsonValue::JsonObject(members)) => {
+                    if let Some(JsonPair(_, member)) = members.iter().find(|pair| pair.0 == *name) {
+                        selected.push((location.name(name), member));
+                    }
+                }
+                (Selector::Wildcard, _) => {
+                    selected.extend(children(location, value));
+                }
+                (Selector::Index(index), JsonValue::JsonArray(elements)) => {
+                    let index = if *index < 0 {
+                        elements.len() as i64 + index
+                    } else {
+                        *index
+                    };
+                    if let Some(element) = usize::try_from(index).ok().and_then(|i| elements.get(i))
+                    {
+                        selected.push((location.index(index as usize), element));
+                    }
+                }
+                (Selector::Slice(start, end, step), JsonValue::JsonArray(elements)) => {
+                    for index in slice_indices(elements.len() as i64, *start, *end, *step) {
+                        selected.push((location.index(index), &elements[index]));
+                    }
+                }
+                (Selector::Filter(expr), _) => {
+                    for (child_location, child) in children(location, value) {
+                        if self.test(expr, child) {
+                            selected.push((child_location, child));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn test(&self, expr: &LogicalExpr, current: &'a JsonValue) -> bool {
+        match expr {
+            LogicalExpr::Or(operands) => operands.iter().any(|expr| self.test(expr, current)),
+            LogicalExpr::And(operands) => operands.iter().all(|expr| self.test(expr, current)),
+            LogicalExpr::Not(operand) => !self.test(operand, current),
+            LogicalExpr::Exists(query) => !self.select(query, current, ()).is_empty(),
+            LogicalExpr::Function(function) => match self.call(function, current) {
+                FunctionResult::Logical(result) => result,
+                FunctionResult::Nodes(nodes) => !nodes.is_empty(),
+                FunctionResult::Value(_) => false,
+            },
+            LogicalExpr::Comparison(left, op, right) => {
+                let left = self.comparable(left, current);
+                let right = self.comparable(right, current);
+                compare(left.as_deref(), *op, right.as_deref())
+            }
+        }
+    }
+
+    fn comparable<'e>(
+        &'e self,
+        comparable: &'e Comparable,
+        current: &'a JsonValue,
+    ) -> Option<Cow<'e, JsonValue>> {
+        match comparable {
+            Comparable::Literal(value) => Some(Cow::Borrowed(value)),
+            Comparable::Query(query) => single(self.select(query, current, ())).map(Cow::Borrowed),
+            Comparable::Function(function) => match self.call(function, current) {
+                FunctionResult::Value(value) => value,
+                _ => None,
+            },
+        }
+    }
+
+    fn argument<'e>(
+        &'e self,
+        argument: &'e Argument,
+        current: &'a JsonValue,
+    ) -> FunctionResult<'e> {
+        match argument {
+            Argument::Literal(value) => FunctionResult::Value(Some(Cow::Borrowed(value))),
+            Argument::Query(query) => FunctionResult::Nodes(
+                self.select(query, current, ())
+                    .into_iter()
+                    .map(|(_, value)| value)
+                    .collect(),
+            ),
+            Argument::Logical(expr) => FunctionResult::Logical(self.test(expr, current)),
+            Argument::Function(function) => self.call(function, current),
+        }
+    }
+
+    fn call<'e>(
+        &'e self,
+        function: &'e FunctionExpr,
+        current: &'a JsonValue,
+    ) -> FunctionResult<'e> {
+        let mut arguments = function
+            .arguments
+            .iter()
+            .map(|argument| self.argument(argument, current));
+
+        match function.function {
+            Function::Length => {
+                let length = match as_value(arguments.next()).as_deref() {
+                    Some(JsonValue::String(string)) => Some(string.chars().count()),
+                    Some(JsonValue::JsonArray(elements)) => Some(elements.len()),
+                    Some(JsonValue::JsonObject(members)) => Some(members.len()),
+                    _ => None,
+                };
+                FunctionResult::Value(
+                    length.map(|length| Cow::Owned(JsonValue::Number(length as f32))),
+                )
+            }
+            Function::Count => {
+                let count = match arguments.next() {
+                    Some(FunctionResult::Nodes(nodes)) => nodes.len(),
+                    _ => 0,
+                };
+                FunctionResult::Value(Some(Cow::Owned(JsonValue::Number(count as f32))))
+            }
+            Function::Value => match arguments.next() {
+                Some(FunctionResult::Nodes(nodes)) if nodes.len() == 1 => {
+                    FunctionResult::Value(Some(Cow::Borrowed(nodes[0])))
+                }
+                _ => FunctionResult::Value(None),
+            },
+            Function::Match | Function::Search => {
+                let subject = as_value(arguments.next());
+                let pattern = as_value(arguments.next());
+                let (Some(JsonValue::String(subject)), Some(JsonValue::String(pattern))) =
+                    (subject.as_deref(), pattern.as_deref())
+                else {
+                    return FunctionResult::Logical(false);
+                };
+
+                let anchored = function.function == Function::Match;
+                FunctionResult::Logical(self.regex_matches(pattern, anchored, subject))
+            }
+        }
+    }
+
+    fn regex_matches(&self, pattern: &str, anchored: bool, subject: &str) -> bool {
+        let mut regexes = self.regexes.borrow_mut();
+        let regex = regexes
+            .entry((pattern.to_owned(), anchored))
+            .or_insert_with(|| {
+                let translated = translate_iregexp(pattern);
+                let translated = if anchored {
+                    format!(r"\A(?:{translated})\z")
+                } else {
+                    translated
+                };
+                Regex::new(&translated).ok()
+            });
+
+        match regex {
+            Some(regex) => regex.is_match(subject),
+            None => false,
+        }
+    }
+}
+
+fn as_value(result: Option<FunctionResult<'_>>) -> Option<Cow<'_, JsonValue>> {
+    match result {
+        Some(FunctionResult::Value(value)) => value,
+        Some(FunctionResult::Nodes(nodes)) if nodes.len() == 1 => Some(Cow::Borrowed(nodes[0])),
+        _ => None,
+    }
+}
+
+fn single<L>(mut nodes: Vec<(L, &JsonValue)>) -> Option<&JsonValue> {
+    if nodes.len() != 1 {
+        return None;
+    }
+    nodes.pop().map(|(_, value)| value)
+}
+
+fn children<'a, L: Location>(location: &L, value: &'a JsonValue) -> Vec<(L, &'a JsonValue)> {
+    match value {
+        JsonValue::JsonObject(members) => members
+            .iter()
+            .map(|JsonPair(name, member)| (location.name(name), member))
+            .collect(),
+        JsonValue::JsonArray(elements) => elements
+            .iter()
+            .enumerate()
+            .map(|(index, element)| (location.index(index), element))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// The node itself followed by all of its descendants, parents before children
+fn collect_descendants<'a, L: Location>(
+    location: L,
+    value: &'a JsonValue,
+    result: &mut Vec<(L, &'a JsonValue)>,
+) {
+    let nested = children(&location, value);
+    result.push((location, value));
+    for (location, child) in nested.into_iter() {
+        collect_descendants(location, child, result);
+    }
+}
+
+// Slice bounds normalization from RFC 9535, section 2.3.4.2.2
+fn slice_indices(
+    length: i64,
+    start: Option<i64>,
+    end: Option<i64>,
+    step: Option<i64>,
+) -> Vec<usize> {
+    let step = step.unwrap_or(1);
+    let normalize = |index: i64| if index >= 0 { index } else { length + index };
+    let mut indices = Vec::new();
+
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, length);
+        let upper = normalize(end.unwrap_or(length)).clamp(0, length);
+        let mut index = lower;
+        while index < upper {
+            indices.push(index as usize);
+            index += step;
+        }
+    } else if step < 0 {
+        let upper = normalize(start.unwrap_or(length - 1)).clamp(-1, length - 1);
+        let lower = normalize(end.unwrap_or(-length - 1)).clamp(-1, length - 1);
+        let mut index = upper;
+        while lower < index {
+            indices.push(index as usize);
+            index += step;
+        }
+    }
+
+    indices
+}
+
+fn compare(left: Option<&JsonValue>, op: ComparisonOp, right: Option<&JsonValue>) -> bool {
+    match op {
+        ComparisonOp::Equal => equal(left, right),
+        ComparisonOp::NotEqual => !equal(left, right),
+        ComparisonOp::Less => less(left, right),
+        ComparisonOp::LessOrEqual => less(left, right) || equal(left, right),
+        ComparisonOp::Greater => less(right, left),
+        ComparisonOp::GreaterOrEqual => less(right, left) || equal(left, right),
+    }
+}
+
+fn equal(left: Option<&JsonValue>, right: Option<&JsonValue>) -> bool {
+    match (left, right) {
+        (None, None) => true,
+        (Some(left), Some(right)) => left.equivalent(right),
+        _ => false,
+    }
+}
+
+fn less(left: Option<&JsonValue>, right: Option<&JsonValue>) -> bool {
+    match (left, right) {
+        (Some(JsonValue::Number(left)), Some(JsonValue::Number(right))) => left < right,
+        (Some(JsonValue::String(left)), Some(JsonValue::String(right))) => left < right,
+        _ => false,
+    }
+}
+
+// I-Regexp (RFC 9485) is close to the regex crate syntax, except that '.' must not
+// match line terminators outside of character classes.
+fn translate_iregexp(pattern: &str) -> String {
+    let mut translated = String::with_capacity(pattern.len());
+    let mut in_class = false;
+    let mut characters = pattern.chars();
+
+    while let Some(ch) = characters.next() {
+        match ch {
+            '\\' => {
+                translated.push(ch);
+                if let Some(escaped) = characters.next() {
+                    translated.push(escaped);
+                }
+            }
+            '[' if !in_class => {
+                in_class = true;
+                translated.push(ch);
+            }
+            ']' if in_class => {
+                in_class = false;
+                translated.push(ch);
+            }
+            '.' if !in_class => translated.push_str(r"[^\n\r]"),
+            ch => translated.push(ch),
+        }
+    }
+
+    translated
+}
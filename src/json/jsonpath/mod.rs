@@ -0,0 +1,427 @@
+use std::fmt;
+
+use thiserror::Error;
+
+use super::pointer::JsonPointer;
+use super::syntactic_analyzer::JsonValue;
+
+mod eval;
+mod parser;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum PathError {
+    #[error("Unexpected end of the JSONPath query")]
+    UnexpectedEnd,
+
+    #[error("Unexpected character '{1}' at position {0}")]
+    UnexpectedCharacter(usize, char),
+
+    #[error("Invalid string literal at position {0}")]
+    InvalidString(usize),
+
+    #[error("Invalid integer at position {0}")]
+    InvalidInteger(usize),
+
+    #[error("Invalid number literal at position {0}")]
+    InvalidNumber(usize),
+
+    #[error("Unknown function extension: {0}")]
+    UnknownFunction(String),
+
+    #[error("Function {0} is not well-typed in this position")]
+    NotWellTyped(String),
+
+    #[error("Only singular queries can be compared, at position {0}")]
+    NonSingularQuery(usize),
+
+    #[error("Literal at position {0} must be part of a comparison")]
+    UncomparedLiteral(usize),
+}
+
+// One step of a normalized path: either a member name or an array index
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathElement {
+    Name(String),
+    Index(usize),
+}
+
+// The canonical location of a node, rendered as in RFC 9535 section 2.7, e.g. $['a'][0]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct NormalizedPath(Vec<PathElement>);
+
+impl NormalizedPath {
+    pub fn elements(&self) -> &[PathElement] {
+        &self.0
+    }
+
+    pub fn to_pointer(&self) -> JsonPointer {
+        self.0
+            .iter()
+            .map(|element| match element {
+                PathElement::Name(name) => name.clone(),
+                PathElement::Index(index) => index.to_string(),
+            })
+            .collect()
+    }
+
+    fn child(&self, element: PathElement) -> NormalizedPath {
+        let mut elements = self.0.clone();
+        elements.push(element);
+        NormalizedPath(elements)
+    }
+}
+
+impl fmt::Display for NormalizedPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for element in self.0.iter() {
+            match element {
+                PathElement::Index(index) => write!(f, "[{index}]")?,
+                PathElement::Name(name) => {
+                    write!(f, "['")?;
+                    for ch in name.chars() {
+                        match ch {
+                            '\u{8}' => write!(f, "\\b")?,
+                            '\u{c}' => write!(f, "\\f")?,
+                            '\n' => write!(f, "\\n")?,
+                            '\r' => write!(f, "\\r")?,
+                            '\t' => write!(f, "\\t")?,
+                            '\'' => write!(f, "\\'")?,
+                            '\\' => write!(f, "\\\\")?,
+                            ch if (ch as u32) < 0x20 => write!(f, "\\u{:04x}", ch as u32)?,
+                            ch => write!(f, "{ch}")?,
+                        }
+                    }
+                    write!(f, "']")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// A value selected by a query together with the location it was found at
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node<'a> {
+    pub path: NormalizedPath,
+    pub value: &'a JsonValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    query: parser::Query,
+}
+
+impl JsonPath {
+    pub fn parse(query: &str) -> Result<JsonPath, PathError> {
+        Ok(JsonPath {
+            query: parser::parse(query)?,
+        })
+    }
+
+    pub fn query<'a>(&self, root: &'a JsonValue) -> Vec<Node<'a>> {
+        eval::Evaluator::new(root)
+            .select(&self.query, root, NormalizedPath::default())
+            .into_iter()
+            .map(|(path, value)| Node { path, value })
+            .collect()
+    }
+
+    pub fn query_values<'a>(&self, root: &'a JsonValue) -> Vec<&'a JsonValue> {
+        eval::Evaluator::new(root)
+            .select(&self.query, root, ())
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect()
+    }
+}
+
+pub fn query<'a>(query: &str, root: &'a JsonValue) -> Result<Vec<Node<'a>>, PathError> {
+    Ok(JsonPath::parse(query)?.query(root))
+}
+
+#[cfg(test)]
+pub mod jsonpath_tests {
+    use super::*;
+    use crate::json::syntactic_analyzer::JsonPair;
+    use pretty_assertions::assert_eq;
+
+    fn string(value: &str) -> JsonValue {
+        JsonValue::String(value.to_string())
+    }
+
+    fn object(members: Vec<(&str, JsonValue)>) -> JsonValue {
+        JsonValue::JsonObject(
+            members
+                .into_iter()
+                .map(|(key, value)| JsonPair(key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    fn book(
+        category: &str,
+        author: &str,
+        title: &str,
+        isbn: Option<&str>,
+        price: f32,
+    ) -> JsonValue {
+        let mut members = vec![
+            ("category", string(category)),
+            ("author", string(author)),
+            ("title", string(title)),
+        ];
+        if let Some(isbn) = isbn {
+            members.push(("isbn", string(isbn)));
+        }
+        members.push(("price", JsonValue::Number(price)));
+        object(members)
+    }
+
+    // The bookstore example from RFC 9535, section 1.5
+    fn store() -> JsonValue {
+        object(vec![(
+            "store",
+            object(vec![
+                (
+                    "book",
+                    JsonValue::JsonArray(vec![
+                        book(
+                            "reference",
+                            "Nigel Rees",
+                            "Sayings of the Century",
+                            None,
+                            8.95,
+                        ),
+                        book("fiction", "Evelyn Waugh", "Sword of Honour", None, 12.99),
+                        book(
+                            "fiction",
+                            "Herman Melville",
+                            "Moby Dick",
+                            Some("0-553-21311-3"),
+                            8.99,
+                        ),
+                        book(
+                            "fiction",
+                            "J. R. R. Tolkien",
+                            "The Lord of the Rings",
+                            Some("0-395-19395-8"),
+                            22.99,
+                        ),
+                    ]),
+                ),
+                (
+                    "bicycle",
+                    object(vec![
+                        ("color", string("red")),
+                        ("price", JsonValue::Number(399.0)),
+                    ]),
+                ),
+            ]),
+        )])
+    }
+
+    fn paths(query_text: &str, root: &JsonValue) -> Vec<String> {
+        query(query_text, root)
+            .unwrap()
+            .into_iter()
+            .map(|node| node.path.to_string())
+            .collect()
+    }
+
+    #[test]
+    pub fn test_child_and_wildcard_selectors() {
+        let root = store();
+
+        assert_eq!(
+            paths("$.store.book[*].author", &root),
+            vec![
+                "$['store']['book'][0]['author']",
+                "$['store']['book'][1]['author']",
+                "$['store']['book'][2]['author']",
+                "$['store']['book'][3]['author']",
+            ]
+        );
+        assert_eq!(
+            paths("$.store.*", &root),
+            vec!["$['store']['book']", "$['store']['bicycle']"]
+        );
+        assert_eq!(
+            query("$['store'][\"bicycle\"].color", &root).unwrap()[0].value,
+            &string("red")
+        );
+    }
+
+    #[test]
+    pub fn test_descendant_selectors() {
+        let root = store();
+
+        assert_eq!(paths("$..author", &root).len(), 4);
+        assert_eq!(
+            paths("$.store..price", &root),
+            vec![
+                "$['store']['book'][0]['price']",
+                "$['store']['book'][1]['price']",
+                "$['store']['book'][2]['price']",
+                "$['store']['book'][3]['price']",
+                "$['store']['bicycle']['price']",
+            ]
+        );
+        assert_eq!(paths("$..*", &root).len(), 27);
+    }
+
+    #[test]
+    pub fn test_index_slice_and_union() {
+        let root = store();
+
+        assert_eq!(paths("$..book[2]", &root), vec!["$['store']['book'][2]"]);
+        assert_eq!(paths("$..book[-1]", &root), vec!["$['store']['book'][3]"]);
+        assert_eq!(
+            paths("$..book[0,1]", &root),
+            vec!["$['store']['book'][0]", "$['store']['book'][1]"]
+        );
+        assert_eq!(
+            paths("$..book[:2]", &root),
+            vec!["$['store']['book'][0]", "$['store']['book'][1]"]
+        );
+        assert_eq!(
+            paths("$..book[::-2]", &root),
+            vec!["$['store']['book'][3]", "$['store']['book'][1]"]
+        );
+        assert_eq!(paths("$..book[1:3:0]", &root), Vec::<String>::new());
+        assert_eq!(
+            paths("$.store.book[0]['title', 'price']", &root),
+            vec![
+                "$['store']['book'][0]['title']",
+                "$['store']['book'][0]['price']"
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_filters() {
+        let root = store();
+
+        assert_eq!(
+            paths("$..book[?@.isbn]", &root),
+            vec!["$['store']['book'][2]", "$['store']['book'][3]"]
+        );
+        assert_eq!(
+            paths("$..book[?@.price<10]", &root),
+            vec!["$['store']['book'][0]", "$['store']['book'][2]"]
+        );
+        assert_eq!(
+            paths(
+                "$..book[?@.category == 'fiction' && !(@.price > 20)].title",
+                &root
+            ),
+            vec![
+                "$['store']['book'][1]['title']",
+                "$['store']['book'][2]['title']"
+            ]
+        );
+        assert_eq!(
+            paths("$.store.book[?@.price > $.store.bicycle.price]", &root),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            paths("$..*[?@ == 'red']", &root),
+            vec!["$['store']['bicycle']['color']"]
+        );
+    }
+
+    #[test]
+    pub fn test_functions() {
+        let root = store();
+
+        assert_eq!(
+            paths("$.store.book[?length(@.title) < 10]", &root),
+            vec!["$['store']['book'][2]"]
+        );
+        assert_eq!(
+            paths("$.store[?count(@.*) == 2]", &root),
+            vec!["$['store']['bicycle']"]
+        );
+        assert_eq!(
+            paths("$.store.book[?match(@.author, 'J.*')]", &root),
+            vec!["$['store']['book'][3]"]
+        );
+        assert_eq!(
+            paths("$.store.book[?search(@.title, 'of')]", &root),
+            vec![
+                "$['store']['book'][0]",
+                "$['store']['book'][1]",
+                "$['store']['book'][3]"
+            ]
+        );
+        assert_eq!(
+            paths("$.store.book[?value(@..isbn) == '0-553-21311-3']", &root),
+            vec!["$['store']['book'][2]"]
+        );
+    }
+
+    #[test]
+    pub fn test_comparison_semantics() {
+        let root = JsonValue::JsonArray(vec![
+            object(vec![("a", JsonValue::Number(1.0)), ("b", JsonValue::Null)]),
+            object(vec![("b", JsonValue::Null), ("a", JsonValue::Number(1.0))]),
+            object(vec![]),
+        ]);
+
+        assert_eq!(paths("$[?@ == $[0]]", &root), vec!["$[0]", "$[1]"]);
+        assert_eq!(paths("$[?@.missing == @.other]", &root).len(), 3);
+        assert_eq!(paths("$[?@.b == null]", &root), vec!["$[0]", "$[1]"]);
+        assert_eq!(paths("$[?@.a <= 1]", &root), vec!["$[0]", "$[1]"]);
+        assert_eq!(paths("$[?@.b < 1]", &root), Vec::<String>::new());
+    }
+
+    #[test]
+    pub fn test_normalized_path_rendering() {
+        let root = object(vec![(
+            "it's\n",
+            JsonValue::JsonArray(vec![JsonValue::Null]),
+        )]);
+        let nodes = query("$.*[0]", &root).unwrap();
+
+        assert_eq!(nodes[0].path.to_string(), "$['it\\'s\\n'][0]");
+        assert_eq!(nodes[0].path.to_pointer().to_string(), "/it's\n/0");
+    }
+
+    #[test]
+    pub fn test_invalid_queries() {
+        assert_eq!(JsonPath::parse("").err(), Some(PathError::UnexpectedEnd));
+        assert_eq!(
+            JsonPath::parse("$.a.").err(),
+            Some(PathError::UnexpectedEnd)
+        );
+        assert_eq!(
+            JsonPath::parse("$[01]").err(),
+            Some(PathError::InvalidInteger(2))
+        );
+        assert_eq!(
+            JsonPath::parse("$[?@.* == 1]").err(),
+            Some(PathError::NonSingularQuery(3))
+        );
+        assert_eq!(
+            JsonPath::parse("$[?foo(@)]").err(),
+            Some(PathError::UnknownFunction("foo".to_string()))
+        );
+        assert_eq!(
+            JsonPath::parse("$[?length(@.a)]").err(),
+            Some(PathError::NotWellTyped("length".to_string()))
+        );
+        assert_eq!(
+            JsonPath::parse("$[?count(1) == 1]").err(),
+            Some(PathError::NotWellTyped("count".to_string()))
+        );
+        assert_eq!(
+            JsonPath::parse("$[?1]").err(),
+            Some(PathError::UncomparedLiteral(3))
+        );
+        assert_eq!(
+            JsonPath::parse(" $").err(),
+            Some(PathError::UnexpectedCharacter(0, ' '))
+        );
+    }
+}
@@ -0,0 +1,746 @@
+use super::PathError;
+use crate::json::syntactic_analyzer::JsonValue;
+
+// Integers in queries are limited to the I-JSON exact range
+const MAX_INT: i64 = (1 << 53) - 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    // true for queries starting with '@', false for '$'
+    pub relative: bool,
+    pub segments: Vec<Segment>,
+}
+
+impl Query {
+    // A singular query selects at most one node: only name and index child selectors
+    pub fn is_singular(&self) -> bool {
+        self.segments.iter().all(|segment| match segment {
+            Segment::Child(selectors) => {
+                selectors.len() == 1
+                    && matches!(selectors[0], Selector::Name(_) | Selector::Index(_))
+            }
+            Segment::Descendant(_) => false,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Child(Vec<Selector>),
+    Descendant(Vec<Selector>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice(Option<i64>, Option<i64>, Option<i64>),
+    Filter(LogicalExpr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogicalExpr {
+    Or(Vec<LogicalExpr>),
+    And(Vec<LogicalExpr>),
+    Not(Box<LogicalExpr>),
+    Comparison(Comparable, ComparisonOp, Comparable),
+    Exists(Query),
+    Function(FunctionExpr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Comparable {
+    Literal(JsonValue),
+    Query(Query),
+    Function(FunctionExpr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComparisonOp {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FunctionType {
+    Value,
+    Logical,
+    Nodes,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Function {
+    Length,
+    Count,
+    Match,
+    Search,
+    Value,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        match name {
+            "length" => Some(Function::Length),
+            "count" => Some(Function::Count),
+            "match" => Some(Function::Match),
+            "search" => Some(Function::Search),
+            "value" => Some(Function::Value),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Function::Length => "length",
+            Function::Count => "count",
+            Function::Match => "match",
+            Function::Search => "search",
+            Function::Value => "value",
+        }
+    }
+
+    fn parameters(&self) -> &'static [FunctionType] {
+        match self {
+            Function::Length => &[FunctionType::Value],
+            Function::Count | Function::Value => &[FunctionType::Nodes],
+            Function::Match | Function::Search => &[FunctionType::Value, FunctionType::Value],
+        }
+    }
+
+    pub fn result_type(&self) -> FunctionType {
+        match self {
+            Function::Length | Function::Count | Function::Value => FunctionType::Value,
+            Function::Match | Function::Search => FunctionType::Logical,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionExpr {
+    pub function: Function,
+    pub arguments: Vec<Argument>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Argument {
+    Literal(JsonValue),
+    Query(Query),
+    Logical(LogicalExpr),
+    Function(FunctionExpr),
+}
+
+pub fn parse(query: &str) -> Result<Query, PathError> {
+    let mut parser = Parser {
+        input: query,
+        position: 0,
+    };
+
+    let result = parser.parse_query()?;
+    match parser.peek() {
+        None => Ok(result),
+        Some(ch) => Err(PathError::UnexpectedCharacter(parser.position, ch)),
+    }
+}
+
+struct Parser<'q> {
+    input: &'q str,
+    position: usize,
+}
+
+impl<'q> Parser<'q> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.input[self.position..].chars().nth(n)
+    }
+
+    fn rest(&self) -> &'q str {
+        &self.input[self.position..]
+    }
+
+    fn next(&mut self) -> Result<char, PathError> {
+        let ch = self.peek().ok_or(PathError::UnexpectedEnd)?;
+        self.position += ch.len_utf8();
+        Ok(ch)
+    }
+
+    fn unexpected(&self) -> PathError {
+        match self.peek() {
+            Some(ch) => PathError::UnexpectedCharacter(self.position, ch),
+            None => PathError::UnexpectedEnd,
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), PathError> {
+        if self.peek() == Some(expected) {
+            self.position += expected.len_utf8();
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn eat(&mut self, expected: &str) -> bool {
+        if self.rest().starts_with(expected) {
+            self.position += expected.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_blank(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn parse_query(&mut self) -> Result<Query, PathError> {
+        let relative = match self.peek() {
+            Some('$') => false,
+            Some('@') => true,
+            _ => return Err(self.unexpected()),
+        };
+        self.position += 1;
+
+        let mut segments = Vec::new();
+        loop {
+            let checkpoint = self.position;
+            self.skip_blank();
+            match self.peek() {
+                Some('.') | Some('[') => segments.push(self.parse_segment()?),
+                _ => {
+                    // blank space is only part of the query when a segment follows it
+                    self.position = checkpoint;
+                    break;
+                }
+            }
+        }
+
+        Ok(Query { relative, segments })
+    }
+
+    fn parse_segment(&mut self) -> Result<Segment, PathError> {
+        if self.eat("..") {
+            return match self.peek() {
+                Some('[') => Ok(Segment::Descendant(self.parse_bracketed_selection()?)),
+                Some('*') => {
+                    self.position += 1;
+                    Ok(Segment::Descendant(vec![Selector::Wildcard]))
+                }
+                _ => Ok(Segment::Descendant(vec![Selector::Name(
+                    self.parse_member_name()?,
+                )])),
+            };
+        }
+
+        if self.eat(".") {
+            if self.eat("*") {
+                return Ok(Segment::Child(vec![Selector::Wildcard]));
+            }
+
+            return Ok(Segment::Child(vec![Selector::Name(
+                self.parse_member_name()?,
+            )]));
+        }
+
+        Ok(Segment::Child(self.parse_bracketed_selection()?))
+    }
+
+    fn parse_member_name(&mut self) -> Result<String, PathError> {
+        let start = self.position;
+        match self.peek() {
+            Some(ch) if is_name_first(ch) => self.position += ch.len_utf8(),
+            _ => return Err(self.unexpected()),
+        }
+
+        while let Some(ch) = self.peek() {
+            if !is_name_first(ch) && !ch.is_ascii_digit() {
+                break;
+            }
+            self.position += ch.len_utf8();
+        }
+
+        Ok(self.input[start..self.position].to_owned())
+    }
+
+    fn parse_bracketed_selection(&mut self) -> Result<Vec<Selector>, PathError> {
+        self.expect('[')?;
+        let mut selectors = Vec::new();
+
+        loop {
+            self.skip_blank();
+            selectors.push(self.parse_selector()?);
+            self.skip_blank();
+
+            match self.next()? {
+                ',' => continue,
+                ']' => break,
+                ch => {
+                    return Err(PathError::UnexpectedCharacter(
+                        self.position - ch.len_utf8(),
+                        ch,
+                    ))
+                }
+            }
+        }
+
+        Ok(selectors)
+    }
+
+    fn parse_selector(&mut self) -> Result<Selector, PathError> {
+        match self.peek() {
+            Some('\'') | Some('"') => Ok(Selector::Name(self.parse_string_literal()?)),
+            Some('*') => {
+                self.position += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                self.position += 1;
+                self.skip_blank();
+                Ok(Selector::Filter(self.parse_logical_or()?))
+            }
+            Some(ch) if ch == '-' || ch == ':' || ch.is_ascii_digit() => {
+                self.parse_index_or_slice()
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_index_or_slice(&mut self) -> Result<Selector, PathError> {
+        let start = self.parse_optional_int()?;
+        self.skip_blank();
+        if self.peek() != Some(':') {
+            return match start {
+                Some(index) => Ok(Selector::Index(index)),
+                None => Err(self.unexpected()),
+            };
+        }
+
+        self.position += 1;
+        self.skip_blank();
+        let end = self.parse_optional_int()?;
+        self.skip_blank();
+
+        let mut step = None;
+        if self.eat(":") {
+            self.skip_blank();
+            step = self.parse_optional_int()?;
+        }
+
+        Ok(Selector::Slice(start, end, step))
+    }
+
+    fn parse_optional_int(&mut self) -> Result<Option<i64>, PathError> {
+        match self.peek() {
+            Some(ch) if ch == '-' || ch.is_ascii_digit() => Ok(Some(self.parse_int()?)),
+            _ => Ok(None),
+        }
+    }
+
+    fn parse_int(&mut self) -> Result<i64, PathError> {
+        let start = self.position;
+        self.eat("-");
+        let digits_start = self.position;
+        while let Some(ch) = self.peek() {
+            if !ch.is_ascii_digit() {
+                break;
+            }
+            self.position += 1;
+        }
+
+        let digits = &self.input[digits_start..self.position];
+        let negative = digits_start != start;
+        let leading_zero = digits.len() > 1 && digits.starts_with('0');
+        let negative_zero = negative && digits == "0";
+        if digits.is_empty() || leading_zero || negative_zero {
+            return Err(PathError::InvalidInteger(start));
+        }
+
+        match self.input[start..self.position].parse::<i64>() {
+            Ok(value) if (-MAX_INT..=MAX_INT).contains(&value) => Ok(value),
+            _ => Err(PathError::InvalidInteger(start)),
+        }
+    }
+
+    fn parse_string_literal(&mut self) -> Result<String, PathError> {
+        let start = self.position;
+        let quote = self.next()?;
+        let mut result = String::new();
+
+        loop {
+            let ch = self.next().map_err(|_| PathError::InvalidString(start))?;
+            match ch {
+                ch if ch == quote => break,
+                '\\' => {
+                    let escaped = self.next().map_err(|_| PathError::InvalidString(start))?;
+                    match escaped {
+                        'b' => result.push('\u{8}'),
+                        'f' => result.push('\u{c}'),
+                        'n' => result.push('\n'),
+                        'r' => result.push('\r'),
+                        't' => result.push('\t'),
+                        '/' => result.push('/'),
+                        '\\' => result.push('\\'),
+                        'u' => result.push(self.parse_unicode_escape(start)?),
+                        ch if ch == quote => result.push(ch),
+                        _ => return Err(PathError::InvalidString(start)),
+                    }
+                }
+                ch if (ch as u32) < 0x20 => return Err(PathError::InvalidString(start)),
+                ch => result.push(ch),
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn parse_hex4(&mut self, start: usize) -> Result<u32, PathError> {
+        let digits = self
+            .rest()
+            .get(..4)
+            .filter(|digits| digits.chars().all(|ch| ch.is_ascii_hexdigit()))
+            .ok_or(PathError::InvalidString(start))?;
+        self.position += 4;
+
+        u32::from_str_radix(digits, 16).map_err(|_| PathError::InvalidString(start))
+    }
+
+    fn parse_unicode_escape(&mut self, start: usize) -> Result<char, PathError> {
+        let high = self.parse_hex4(start)?;
+        let code = match high {
+            0xD800..=0xDBFF => {
+                if !self.eat("\\u") {
+                    return Err(PathError::InvalidString(start));
+                }
+                let low = self.parse_hex4(start)?;
+                if !(0xDC00..=0xDFFF).contains(&low) {
+                    return Err(PathError::InvalidString(start));
+                }
+                0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+            }
+            0xDC00..=0xDFFF => return Err(PathError::InvalidString(start)),
+            code => code,
+        };
+
+        char::from_u32(code).ok_or(PathError::InvalidString(start))
+    }
+
+    fn parse_logical_or(&mut self) -> Result<LogicalExpr, PathError> {
+        let mut operands = vec![self.parse_logical_and()?];
+        loop {
+            let checkpoint = self.position;
+            self.skip_blank();
+            if !self.eat("||") {
+                self.position = checkpoint;
+                break;
+            }
+            self.skip_blank();
+            operands.push(self.parse_logical_and()?);
+        }
+
+        if operands.len() == 1 {
+            return Ok(operands.remove(0));
+        }
+        Ok(LogicalExpr::Or(operands))
+    }
+
+    fn parse_logical_and(&mut self) -> Result<LogicalExpr, PathError> {
+        let mut operands = vec![self.parse_basic_expr()?];
+        loop {
+            let checkpoint = self.position;
+            self.skip_blank();
+            if !self.eat("&&") {
+                self.position = checkpoint;
+                break;
+            }
+            self.skip_blank();
+            operands.push(self.parse_basic_expr()?);
+        }
+
+        if operands.len() == 1 {
+            return Ok(operands.remove(0));
+        }
+        Ok(LogicalExpr::And(operands))
+    }
+
+    fn parse_basic_expr(&mut self) -> Result<LogicalExpr, PathError> {
+        if self.peek() == Some('!') && self.peek_nth(1) != Some('=') {
+            self.position += 1;
+            self.skip_blank();
+            let operand = match self.peek() {
+                Some('(') => self.parse_paren_expr()?,
+                _ => self.parse_test_expr()?,
+            };
+            return Ok(LogicalExpr::Not(Box::new(operand)));
+        }
+
+        if self.peek() == Some('(') {
+            return self.parse_paren_expr();
+        }
+
+        let start = self.position;
+        let left = self.parse_comparable()?;
+
+        let checkpoint = self.position;
+        self.skip_blank();
+        let Some(op) = self.parse_comparison_op() else {
+            self.position = checkpoint;
+            return match left {
+                Comparable::Query(query) => Ok(LogicalExpr::Exists(query)),
+                Comparable::Function(function) => {
+                    if function.function.result_type() == FunctionType::Value {
+                        return Err(PathError::NotWellTyped(function.function.name().to_owned()));
+                    }
+                    Ok(LogicalExpr::Function(function))
+                }
+                Comparable::Literal(_) => Err(PathError::UncomparedLiteral(start)),
+            };
+        };
+
+        self.skip_blank();
+        let right_start = self.position;
+        let right = self.parse_comparable()?;
+
+        check_comparable(&left, start)?;
+        check_comparable(&right, right_start)?;
+
+        Ok(LogicalExpr::Comparison(left, op, right))
+    }
+
+    fn parse_paren_expr(&mut self) -> Result<LogicalExpr, PathError> {
+        self.expect('(')?;
+        self.skip_blank();
+        let expr = self.parse_logical_or()?;
+        self.skip_blank();
+        self.expect(')')?;
+
+        Ok(expr)
+    }
+
+    fn parse_test_expr(&mut self) -> Result<LogicalExpr, PathError> {
+        match self.peek() {
+            Some('@') | Some('$') => Ok(LogicalExpr::Exists(self.parse_query()?)),
+            _ => {
+                let function = self.parse_function_expr()?;
+                if function.function.result_type() == FunctionType::Value {
+                    return Err(PathError::NotWellTyped(function.function.name().to_owned()));
+                }
+                Ok(LogicalExpr::Function(function))
+            }
+        }
+    }
+
+    fn parse_comparison_op(&mut self) -> Option<ComparisonOp> {
+        let operators = [
+            ("==", ComparisonOp::Equal),
+            ("!=", ComparisonOp::NotEqual),
+            ("<=", ComparisonOp::LessOrEqual),
+            (">=", ComparisonOp::GreaterOrEqual),
+            ("<", ComparisonOp::Less),
+            (">", ComparisonOp::Greater),
+        ];
+
+        operators
+            .into_iter()
+            .find(|(text, _)| self.eat(text))
+            .map(|(_, op)| op)
+    }
+
+    fn parse_comparable(&mut self) -> Result<Comparable, PathError> {
+        match self.peek() {
+            Some('@') | Some('$') => Ok(Comparable::Query(self.parse_query()?)),
+            Some(ch) if ch.is_ascii_lowercase() && !self.at_keyword_literal() => {
+                Ok(Comparable::Function(self.parse_function_expr()?))
+            }
+            _ => Ok(Comparable::Literal(self.parse_literal()?)),
+        }
+    }
+
+    fn at_keyword_literal(&self) -> bool {
+        ["true", "false", "null"].iter().any(|keyword| {
+            self.rest().starts_with(keyword)
+                && !matches!(
+                    self.rest()[keyword.len()..].chars().next(),
+                    Some(ch) if ch == '(' || ch == '_' || ch.is_ascii_alphanumeric()
+                )
+        })
+    }
+
+    fn parse_literal(&mut self) -> Result<JsonValue, PathError> {
+        match self.peek() {
+            Some('\'') | Some('"') => Ok(JsonValue::String(self.parse_string_literal()?)),
+            Some(ch) if ch == '-' || ch.is_ascii_digit() => self.parse_number(),
+            _ => {
+                if self.at_keyword_literal() {
+                    if self.eat("true") {
+                        return Ok(JsonValue::Boolean(true));
+                    }
+                    if self.eat("false") {
+                        return Ok(JsonValue::Boolean(false));
+                    }
+                    if self.eat("null") {
+                        return Ok(JsonValue::Null);
+                    }
+                }
+                Err(self.unexpected())
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue, PathError> {
+        let start = self.position;
+        self.eat("-");
+
+        let int_start = self.position;
+        self.skip_digits();
+        let int = &self.input[int_start..self.position];
+        if int.is_empty() || (int.len() > 1 && int.starts_with('0')) {
+            return Err(PathError::InvalidNumber(start));
+        }
+
+        if self.eat(".") && self.skip_digits() == 0 {
+            return Err(PathError::InvalidNumber(start));
+        }
+
+        if self.eat("e") || self.eat("E") {
+            if !self.eat("-") {
+                self.eat("+");
+            }
+            if self.skip_digits() == 0 {
+                return Err(PathError::InvalidNumber(start));
+            }
+        }
+
+        self.input[start..self.position]
+            .parse::<f32>()
+            .map(JsonValue::Number)
+            .map_err(|_| PathError::InvalidNumber(start))
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let start = self.position;
+        while let Some(ch) = self.peek() {
+            if !ch.is_ascii_digit() {
+                break;
+            }
+            self.position += 1;
+        }
+        self.position - start
+    }
+
+    fn parse_function_expr(&mut self) -> Result<FunctionExpr, PathError> {
+        let start = self.position;
+        while let Some(ch) = self.peek() {
+            if !(ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '_') {
+                break;
+            }
+            self.position += 1;
+        }
+
+        let name = &self.input[start..self.position];
+        if self.peek() != Some('(') {
+            return Err(self.unexpected());
+        }
+        let function =
+            Function::from_name(name).ok_or(PathError::UnknownFunction(name.to_owned()))?;
+        self.position += 1;
+
+        let mut arguments = Vec::new();
+        self.skip_blank();
+        if !self.eat(")") {
+            loop {
+                self.skip_blank();
+                arguments.push(self.parse_argument()?);
+                self.skip_blank();
+                match self.next()? {
+                    ',' => continue,
+                    ')' => break,
+                    ch => {
+                        return Err(PathError::UnexpectedCharacter(
+                            self.position - ch.len_utf8(),
+                            ch,
+                        ))
+                    }
+                }
+            }
+        }
+
+        let parameters = function.parameters();
+        let well_typed = parameters.len() == arguments.len()
+            && parameters
+                .iter()
+                .zip(arguments.iter())
+                .all(|(parameter, argument)| accepts(*parameter, argument));
+        if !well_typed {
+            return Err(PathError::NotWellTyped(function.name().to_owned()));
+        }
+
+        Ok(FunctionExpr {
+            function,
+            arguments,
+        })
+    }
+
+    fn parse_argument(&mut self) -> Result<Argument, PathError> {
+        // A bare literal is only valid as a whole argument, try it first
+        let checkpoint = self.position;
+        if let Ok(literal) = self.parse_literal() {
+            self.skip_blank();
+            if matches!(self.peek(), Some(',') | Some(')')) {
+                return Ok(Argument::Literal(literal));
+            }
+        }
+        self.position = checkpoint;
+
+        match self.parse_logical_or()? {
+            LogicalExpr::Exists(query) => Ok(Argument::Query(query)),
+            LogicalExpr::Function(function) => Ok(Argument::Function(function)),
+            expr => Ok(Argument::Logical(expr)),
+        }
+    }
+}
+
+fn is_name_first(ch: char) -> bool {
+    ch.is_ascii_alphabetic() || ch == '_' || (ch as u32) >= 0x80
+}
+
+fn check_comparable(comparable: &Comparable, position: usize) -> Result<(), PathError> {
+    match comparable {
+        Comparable::Query(query) if !query.is_singular() => {
+            Err(PathError::NonSingularQuery(position))
+        }
+        Comparable::Function(function)
+            if function.function.result_type() != FunctionType::Value =>
+        {
+            Err(PathError::NotWellTyped(function.function.name().to_owned()))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn accepts(parameter: FunctionType, argument: &Argument) -> bool {
+    match (parameter, argument) {
+        (FunctionType::Value, Argument::Literal(_)) => true,
+        (FunctionType::Value, Argument::Query(query)) => query.is_singular(),
+        (FunctionType::Value, Argument::Function(function)) => {
+            function.function.result_type() == FunctionType::Value
+        }
+        (FunctionType::Logical, Argument::Logical(_) | Argument::Query(_)) => true,
+        (FunctionType::Logical, Argument::Function(function)) => {
+            function.function.result_type() != FunctionType::Value
+        }
+        (FunctionType::Nodes, Argument::Query(_)) => true,
+        (FunctionType::Nodes, Argument::Function(function)) => {
+            function.function.result_type() == FunctionType::Nodes
+        }
+        _ => false,
+    }
+}
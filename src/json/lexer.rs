@@ -208,7 +208,7 @@ pub fn lex_number(json_content: &str) -> Result<LexNumberOutput, LexerError> {
         .parse::<f32>()
         .map_err(LexerError::NotAFloat)?;
     let number_result = if negative {
-        number_result * -1.00
+        -number_result
     } else {
         number_result
     };
@@ -329,7 +329,7 @@ pub fn lex(json_content: &str) -> Result<TokenStream, LexerError> {
         }
     }
 
-    let _ = validate_stream(&mut tokens)?;
+    validate_stream(&mut tokens)?;
 
     Ok(tokens)
 }
@@ -349,6 +349,7 @@ fn validate_stream(stream: &mut TokenStream) -> Result<(), LexerError> {
 }
 
 #[cfg(test)]
+#[allow(clippy::needless_borrow, clippy::unnecessary_fallible_conversions)]
 pub mod lexer_tests {
     use super::*;
     use pretty_assertions::assert_eq;
@@ -0,0 +1,300 @@
+use thiserror::Error;
+
+use super::pointer::JsonPointer;
+use super::syntactic_analyzer::{JsonPair, JsonValue};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum MergePatchError {
+    #[error("A merge patch can not set the member at {0} to null")]
+    NullMember(JsonPointer),
+}
+
+// Applies an RFC 7396 merge patch: object members are merged recursively, null members
+// delete the matching target member and every other value (arrays included) replaces
+// the target as a whole.
+pub fn merge_patch(target: &mut JsonValue, patch: &JsonValue) {
+    let JsonValue::JsonObject(patch_members) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !matches!(target, JsonValue::JsonObject(_)) {
+        *target = JsonValue::JsonObject(Vec::new());
+    }
+    let JsonValue::JsonObject(target_members) = target else {
+        unreachable!("target was replaced by an object above");
+    };
+
+    for JsonPair(name, value) in patch_members.iter() {
+        let position = target_members.iter().position(|pair| pair.0 == *name);
+
+        match (value, position) {
+            (JsonValue::Null, Some(position)) => {
+                target_members.remove(position);
+            }
+            (JsonValue::Null, None) => {}
+            (value, Some(position)) => merge_patch(&mut target_members[position].1, value),
+            (value, None) => {
+                let mut member = JsonValue::Null;
+                merge_patch(&mut member, value);
+                target_members.push(JsonPair(name.clone(), member));
+            }
+        }
+    }
+}
+
+// Computes the merge patch that turns `from` into `to`, so that
+// `merge_patch(&mut from, &create_merge_patch(&from, &to)?)` yields `to`.
+// Merge patches can not express object members whose value is null, adding one or
+// changing one to null is reported as an error instead of silently producing a patch
+// that deletes it. Null members left as they are need no patch and are fine.
+pub fn create_merge_patch(from: &JsonValue, to: &JsonValue) -> Result<JsonValue, MergePatchError> {
+    create_at(JsonPointer::root(), from, to)
+}
+
+fn create_at(
+    path: JsonPointer,
+    from: &JsonValue,
+    to: &JsonValue,
+) -> Result<JsonValue, MergePatchError> {
+    let (JsonValue::JsonObject(from_members), JsonValue::JsonObject(to_members)) = (from, to)
+    else {
+        check_no_null_members(&path, to)?;
+        return Ok(to.clone());
+    };
+
+    let mut patch = Vec::new();
+    for JsonPair(name, _) in from_members.iter() {
+        if !to_members.iter().any(|pair| pair.0 == *name) {
+            patch.push(JsonPair(name.clone(), JsonValue::Null));
+        }
+    }
+
+    for JsonPair(name, value) in to_members.iter() {
+        let member_path = path.clone().with(name.as_str());
+        match from_members.iter().find(|pair| pair.0 == *name) {
+            Some(JsonPair(_, existing)) if existing.equivalent(value) => {}
+            _ if *value == JsonValue::Null => {
+                return Err(MergePatchError::NullMember(member_path));
+            }
+            Some(JsonPair(_, existing)) => {
+                patch.push(JsonPair(
+                    name.clone(),
+                    create_at(member_path, existing, value)?,
+                ));
+            }
+            None => {
+                check_no_null_members(&member_path, value)?;
+                patch.push(JsonPair(name.clone(), value.clone()));
+            }
+        }
+    }
+
+    Ok(JsonValue::JsonObject(patch))
+}
+
+// Objects inside a patch are merged, so any null member in them would turn into a deletion.
+// Arrays are copied verbatim and may contain anything.
+fn check_no_null_members(path: &JsonPointer, value: &JsonValue) -> Result<(), MergePatchError> {
+    let JsonValue::JsonObject(members) = value else {
+        return Ok(());
+    };
+
+    for JsonPair(name, member) in members.iter() {
+        let member_path = path.clone().with(name.as_str());
+        if *member == JsonValue::Null {
+            return Err(MergePatchError::NullMember(member_path));
+        }
+        check_no_null_members(&member_path, member)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod merge_patch_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn string(value: &str) -> JsonValue {
+        JsonValue::String(value.to_string())
+    }
+
+    fn object(members: Vec<(&str, JsonValue)>) -> JsonValue {
+        JsonValue::JsonObject(
+            members
+                .into_iter()
+                .map(|(key, value)| JsonPair(key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    fn merged(target: JsonValue, patch: JsonValue) -> JsonValue {
+        let mut target = target;
+        merge_patch(&mut target, &patch);
+        target
+    }
+
+    #[test]
+    pub fn test_rfc_examples() {
+        // RFC 7396, appendix A
+        assert_eq!(
+            merged(
+                object(vec![("a", string("b"))]),
+                object(vec![("a", string("c"))])
+            ),
+            object(vec![("a", string("c"))])
+        );
+        assert_eq!(
+            merged(
+                object(vec![("a", string("b"))]),
+                object(vec![("b", string("c"))])
+            ),
+            object(vec![("a", string("b")), ("b", string("c"))])
+        );
+        assert_eq!(
+            merged(
+                object(vec![("a", string("b")), ("b", string("c"))]),
+                object(vec![("a", JsonValue::Null)])
+            ),
+            object(vec![("b", string("c"))])
+        );
+        assert_eq!(
+            merged(
+                object(vec![("a", JsonValue::JsonArray(vec![string("b")]))]),
+                object(vec![("a", string("c"))])
+            ),
+            object(vec![("a", string("c"))])
+        );
+        assert_eq!(
+            merged(
+                object(vec![("a", object(vec![("b", string("c"))]))]),
+                object(vec![(
+                    "a",
+                    object(vec![("b", string("d")), ("c", JsonValue::Null)])
+                )])
+            ),
+            object(vec![("a", object(vec![("b", string("d"))]))])
+        );
+        assert_eq!(
+            merged(
+                object(vec![(
+                    "a",
+                    JsonValue::JsonArray(vec![object(vec![("b", string("c"))])])
+                )]),
+                object(vec![(
+                    "a",
+                    JsonValue::JsonArray(vec![JsonValue::Number(1.0)])
+                )])
+            ),
+            object(vec![(
+                "a",
+                JsonValue::JsonArray(vec![JsonValue::Number(1.0)])
+            )])
+        );
+        assert_eq!(
+            merged(
+                JsonValue::JsonArray(vec![string("a"), string("b")]),
+                JsonValue::JsonArray(vec![string("c"), string("d")])
+            ),
+            JsonValue::JsonArray(vec![string("c"), string("d")])
+        );
+        assert_eq!(
+            merged(object(vec![("a", string("foo"))]), JsonValue::Null),
+            JsonValue::Null
+        );
+        assert_eq!(
+            merged(string("foo"), object(vec![("bar", JsonValue::Null)])),
+            object(vec![])
+        );
+        assert_eq!(
+            merged(
+                object(vec![]),
+                object(vec![(
+                    "a",
+                    object(vec![("bb", object(vec![("ccc", JsonValue::Null)]))])
+                )])
+            ),
+            object(vec![("a", object(vec![("bb", object(vec![]))]))])
+        );
+    }
+
+    #[test]
+    pub fn test_create_merge_patch() {
+        let from = object(vec![
+            ("title", string("Goodbye!")),
+            (
+                "author",
+                object(vec![
+                    ("givenName", string("John")),
+                    ("familyName", string("Doe")),
+                ]),
+            ),
+            (
+                "tags",
+                JsonValue::JsonArray(vec![string("example"), string("sample")]),
+            ),
+            ("content", string("This will be unchanged")),
+        ]);
+        let to = object(vec![
+            ("title", string("Hello!")),
+            ("author", object(vec![("givenName", string("John"))])),
+            ("tags", JsonValue::JsonArray(vec![string("example")])),
+            ("content", string("This will be unchanged")),
+            ("phoneNumber", string("+01-123-456-7890")),
+        ]);
+
+        let patch = create_merge_patch(&from, &to).unwrap();
+        assert_eq!(
+            patch,
+            object(vec![
+                ("title", string("Hello!")),
+                ("author", object(vec![("familyName", JsonValue::Null)])),
+                ("tags", JsonValue::JsonArray(vec![string("example")])),
+                ("phoneNumber", string("+01-123-456-7890")),
+            ])
+        );
+
+        assert_eq!(merged(from, patch), to);
+    }
+
+    #[test]
+    pub fn test_create_merge_patch_rejects_null_members() {
+        let from = object(vec![("a", JsonValue::Number(1.0))]);
+        let to = object(vec![
+            (
+                "a",
+                object(vec![("b", JsonValue::JsonArray(vec![JsonValue::Null]))]),
+            ),
+            ("c", object(vec![("d", JsonValue::Null)])),
+        ]);
+
+        assert_eq!(
+            create_merge_patch(&from, &to),
+            Err(MergePatchError::NullMember(
+                JsonPointer::root().with("c").with("d")
+            ))
+        );
+
+        assert_eq!(
+            create_merge_patch(&from, &JsonValue::Null),
+            Ok(JsonValue::Null)
+        );
+
+        let changed_to_null = object(vec![("a", JsonValue::Null)]);
+        assert_eq!(
+            create_merge_patch(&from, &changed_to_null),
+            Err(MergePatchError::NullMember(JsonPointer::root().with("a")))
+        );
+    }
+
+    #[test]
+    pub fn test_create_merge_patch_keeps_unchanged_null_members() {
+        let from = object(vec![("a", JsonValue::Null), ("b", JsonValue::Number(1.0))]);
+        let to = object(vec![("a", JsonValue::Null), ("b", JsonValue::Number(2.0))]);
+
+        let patch = create_merge_patch(&from, &to).unwrap();
+        assert_eq!(patch, object(vec![("b", JsonValue::Number(2.0))]));
+        assert_eq!(merged(from, patch), to);
+    }
+}
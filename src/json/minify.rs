@@ -0,0 +1,224 @@
+use std::io::{BufReader, BufWriter, Read, Write};
+
+use super::byte_lexer::ByteLexer;
+use super::dialect::Dialect;
+use super::events::{EventError, Grammar, LexemeSource};
+use super::scanner::Lexeme;
+use super::serializer::{write_number, write_string};
+use super::stream::{StreamLexer, DEFAULT_BUFFER_SIZE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MinifyOptions {
+    // accept comments and trailing commas
+    pub jsonc: bool,
+    // write the comments of JSONC input instead of dropping them
+    pub keep_comments: bool,
+}
+
+// Copies a json text without its insignificant whitespace. The input is validated
+// on the way, one lexeme at a time: memory use does not depend on its size.
+// Numbers keep their spelling, strings are written with the serializer's escapes
+// and trailing commas are dropped. When an error is returned, the writer has been
+// given the output up to the last valid lexeme: it is partial and should be discarded.
+pub fn minify<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    options: MinifyOptions,
+) -> Result<(), EventError> {
+    let (lexer, mut grammar) = if options.jsonc {
+        let lexer = ByteLexer::with_comments();
+        let lexer = if options.keep_comments {
+            lexer.keep_comments()
+        } else {
+            lexer
+        };
+        (lexer, Grammar::with_dialect(Dialect::JSONC))
+    } else {
+        (ByteLexer::new(), Grammar::new())
+    };
+
+    let reader = BufReader::with_capacity(DEFAULT_BUFFER_SIZE, reader);
+    let mut source = StreamLexer::with_lexer(reader, lexer);
+    let mut out = BufWriter::new(writer);
+    let io_error = |error: std::io::Error| EventError::Io(error.to_string());
+
+    let mut pending_comma = false;
+    let mut string = String::new();
+    let mut text = Vec::new();
+    loop {
+        let next = source.next_lexeme()?;
+
+        // a comma is only written once it is known not to be a trailing one
+        let closing = matches!(
+            next,
+            None | Some((Lexeme::RightBrace | Lexeme::RightBracket, _))
+        );
+        let comma = std::mem::take(&mut pending_comma) && !closing;
+
+        // nothing is written before the grammar accepts it
+        text.clear();
+        let finished = match next {
+            Some((lexeme, position)) => {
+                text.extend_from_slice(match &lexeme {
+                    Lexeme::LeftBrace => b"{",
+                    Lexeme::RightBrace => b"}",
+                    Lexeme::LeftBracket => b"[",
+                    Lexeme::RightBracket => b"]",
+                    Lexeme::Colon => b":",
+                    Lexeme::Comma => {
+                        pending_comma = true;
+                        b""
+                    }
+                    Lexeme::String(value) => {
+                        string.clear();
+                        write_string(&mut string, value)
+                            .expect("writing to a String does not fail");
+                        string.as_bytes()
+                    }
+                    Lexeme::Number(text) | Lexeme::Identifier(text) => text.as_bytes(),
+                    Lexeme::NumberValue(number) => {
+                        string.clear();
+                        write_number(&mut string, *number)
+                            .expect("writing to a String does not fail");
+                        string.as_bytes()
+                    }
+                    Lexeme::True => b"true",
+                    Lexeme::False => b"false",
+                    Lexeme::Null => b"null",
+                });
+                grammar.accept(lexeme, position)?;
+                false
+            }
+            None => {
+                grammar.finish(source.position())?;
+                true
+            }
+        };
+
+        if comma {
+            out.write_all(b",").map_err(io_error)?;
+        }
+        for comment in source.lexer_mut().take_comments() {
+            out.write_all(comment.as_bytes()).map_err(io_error)?;
+            // a line comment lasts until the end of the line
+            if comment.starts_with("//") {
+                out.write_all(b"\n").map_err(io_error)?;
+            }
+        }
+        out.write_all(&text).map_err(io_error)?;
+
+        if finished {
+            return out.flush().map_err(io_error);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod minify_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::io;
+
+    fn minified(input: &str, options: MinifyOptions) -> Result<String, EventError> {
+        let mut output = Vec::new();
+        minify(input.as_bytes(), &mut output, options)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    pub fn test_minify() {
+        let input = "{\n  \"name\" : \"caf\\u00e9\\n\",\n  \"list\": [ 1.50, -0, 2e10, true, false, null ],\n  \"empty\": { }\n}\n";
+        assert_eq!(
+            minified(input, MinifyOptions::default()).unwrap(),
+            "{\"name\":\"café\\n\",\"list\":[1.50,-0,2e10,true,false,null],\"empty\":{}}"
+        );
+
+        // larger than the read buffer
+        let elements = vec!["{ \"key\" : [ 1 , \"two\" ] }"; 2000];
+        let input = format!("[\n{}\n]", elements.join(",\n"));
+        let expected = format!("[{}]", vec!["{\"key\":[1,\"two\"]}"; 2000].join(","));
+        assert_eq!(
+            minified(&input, MinifyOptions::default()).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    pub fn test_minify_jsonc() {
+        let input = "// settings\n{\n  \"a\": 1, /* one */\n  \"b\": [2, 3,], // list\r\n}\n";
+        let jsonc = MinifyOptions {
+            jsonc: true,
+            ..MinifyOptions::default()
+        };
+
+        assert_eq!(minified(input, jsonc).unwrap(), r#"{"a":1,"b":[2,3]}"#);
+        assert_eq!(
+            minified(
+                input,
+                MinifyOptions {
+                    keep_comments: true,
+                    ..jsonc
+                }
+            )
+            .unwrap(),
+            "// settings\n{\"a\":1,/* one */\"b\":[2,3]// list\n}"
+        );
+        assert!(matches!(
+            minified(input, MinifyOptions::default()),
+            Err(EventError::UnexpectedCharacter('/', _))
+        ));
+    }
+
+    #[test]
+    pub fn test_minify_errors() {
+        let options = MinifyOptions::default();
+
+        assert!(matches!(
+            minified("[1, 2", options),
+            Err(EventError::UnexpectedEnd(_))
+        ));
+        assert!(matches!(
+            minified("[1] [2]", options),
+            Err(EventError::TrailingCharacters(_))
+        ));
+        assert!(matches!(
+            minified("[1,]", options),
+            Err(EventError::UnexpectedToken("']'", _))
+        ));
+        assert!(matches!(
+            minified(
+                "[1] /* open",
+                MinifyOptions {
+                    jsonc: true,
+                    ..options
+                }
+            ),
+            Err(EventError::UnterminatedComment(_))
+        ));
+
+        // what was written stops before the offending lexeme
+        for (input, written) in [
+            ("[1] [2]", "[1]"),
+            ("{\"a\" 1}", "{\"a\""),
+            ("[1, 2 3]", "[1,2"),
+        ] {
+            let mut output = Vec::new();
+            assert!(minify(input.as_bytes(), &mut output, options).is_err());
+            assert_eq!(String::from_utf8(output).unwrap(), written);
+        }
+
+        struct FailingWriter;
+        impl Write for FailingWriter {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::other("disk full"))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        assert_eq!(
+            minify("[1]".as_bytes(), FailingWriter, options),
+            Err(EventError::Io("disk full".to_string()))
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use std::fs::File;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use super::borrowed::{parse_borrowed, BorrowedValue};
+use super::events::{EventError, Position};
+use super::syntactic_analyzer::ParseError;
+
+// A JSON file mapped in memory. The values it hands out borrow their strings from
+// the mapping instead of copying them, so a large file costs little more than the
+// structure of the values. The file is parsed once, when it is opened.
+#[derive(Debug)]
+pub struct Document {
+    // borrows from the mapping below, and is declared first to be dropped before it
+    root: BorrowedValue<'static>,
+    map: Option<Mmap>,
+}
+
+impl Document {
+    /// Maps the file, checks that it holds valid UTF-8 and builds its values.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this process or any other, while
+    /// the Document is alive. The checks made here are not repeated: `as_str` and the
+    /// values of `root` hand out the mapped bytes as strs, so a change could put invalid
+    /// UTF-8 in a str or make reading the mapping fault, which is undefined behavior.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> Result<Document, ParseError> {
+        let io_error = |error: std::io::Error| ParseError::Io(error.to_string());
+        let file = File::open(path).map_err(io_error)?;
+
+        // empty files cannot be mapped
+        let map = if file.metadata().map_err(io_error)?.len() == 0 {
+            None
+        } else {
+            // SAFETY: the mapping is read only, and the caller guarantees that the file
+            // does not change while the Document is alive
+            Some(unsafe { Mmap::map(&file) }.map_err(io_error)?)
+        };
+
+        let bytes: &[u8] = map.as_deref().unwrap_or_default();
+        let text = match std::str::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(error) => {
+                let valid = &bytes[..error.valid_up_to()];
+                let line_start = valid
+                    .iter()
+                    .rposition(|byte| *byte == b'\n')
+                    .map_or(0, |n| n + 1);
+                return Err(ParseError::Syntax(EventError::InvalidUtf8(Position {
+                    offset: valid.len(),
+                    line: valid.iter().filter(|byte| **byte == b'\n').count() + 1,
+                    column: valid.len() - line_start + 1,
+                })));
+            }
+        };
+
+        // SAFETY: the mapped bytes do not move with the Mmap and stay mapped until it is
+        // dropped, after root. Only borrows of the Document are handed out.
+        let text: &'static str = unsafe { &*(text as *const str) };
+        let root = parse_borrowed(text)?;
+        Ok(Document { root, map })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or_default()
+    }
+
+    pub fn as_str(&self) -> &str {
+        // SAFETY: checked to be UTF-8 in open, and unchanged since as open requires
+        unsafe { std::str::from_utf8_unchecked(self.bytes()) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes().is_empty()
+    }
+
+    pub fn root(&self) -> &BorrowedValue<'_> {
+        &self.root
+    }
+}
+
+#[cfg(test)]
+pub mod mmap_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::borrow::Cow;
+    use std::path::PathBuf;
+
+    fn temp_file(name: &str, content: &[u8]) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("json_parser_rs_{}_{name}", std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn open(path: &Path) -> Result<Document, ParseError> {
+        // SAFETY: the test files are not changed while they are mapped
+        unsafe { Document::open(path) }
+    }
+
+    #[test]
+    pub fn test_strings_point_into_the_mapping() {
+        let path = temp_file("mapped.json", br#"{"name": "mapped", "list": [1, "two"]}"#);
+        let document = open(&path).unwrap();
+        let root = document.root();
+
+        let Some(BorrowedValue::String(Cow::Borrowed(name))) = root.get("name") else {
+            panic!("expected a borrowed string");
+        };
+        assert_eq!(*name, "mapped");
+        let mapping = document.as_str().as_bytes().as_ptr_range();
+        assert!(mapping.contains(&name.as_ptr()));
+        // built once when the file was opened
+        assert!(std::ptr::eq(root, document.root()));
+
+        assert_eq!(
+            root.get("list").and_then(|list| list.get_index(1)),
+            Some(&BorrowedValue::String(Cow::Borrowed("two")))
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    pub fn test_open_errors() {
+        let path = temp_file("empty.json", b"");
+        assert!(matches!(
+            open(&path),
+            Err(ParseError::Syntax(EventError::UnexpectedEnd(_)))
+        ));
+        std::fs::remove_file(path).unwrap();
+
+        let path = temp_file("latin1.json", b"[\"ok\",\n \"caf\xe9\"]");
+        assert_eq!(
+            open(&path).unwrap_err(),
+            ParseError::Syntax(EventError::InvalidUtf8(Position {
+                offset: 12,
+                line: 2,
+                column: 6
+            }))
+        );
+        std::fs::remove_file(path).unwrap();
+
+        assert!(matches!(
+            open(Path::new("tests/does-not-exist.json")),
+            Err(ParseError::Io(_))
+        ));
+    }
+}
@@ -1,2 +1,33 @@
+#[cfg(feature = "async")]
+pub mod async_stream;
+mod binary;
+pub mod borrowed;
+pub mod bson;
+pub mod byte_lexer;
+pub mod cbor;
+pub mod concatenated;
+pub mod cst;
+pub mod dialect;
+pub mod diff;
+pub mod events;
+pub mod formatter;
+pub mod hjson;
+pub mod json_seq;
+pub mod jsonc;
+pub mod jsonpath;
 pub mod lexer;
+pub mod merge_patch;
+pub mod minify;
+pub mod mmap;
+pub mod msgpack;
+pub mod ndjson;
+pub mod patch;
+pub mod pointer;
+pub mod push;
+pub mod raw;
+pub mod scanner;
+pub mod serializer;
+pub mod smile;
+pub mod stream;
 pub mod syntactic_analyzer;
+pub mod ubjson;
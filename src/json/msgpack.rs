@@ -0,0 +1,448 @@
+use thiserror::Error;
+
+use super::binary::{is_integral, Cursor, UnexpectedEnd};
+use super::syntactic_analyzer::{JsonPair, JsonValue};
+
+pub use super::binary::MAX_DEPTH;
+
+// Byte offsets point at the start of the offending object
+#[derive(Debug, Clone, Error, PartialEq)]
+pub enum MsgpackError {
+    #[error("Unexpected end of the MessagePack data at byte {0}")]
+    UnexpectedEnd(usize),
+
+    #[error("Invalid format byte 0x{0:02x} at byte {1}")]
+    InvalidFormat(u8, usize),
+
+    #[error("Binary data has no json representation (byte {0})")]
+    Binary(usize),
+
+    #[error("Extension type {0} has no json representation (byte {1})")]
+    Extension(i8, usize),
+
+    #[error("Map keys must be strings (byte {0})")]
+    NonStringKey(usize),
+
+    #[error("Invalid UTF-8 in the string at byte {0}")]
+    InvalidUtf8(usize),
+
+    #[error("Objects are nested deeper than {0} levels")]
+    TooDeep(usize),
+
+    #[error("{0} bytes follow the MessagePack object")]
+    TrailingBytes(usize),
+
+    #[error("A length of {0} does not fit in the 32 bit MessagePack formats")]
+    TooLong(usize),
+}
+
+const NIL: u8 = 0xc0;
+const FALSE: u8 = 0xc2;
+const TRUE: u8 = 0xc3;
+const FLOAT32: u8 = 0xca;
+const FLOAT64: u8 = 0xcb;
+const UINT8: u8 = 0xcc;
+const INT8: u8 = 0xd0;
+const STR8: u8 = 0xd9;
+const STR16: u8 = 0xda;
+const STR32: u8 = 0xdb;
+const ARRAY16: u8 = 0xdc;
+const ARRAY32: u8 = 0xdd;
+const MAP16: u8 = 0xde;
+const MAP32: u8 = 0xdf;
+
+// Encodes a value as MessagePack, each object in its smallest format. Integral
+// numbers become integers, the other numbers 32 bit floats. Strings, arrays and
+// objects longer than u32::MAX have no MessagePack format and fail with TooLong.
+pub fn to_msgpack(value: &JsonValue) -> Result<Vec<u8>, MsgpackError> {
+    let mut out = Vec::new();
+    encode(&mut out, value)?;
+    Ok(out)
+}
+
+fn encode(out: &mut Vec<u8>, value: &JsonValue) -> Result<(), MsgpackError> {
+    match value {
+        JsonValue::Null => out.push(NIL),
+        JsonValue::Boolean(boolean) => out.push(if *boolean { TRUE } else { FALSE }),
+        JsonValue::Number(number) => encode_number(out, *number),
+        JsonValue::String(string) => encode_str(out, string)?,
+        JsonValue::JsonArray(elements) => {
+            header(out, elements.len(), 0x90, ARRAY16)?;
+            for element in elements {
+                encode(out, element)?;
+            }
+        }
+        JsonValue::JsonObject(members) => {
+            header(out, members.len(), 0x80, MAP16)?;
+            for JsonPair(key, member) in members {
+                encode_str(out, key)?;
+                encode(out, member)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// A fix format when the length fits in four bits, the 16 or 32 bit one otherwise
+fn header(out: &mut Vec<u8>, length: usize, fix: u8, format16: u8) -> Result<(), MsgpackError> {
+    let length32 = u32::try_from(length).map_err(|_| MsgpackError::TooLong(length))?;
+    match length {
+        0..=15 => out.push(fix | length as u8),
+        16..=0xffff => {
+            out.push(format16);
+            out.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        _ => {
+            out.push(format16 + 1);
+            out.extend_from_slice(&length32.to_be_bytes());
+        }
+    }
+    Ok(())
+}
+
+fn encode_str(out: &mut Vec<u8>, string: &str) -> Result<(), MsgpackError> {
+    str_header(out, string.len())?;
+    out.extend_from_slice(string.as_bytes());
+    Ok(())
+}
+
+fn str_header(out: &mut Vec<u8>, length: usize) -> Result<(), MsgpackError> {
+    let length32 = u32::try_from(length).map_err(|_| MsgpackError::TooLong(length))?;
+    match length {
+        0..=31 => out.push(0xa0 | length as u8),
+        32..=0xff => out.extend_from_slice(&[STR8, length as u8]),
+        0x100..=0xffff => {
+            out.push(STR16);
+            out.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        _ => {
+            out.push(STR32);
+            out.extend_from_slice(&length32.to_be_bytes());
+        }
+    }
+    Ok(())
+}
+
+fn encode_number(out: &mut Vec<u8>, number: f32) {
+    // 2^64 and -2^63, the first values out of the range of the integer formats
+    const UNSIGNED_LIMIT: f64 = 18446744073709551616.0;
+    const SIGNED_LIMIT: f64 = -9223372036854775808.0;
+
+    let wide = number as f64;
+    let integral = is_integral(number);
+    if integral && (0.0..UNSIGNED_LIMIT).contains(&wide) {
+        encode_unsigned(out, wide as u64);
+    } else if integral && (SIGNED_LIMIT..0.0).contains(&wide) {
+        encode_signed(out, wide as i64);
+    } else {
+        out.push(FLOAT32);
+        out.extend_from_slice(&number.to_bits().to_be_bytes());
+    }
+}
+
+fn encode_unsigned(out: &mut Vec<u8>, integer: u64) {
+    match integer {
+        0..=0x7f => out.push(integer as u8),
+        0x80..=0xff => out.extend_from_slice(&[UINT8, integer as u8]),
+        0x100..=0xffff => {
+            out.push(UINT8 + 1);
+            out.extend_from_slice(&(integer as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(UINT8 + 2);
+            out.extend_from_slice(&(integer as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(UINT8 + 3);
+            out.extend_from_slice(&integer.to_be_bytes());
+        }
+    }
+}
+
+// Only called for negative integers, the others are unsigned
+fn encode_signed(out: &mut Vec<u8>, integer: i64) {
+    const I16_MIN: i64 = i16::MIN as i64;
+    const I32_MIN: i64 = i32::MIN as i64;
+
+    match integer {
+        -32..=-1 => out.push(integer as u8),
+        -128..=-33 => out.extend_from_slice(&[INT8, integer as u8]),
+        I16_MIN..=-129 => {
+            out.push(INT8 + 1);
+            out.extend_from_slice(&(integer as i16).to_be_bytes());
+        }
+        I32_MIN..=-32769 => {
+            out.push(INT8 + 2);
+            out.extend_from_slice(&(integer as i32).to_be_bytes());
+        }
+        _ => {
+            out.push(INT8 + 3);
+            out.extend_from_slice(&integer.to_be_bytes());
+        }
+    }
+}
+
+// Decodes one MessagePack object. Integers and floats of every width become numbers,
+// binary data and extension types are refused.
+pub fn from_msgpack(bytes: &[u8]) -> Result<JsonValue, MsgpackError> {
+    let mut decoder = Decoder {
+        cursor: Cursor::new(bytes, 0),
+    };
+    let value = decoder.object(0)?;
+    match decoder.cursor.remaining() {
+        0 => Ok(value),
+        trailing => Err(MsgpackError::TrailingBytes(trailing)),
+    }
+}
+
+impl From<UnexpectedEnd> for MsgpackError {
+    fn from(UnexpectedEnd(offset): UnexpectedEnd) -> Self {
+        MsgpackError::UnexpectedEnd(offset)
+    }
+}
+
+struct Decoder<'a> {
+    cursor: Cursor<'a>,
+}
+
+impl Decoder<'_> {
+    // A big endian unsigned integer of 1, 2, 4 or 8 bytes
+    fn unsigned(&mut self, width: usize) -> Result<u64, MsgpackError> {
+        let bytes = self.cursor.take(width)?;
+        Ok(bytes
+            .iter()
+            .fold(0, |integer, byte| (integer << 8) | *byte as u64))
+    }
+
+    fn signed(&mut self, width: usize) -> Result<i64, MsgpackError> {
+        let unsigned = self.unsigned(width)?;
+        // moves the sign bit of the integer to the top before shifting it back
+        let unused = 64 - 8 * width as u32;
+        Ok(((unsigned << unused) as i64) >> unused)
+    }
+
+    fn object(&mut self, depth: usize) -> Result<JsonValue, MsgpackError> {
+        if depth > MAX_DEPTH {
+            return Err(MsgpackError::TooDeep(MAX_DEPTH));
+        }
+
+        let start = self.cursor.offset;
+        let format = self.cursor.byte()?;
+        let number = |integer: f32| Ok(JsonValue::Number(integer));
+        match format {
+            0x00..=0x7f => number(format as f32),
+            0xe0..=0xff => number(format as i8 as f32),
+            0x80..=0x8f => self.map((format & 0x0f) as usize, depth),
+            0x90..=0x9f => self.array((format & 0x0f) as usize, depth),
+            0xa0..=0xbf => self.str((format & 0x1f) as usize, start),
+            NIL => Ok(JsonValue::Null),
+            FALSE => Ok(JsonValue::Boolean(false)),
+            TRUE => Ok(JsonValue::Boolean(true)),
+            0xc4..=0xc6 => Err(MsgpackError::Binary(start)),
+            0xc7..=0xc9 => {
+                let length_width = 1 << (format - 0xc7);
+                self.unsigned(length_width)?;
+                Err(MsgpackError::Extension(self.cursor.byte()? as i8, start))
+            }
+            0xd4..=0xd8 => Err(MsgpackError::Extension(self.cursor.byte()? as i8, start)),
+            FLOAT32 => number(f32::from_be_bytes(self.cursor.fixed()?)),
+            FLOAT64 => number(f64::from_be_bytes(self.cursor.fixed()?) as f32),
+            0xcc..=0xcf => number(self.unsigned(1 << (format - UINT8))? as f32),
+            0xd0..=0xd3 => number(self.signed(1 << (format - INT8))? as f32),
+            STR8 | STR16 | STR32 => {
+                let length = self.unsigned(1 << (format - STR8))?;
+                self.str(length as usize, start)
+            }
+            ARRAY16 | ARRAY32 => {
+                let length = self.unsigned(2 << (format - ARRAY16))?;
+                self.array(length as usize, depth)
+            }
+            MAP16 | MAP32 => {
+                let length = self.unsigned(2 << (format - MAP16))?;
+                self.map(length as usize, depth)
+            }
+            _ => Err(MsgpackError::InvalidFormat(format, start)),
+        }
+    }
+
+    fn str(&mut self, length: usize, start: usize) -> Result<JsonValue, MsgpackError> {
+        let bytes = self.cursor.take(length)?;
+        std::str::from_utf8(bytes)
+            .map(|string| JsonValue::String(string.to_string()))
+            .map_err(|_| MsgpackError::InvalidUtf8(start))
+    }
+
+    fn array(&mut self, length: usize, depth: usize) -> Result<JsonValue, MsgpackError> {
+        let mut elements = Vec::new();
+        for _ in 0..length {
+            elements.push(self.object(depth + 1)?);
+        }
+        Ok(JsonValue::JsonArray(elements))
+    }
+
+    fn map(&mut self, length: usize, depth: usize) -> Result<JsonValue, MsgpackError> {
+        let mut members = Vec::new();
+        for _ in 0..length {
+            let start = self.cursor.offset;
+            let JsonValue::String(key) = self.object(depth + 1)? else {
+                return Err(MsgpackError::NonStringKey(start));
+            };
+            members.push(JsonPair(key, self.object(depth + 1)?));
+        }
+        Ok(JsonValue::JsonObject(members))
+    }
+}
+
+#[cfg(test)]
+pub mod msgpack_tests {
+    use super::*;
+    use crate::json::binary::binary_tests::hex;
+    use crate::json::syntactic_analyzer::parse_str;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    pub fn test_smallest_formats() {
+        let examples = [
+            ("0", "00"),
+            ("127", "7f"),
+            ("128", "cc80"),
+            ("256", "cd0100"),
+            ("65536", "ce00010000"),
+            ("1099511627776", "cf0000010000000000"),
+            ("-1", "ff"),
+            ("-32", "e0"),
+            ("-33", "d0df"),
+            ("-129", "d1ff7f"),
+            ("-32769", "d2ffff7fff"),
+            ("-1099511627776", "d3ffffff0000000000"),
+            ("1.5", "ca3fc00000"),
+            ("-0.0", "ca80000000"),
+            ("null", "c0"),
+            ("false", "c2"),
+            ("true", "c3"),
+            (r#""""#, "a0"),
+            (r#""abc""#, "a3616263"),
+            ("[]", "90"),
+            ("[1, [2]]", "920191 02"),
+            ("{}", "80"),
+            (r#"{"a": [true]}"#, "81a16191c3"),
+        ];
+
+        for (json, msgpack) in examples {
+            let bytes = hex(&msgpack.replace(' ', ""));
+            assert_eq!(
+                to_msgpack(&parse_str(json).unwrap()).unwrap(),
+                bytes,
+                "{json}"
+            );
+            assert_eq!(from_msgpack(&bytes).unwrap(), parse_str(json).unwrap());
+        }
+    }
+
+    #[test]
+    pub fn test_length_formats() {
+        let lengths = [
+            (31, "bf", 1),
+            (32, "d920", 2),
+            (256, "da0100", 3),
+            (65536, "db00010000", 5),
+        ];
+        for (length, header, width) in lengths {
+            let value = JsonValue::String("x".repeat(length));
+            let bytes = to_msgpack(&value).unwrap();
+            assert_eq!(bytes[..width], hex(header), "{length}");
+            assert_eq!(from_msgpack(&bytes).unwrap(), value);
+        }
+
+        let lengths = [(15, "9f", 1), (16, "dc0010", 3), (65536, "dd00010000", 5)];
+        for (length, header, width) in lengths {
+            let value = JsonValue::JsonArray(vec![JsonValue::Null; length]);
+            let bytes = to_msgpack(&value).unwrap();
+            assert_eq!(bytes[..width], hex(header), "{length}");
+            assert_eq!(from_msgpack(&bytes).unwrap(), value);
+        }
+
+        let members = |count| {
+            (0..count)
+                .map(|index: usize| JsonPair(index.to_string(), JsonValue::Null))
+                .collect()
+        };
+        let value = JsonValue::JsonObject(members(16));
+        let bytes = to_msgpack(&value).unwrap();
+        assert_eq!(bytes[..3], hex("de0010"));
+        assert_eq!(from_msgpack(&bytes).unwrap(), value);
+    }
+
+    // Values that long cannot be allocated here, so only the headers are checked
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    pub fn test_too_long() {
+        let mut out = Vec::new();
+        let largest = u32::MAX as usize;
+        header(&mut out, largest, 0x90, ARRAY16).unwrap();
+        str_header(&mut out, largest).unwrap();
+        assert_eq!(out, hex("ddffffffffdbffffffff"));
+
+        let length = largest + 1;
+        assert_eq!(
+            header(&mut out, length, 0x90, ARRAY16),
+            Err(MsgpackError::TooLong(length))
+        );
+        assert_eq!(
+            header(&mut out, length, 0x80, MAP16),
+            Err(MsgpackError::TooLong(length))
+        );
+        assert_eq!(
+            str_header(&mut out, length),
+            Err(MsgpackError::TooLong(length))
+        );
+        assert_eq!(out.len(), 10);
+    }
+
+    #[test]
+    pub fn test_decode() {
+        // wider formats than needed
+        assert_eq!(
+            from_msgpack(&hex("cd0001")).unwrap(),
+            JsonValue::Number(1.0)
+        );
+        assert_eq!(
+            from_msgpack(&hex("d3fffffffffffffffe")).unwrap(),
+            JsonValue::Number(-2.0)
+        );
+        assert_eq!(
+            from_msgpack(&hex("cb3ff199999999999a")).unwrap(),
+            JsonValue::Number(1.1)
+        );
+        assert_eq!(
+            from_msgpack(&hex("d90161")).unwrap(),
+            JsonValue::String("a".to_string())
+        );
+        assert_eq!(
+            from_msgpack(&hex("de0001a16101")).unwrap(),
+            parse_str(r#"{"a": 1}"#).unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_decode_errors() {
+        let errors = [
+            ("c40100", MsgpackError::Binary(0)),
+            ("d4010a", MsgpackError::Extension(1, 0)),
+            ("91c701ff0a", MsgpackError::Extension(-1, 1)),
+            ("8101c0", MsgpackError::NonStringKey(1)),
+            ("81c0c0", MsgpackError::NonStringKey(1)),
+            ("a2c3", MsgpackError::UnexpectedEnd(2)),
+            ("9201", MsgpackError::UnexpectedEnd(2)),
+            ("a1ff", MsgpackError::InvalidUtf8(0)),
+            ("c1", MsgpackError::InvalidFormat(0xc1, 0)),
+            ("c0c0", MsgpackError::TrailingBytes(1)),
+        ];
+        for (msgpack, error) in errors {
+            assert_eq!(from_msgpack(&hex(msgpack)), Err(error), "{msgpack}");
+        }
+
+        let nested = vec![0x91; MAX_DEPTH + 1];
+        assert_eq!(from_msgpack(&nested), Err(MsgpackError::TooDeep(MAX_DEPTH)));
+    }
+}
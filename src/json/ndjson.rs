@@ -0,0 +1,434 @@
+use std::io::{self, BufRead, Write};
+use std::thread;
+
+use thiserror::Error;
+
+use super::events::EventError;
+use super::push::ValuePushParser;
+use super::syntactic_analyzer::JsonValue;
+
+// Lines are counted from 1, positions inside the syntax errors are relative to the line
+#[derive(Debug, Clone, Error, PartialEq)]
+pub enum NdjsonError {
+    #[error("Line {0}: {1}")]
+    Syntax(usize, EventError),
+
+    #[error("Line {0} is blank")]
+    BlankLine(usize),
+
+    #[error("Line {0} does not end with a newline")]
+    MissingNewline(usize),
+
+    #[error("Could not read line {0}: {1}")]
+    Io(usize, String),
+}
+
+impl NdjsonError {
+    fn shift_lines(self, lines: usize) -> NdjsonError {
+        match self {
+            NdjsonError::Syntax(line, error) => NdjsonError::Syntax(line + lines, error),
+            NdjsonError::BlankLine(line) => NdjsonError::BlankLine(line + lines),
+            NdjsonError::MissingNewline(line) => NdjsonError::MissingNewline(line + lines),
+            NdjsonError::Io(line, message) => NdjsonError::Io(line + lines, message),
+        }
+    }
+
+    pub fn line(&self) -> usize {
+        match self {
+            NdjsonError::Syntax(line, _)
+            | NdjsonError::BlankLine(line)
+            | NdjsonError::MissingNewline(line)
+            | NdjsonError::Io(line, _) => *line,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OnError {
+    // the error is the last item of the iteration
+    #[default]
+    Stop,
+    // the bad line is reported and reading goes on with the next one
+    Continue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NdjsonOptions {
+    pub skip_blank_lines: bool,
+    pub allow_missing_final_newline: bool,
+    pub on_error: OnError,
+}
+
+impl Default for NdjsonOptions {
+    fn default() -> Self {
+        NdjsonOptions {
+            skip_blank_lines: true,
+            allow_missing_final_newline: true,
+            on_error: OnError::Stop,
+        }
+    }
+}
+
+// Reads one JSON document per line, only one line is held in memory at a time
+#[derive(Debug)]
+pub struct NdjsonReader<R> {
+    reader: R,
+    options: NdjsonOptions,
+    line: usize,
+    buffer: Vec<u8>,
+    stopped: bool,
+}
+
+impl<R: BufRead> NdjsonReader<R> {
+    pub fn new(reader: R) -> NdjsonReader<R> {
+        NdjsonReader::with_options(reader, NdjsonOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: NdjsonOptions) -> NdjsonReader<R> {
+        NdjsonReader {
+            reader,
+            options,
+            line: 0,
+            buffer: Vec::new(),
+            stopped: false,
+        }
+    }
+
+    // The number of the last line read
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn next_value(&mut self) -> Option<Result<JsonValue, NdjsonError>> {
+        while !self.stopped {
+            self.buffer.clear();
+            self.line += 1;
+
+            let read = match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(read) => read,
+                Err(error) => {
+                    self.stopped = true;
+                    return Some(Err(NdjsonError::Io(self.line, error.to_string())));
+                }
+            };
+            if read == 0 {
+                self.stopped = true;
+                break;
+            }
+
+            let result = parse_line(&self.buffer, self.line, &self.options);
+            match result {
+                None => continue,
+                Some(Err(_)) if self.options.on_error == OnError::Stop => self.stopped = true,
+                Some(_) => {}
+            }
+            return result;
+        }
+
+        None
+    }
+}
+
+// Parses one line as read with its terminator, None when it is a blank line to skip.
+// The async reader shares it.
+pub(crate) fn parse_line(
+    buffer: &[u8],
+    number: usize,
+    options: &NdjsonOptions,
+) -> Option<Result<JsonValue, NdjsonError>> {
+    let mut line = buffer;
+    let terminated = line.last() == Some(&b'\n');
+    if terminated {
+        line = &line[..line.len() - 1];
+    }
+    if line.last() == Some(&b'\r') {
+        line = &line[..line.len() - 1];
+    }
+    if line.iter().all(|byte| matches!(byte, b' ' | b'\t')) {
+        return if options.skip_blank_lines {
+            None
+        } else {
+            Some(Err(NdjsonError::BlankLine(number)))
+        };
+    }
+
+    if !terminated && !options.allow_missing_final_newline {
+        return Some(Err(NdjsonError::MissingNewline(number)));
+    }
+
+    let mut parser = ValuePushParser::new();
+    let value = match parser.feed(line) {
+        Ok(_) => parser.finish(),
+        Err(error) => Err(error),
+    };
+    Some(value.map_err(|error| NdjsonError::Syntax(number, error)))
+}
+
+impl<R: BufRead> Iterator for NdjsonReader<R> {
+    type Item = Result<JsonValue, NdjsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_value()
+    }
+}
+
+// Writes every value compact on its own line
+#[derive(Debug)]
+pub struct NdjsonWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub fn new(writer: W) -> NdjsonWriter<W> {
+        NdjsonWriter { writer }
+    }
+
+    pub fn write(&mut self, value: &JsonValue) -> io::Result<()> {
+        writeln!(self.writer, "{value}")
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+// Splits the input in about `workers` parts at line boundaries and parses them on
+// scoped threads. The results are in input order, exactly as NdjsonReader returns them,
+// each with the number of its line.
+pub fn parse_parallel(
+    input: &[u8],
+    workers: usize,
+    options: &NdjsonOptions,
+) -> Vec<(usize, Result<JsonValue, NdjsonError>)> {
+    let chunks = split_lines(input, workers.max(1));
+
+    // every worker continues on errors, stopping is applied once the parts are merged
+    let worker_options = NdjsonOptions {
+        on_error: OnError::Continue,
+        ..options.clone()
+    };
+
+    type Part = (usize, Vec<(usize, Result<JsonValue, NdjsonError>)>);
+    let parts: Vec<Part> = thread::scope(|scope| {
+        let handles: Vec<_> = chunks
+            .iter()
+            .map(|chunk| {
+                let options = worker_options.clone();
+                scope.spawn(move || {
+                    let mut reader = NdjsonReader::with_options(*chunk, options);
+                    let mut results = Vec::new();
+                    while let Some(result) = reader.next_value() {
+                        results.push((reader.line(), result));
+                    }
+                    (reader.line() - 1, results)
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("ndjson worker panicked"))
+            .collect()
+    });
+
+    let mut results = Vec::new();
+    let mut lines_before = 0;
+    for (lines, part) in parts {
+        for (line, result) in part {
+            let result = result.map_err(|error| error.shift_lines(lines_before));
+            let failed = result.is_err();
+            results.push((line + lines_before, result));
+            if failed && options.on_error == OnError::Stop {
+                return results;
+            }
+        }
+        lines_before += lines;
+    }
+
+    results
+}
+
+fn split_lines(input: &[u8], parts: usize) -> Vec<&[u8]> {
+    let mut chunks = Vec::with_capacity(parts);
+    let mut start = 0;
+
+    for part in 1..parts {
+        let target = (input.len() * part / parts).max(start);
+        let Some(newline) = input[target..].iter().position(|byte| *byte == b'\n') else {
+            break;
+        };
+        let end = target + newline + 1;
+        if end > start {
+            chunks.push(&input[start..end]);
+            start = end;
+        }
+    }
+
+    if start < input.len() {
+        chunks.push(&input[start..]);
+    }
+    chunks
+}
+
+pub fn to_ndjson_string<'a, I>(values: I) -> String
+where
+    I: IntoIterator<Item = &'a JsonValue>,
+{
+    values
+        .into_iter()
+        .map(|value| format!("{value}\n"))
+        .collect()
+}
+
+#[cfg(test)]
+pub mod ndjson_tests {
+    use super::*;
+    use crate::json::events::Position;
+    use crate::json::syntactic_analyzer::{parse_str, JsonPair};
+    use pretty_assertions::assert_eq;
+
+    fn read(input: &str, options: NdjsonOptions) -> Vec<Result<JsonValue, NdjsonError>> {
+        NdjsonReader::with_options(input.as_bytes(), options).collect()
+    }
+
+    #[test]
+    pub fn test_read_lines() {
+        let input = "{\"id\": 1}\r\n\n  \n[true, null]\n\"last\"";
+        let values: Vec<JsonValue> = NdjsonReader::new(input.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(
+            values,
+            vec![
+                parse_str(r#"{"id": 1}"#).unwrap(),
+                parse_str("[true, null]").unwrap(),
+                JsonValue::String("last".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_errors_carry_line_numbers() {
+        let input = "1\n{\"a\" 2}\n3\n";
+
+        let results = read(input, NdjsonOptions::default());
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            results[1],
+            Err(NdjsonError::Syntax(
+                2,
+                EventError::UnexpectedToken(
+                    "number",
+                    Position {
+                        offset: 5,
+                        line: 1,
+                        column: 6
+                    }
+                )
+            ))
+        );
+
+        let options = NdjsonOptions {
+            on_error: OnError::Continue,
+            ..NdjsonOptions::default()
+        };
+        let results = read(input, options);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1].as_ref().unwrap_err().line(), 2);
+        assert_eq!(results[2], Ok(JsonValue::Number(3.0)));
+    }
+
+    #[test]
+    pub fn test_strict_options() {
+        let options = NdjsonOptions {
+            skip_blank_lines: false,
+            allow_missing_final_newline: false,
+            on_error: OnError::Continue,
+        };
+
+        assert_eq!(
+            read("1\n\n2", options),
+            vec![
+                Ok(JsonValue::Number(1.0)),
+                Err(NdjsonError::BlankLine(2)),
+                Err(NdjsonError::MissingNewline(3)),
+            ]
+        );
+        // two values on one line are not allowed
+        assert!(matches!(
+            read("1 2\n", NdjsonOptions::default())[0],
+            Err(NdjsonError::Syntax(1, EventError::TrailingCharacters(_)))
+        ));
+    }
+
+    #[test]
+    pub fn test_parse_parallel() {
+        let mut input = String::new();
+        for index in 0..500 {
+            match index % 97 {
+                13 => input.push_str("{\"broken\": }\n"),
+                50 => input.push('\n'),
+                _ => input.push_str(&format!("{{\"id\": {index}, \"tags\": [\"a\", \"b\"]}}\n")),
+            }
+        }
+        input.push_str("[\"no final newline\"]");
+
+        let continue_options = NdjsonOptions {
+            on_error: OnError::Continue,
+            ..NdjsonOptions::default()
+        };
+        for options in [NdjsonOptions::default(), continue_options] {
+            let mut reader = NdjsonReader::with_options(input.as_bytes(), options.clone());
+            let mut sequential = Vec::new();
+            while let Some(result) = reader.next_value() {
+                sequential.push((reader.line(), result));
+            }
+            for workers in [1, 2, 3, 8, 1000] {
+                assert_eq!(
+                    parse_parallel(input.as_bytes(), workers, &options),
+                    sequential
+                );
+            }
+        }
+        assert_eq!(parse_parallel(b"", 4, &NdjsonOptions::default()), vec![]);
+
+        // skipped blank lines still count
+        assert_eq!(
+            parse_parallel(b"1\n\n\n2\n", 2, &NdjsonOptions::default()),
+            vec![
+                (1, Ok(JsonValue::Number(1.0))),
+                (4, Ok(JsonValue::Number(2.0))),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_write_and_read_back() {
+        let values = vec![
+            JsonValue::JsonObject(vec![JsonPair(
+                "text".to_string(),
+                JsonValue::String("multi\nline".to_string()),
+            )]),
+            JsonValue::JsonArray(vec![JsonValue::Number(1.5), JsonValue::Null]),
+        ];
+
+        let mut writer = NdjsonWriter::new(Vec::new());
+        for value in &values {
+            writer.write(value).unwrap();
+        }
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+
+        assert_eq!(output, "{\"text\":\"multi\\nline\"}\n[1.5,null]\n");
+        assert_eq!(to_ndjson_string(&values), output);
+
+        let read_back: Vec<JsonValue> = NdjsonReader::new(output.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(read_back, values);
+    }
+}
@@ -0,0 +1,660 @@
+use thiserror::Error;
+
+use super::pointer::{JsonPointer, PointerError};
+use super::syntactic_analyzer::{JsonPair, JsonValue};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum PatchError {
+    #[error("Patch document must be an array of operations")]
+    NotAnArray,
+
+    #[error("Patch operation {0} is not an object")]
+    NotAnObject(usize),
+
+    #[error("Patch operation {0} is missing the '{1}' member")]
+    MissingMember(usize, &'static str),
+
+    #[error("Patch operation {0} has an invalid '{1}' member")]
+    InvalidMember(usize, &'static str),
+
+    #[error("Unknown patch operation: {0}")]
+    UnknownOperation(String),
+
+    #[error("Patch operation {0} failed: {1}")]
+    Pointer(usize, PointerError),
+
+    #[error("Patch operation {0} failed, no value at {1}")]
+    NotFound(usize, JsonPointer),
+
+    #[error("Patch operation {0} can not move {1} into one of its children")]
+    MoveIntoChild(usize, JsonPointer),
+
+    #[error("Test operation {0} failed at {1}")]
+    TestFailed(usize, JsonPointer),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOperation {
+    Add {
+        path: JsonPointer,
+        value: JsonValue,
+    },
+    Remove {
+        path: JsonPointer,
+    },
+    Replace {
+        path: JsonPointer,
+        value: JsonValue,
+    },
+    Move {
+        from: JsonPointer,
+        path: JsonPointer,
+    },
+    Copy {
+        from: JsonPointer,
+        path: JsonPointer,
+    },
+    Test {
+        path: JsonPointer,
+        value: JsonValue,
+    },
+}
+
+impl PatchOperation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PatchOperation::Add { .. } => "add",
+            PatchOperation::Remove { .. } => "remove",
+            PatchOperation::Replace { .. } => "replace",
+            PatchOperation::Move { .. } => "move",
+            PatchOperation::Copy { .. } => "copy",
+            PatchOperation::Test { .. } => "test",
+        }
+    }
+
+    pub fn from_value(index: usize, operation: &JsonValue) -> Result<PatchOperation, PatchError> {
+        let JsonValue::JsonObject(members) = operation else {
+            return Err(PatchError::NotAnObject(index));
+        };
+
+        let member = |name: &'static str| {
+            members
+                .iter()
+                .find(|JsonPair(key, _)| key == name)
+                .map(|JsonPair(_, value)| value)
+                .ok_or(PatchError::MissingMember(index, name))
+        };
+        let pointer = |name: &'static str| match member(name)? {
+            JsonValue::String(pointer) => {
+                JsonPointer::parse(pointer).map_err(|_| PatchError::InvalidMember(index, name))
+            }
+            _ => Err(PatchError::InvalidMember(index, name)),
+        };
+
+        let JsonValue::String(op) = member("op")? else {
+            return Err(PatchError::InvalidMember(index, "op"));
+        };
+
+        let operation = match op.as_str() {
+            "add" => PatchOperation::Add {
+                path: pointer("path")?,
+                value: member("value")?.clone(),
+            },
+            "remove" => PatchOperation::Remove {
+                path: pointer("path")?,
+            },
+            "replace" => PatchOperation::Replace {
+                path: pointer("path")?,
+                value: member("value")?.clone(),
+            },
+            "move" => PatchOperation::Move {
+                from: pointer("from")?,
+                path: pointer("path")?,
+            },
+            "copy" => PatchOperation::Copy {
+                from: pointer("from")?,
+                path: pointer("path")?,
+            },
+            "test" => PatchOperation::Test {
+                path: pointer("path")?,
+                value: member("value")?.clone(),
+            },
+            other => return Err(PatchError::UnknownOperation(other.to_owned())),
+        };
+
+        Ok(operation)
+    }
+
+    pub fn to_value(&self) -> JsonValue {
+        let string = |value: &str| JsonValue::String(value.to_owned());
+        let mut members = vec![JsonPair("op".to_owned(), string(self.name()))];
+
+        match self {
+            PatchOperation::Add { path, value }
+            | PatchOperation::Replace { path, value }
+            | PatchOperation::Test { path, value } => {
+                members.push(JsonPair("path".to_owned(), string(&path.to_string())));
+                members.push(JsonPair("value".to_owned(), value.clone()));
+            }
+            PatchOperation::Remove { path } => {
+                members.push(JsonPair("path".to_owned(), string(&path.to_string())));
+            }
+            PatchOperation::Move { from, path } | PatchOperation::Copy { from, path } => {
+                members.push(JsonPair("from".to_owned(), string(&from.to_string())));
+                members.push(JsonPair("path".to_owned(), string(&path.to_string())));
+            }
+        }
+
+        JsonValue::JsonObject(members)
+    }
+
+    fn apply(&self, index: usize, target: &mut JsonValue) -> Result<(), PatchError> {
+        let pointer_error = |error| PatchError::Pointer(index, error);
+
+        match self {
+            PatchOperation::Add { path, value } => {
+                path.insert(target, value.clone()).map_err(pointer_error)?;
+            }
+            PatchOperation::Remove { path } => {
+                path.remove(target).map_err(pointer_error)?;
+            }
+            PatchOperation::Replace { path, value } => {
+                let Some(existing) = path.get_mut(target) else {
+                    return Err(PatchError::NotFound(index, path.clone()));
+                };
+                *existing = value.clone();
+            }
+            PatchOperation::Move { from, path } => {
+                if from == path {
+                    return Ok(());
+                }
+                if path.starts_with(from) {
+                    return Err(PatchError::MoveIntoChild(index, from.clone()));
+                }
+
+                let value = from.remove(target).map_err(pointer_error)?;
+                path.insert(target, value).map_err(pointer_error)?;
+            }
+            PatchOperation::Copy { from, path } => {
+                let Some(value) = from.get(target) else {
+                    return Err(PatchError::NotFound(index, from.clone()));
+                };
+                path.insert(target, value.clone()).map_err(pointer_error)?;
+            }
+            PatchOperation::Test { path, value } => {
+                let Some(existing) = path.get(target) else {
+                    return Err(PatchError::NotFound(index, path.clone()));
+                };
+                if !existing.equivalent(value) {
+                    return Err(PatchError::TestFailed(index, path.clone()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Patch(pub Vec<PatchOperation>);
+
+impl Patch {
+    pub fn from_value(document: &JsonValue) -> Result<Patch, PatchError> {
+        let JsonValue::JsonArray(operations) = document else {
+            return Err(PatchError::NotAnArray);
+        };
+
+        let operations = operations
+            .iter()
+            .enumerate()
+            .map(|(index, operation)| PatchOperation::from_value(index, operation))
+            .collect::<Result<Vec<PatchOperation>, PatchError>>()?;
+
+        Ok(Patch(operations))
+    }
+
+    pub fn to_value(&self) -> JsonValue {
+        JsonValue::JsonArray(self.0.iter().map(PatchOperation::to_value).collect())
+    }
+
+    pub fn operations(&self) -> &[PatchOperation] {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // The operations are applied to a working copy which only replaces the target
+    // once every operation succeeded, so a failing patch leaves the target untouched.
+    pub fn apply(&self, target: &mut JsonValue) -> Result<(), PatchError> {
+        let mut patched = target.clone();
+        for (index, operation) in self.0.iter().enumerate() {
+            operation.apply(index, &mut patched)?;
+        }
+
+        *target = patched;
+        Ok(())
+    }
+}
+
+pub fn apply_patch(target: &mut JsonValue, patch: &Patch) -> Result<(), PatchError> {
+    patch.apply(target)
+}
+
+// Generates a patch that turns `from` into `to`. Objects are compared member by member
+// and arrays are aligned on their longest common subsequence, so only the differing
+// parts of the documents end up in the patch.
+pub fn diff(from: &JsonValue, to: &JsonValue) -> Patch {
+    let mut operations = Vec::new();
+    diff_values(JsonPointer::root(), from, to, &mut operations);
+    Patch(operations)
+}
+
+fn diff_values(
+    path: JsonPointer,
+    from: &JsonValue,
+    to: &JsonValue,
+    operations: &mut Vec<PatchOperation>,
+) {
+    if from.equivalent(to) {
+        return;
+    }
+
+    match (from, to) {
+        (JsonValue::JsonObject(from_members), JsonValue::JsonObject(to_members)) => {
+            for JsonPair(key, value) in from_members.iter() {
+                match to_members.iter().find(|pair| pair.0 == *key) {
+                    Some(JsonPair(_, other)) => {
+                        diff_values(path.clone().with(key.as_str()), value, other, operations)
+                    }
+                    None => operations.push(PatchOperation::Remove {
+                        path: path.clone().with(key.as_str()),
+                    }),
+                }
+            }
+
+            for JsonPair(key, value) in to_members.iter() {
+                if !from_members.iter().any(|pair| pair.0 == *key) {
+                    operations.push(PatchOperation::Add {
+                        path: path.clone().with(key.as_str()),
+                        value: value.clone(),
+                    });
+                }
+            }
+        }
+        (JsonValue::JsonArray(from_elements), JsonValue::JsonArray(to_elements)) => {
+            diff_arrays(path, from_elements, to_elements, operations);
+        }
+        _ => operations.push(PatchOperation::Replace {
+            path,
+            value: to.clone(),
+        }),
+    }
+}
+
+fn diff_arrays(
+    path: JsonPointer,
+    from: &[JsonValue],
+    to: &[JsonValue],
+    operations: &mut Vec<PatchOperation>,
+) {
+    let table = lcs_table(from, to, JsonValue::equivalent);
+
+    // `position` tracks the index in the array as it looks after the operations so far
+    let (mut i, mut j, mut position) = (0, 0, 0);
+    while i < from.len() || j < to.len() {
+        if i < from.len() && j < to.len() && from[i].equivalent(&to[j]) {
+            i += 1;
+            j += 1;
+            position += 1;
+        } else if i < from.len() && j < to.len() && table[i + 1][j + 1] == table[i][j] {
+            // replacing this element in place keeps the common subsequence intact
+            diff_values(
+                path.clone().with_index(position),
+                &from[i],
+                &to[j],
+                operations,
+            );
+            i += 1;
+            j += 1;
+            position += 1;
+        } else if j < to.len() && (i == from.len() || table[i][j + 1] >= table[i + 1][j]) {
+            operations.push(PatchOperation::Add {
+                path: path.clone().with_index(position),
+                value: to[j].clone(),
+            });
+            j += 1;
+            position += 1;
+        } else {
+            operations.push(PatchOperation::Remove {
+                path: path.clone().with_index(position),
+            });
+            i += 1;
+        }
+    }
+}
+
+// table[i][j] is the length of the longest common subsequence of from[i..] and to[j..]
+pub(crate) fn lcs_table<F>(from: &[JsonValue], to: &[JsonValue], equal: F) -> Vec<Vec<usize>>
+where
+    F: Fn(&JsonValue, &JsonValue) -> bool,
+{
+    let mut table = vec![vec![0; to.len() + 1]; from.len() + 1];
+    for i in (0..from.len()).rev() {
+        for j in (0..to.len()).rev() {
+            table[i][j] = if equal(&from[i], &to[j]) {
+                table[i + 1][j + 1] + 1
+            } else {
+                table[i + 1][j].max(table[i][j + 1])
+            };
+        }
+    }
+
+    table
+}
+
+#[cfg(test)]
+pub mod patch_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn string(value: &str) -> JsonValue {
+        JsonValue::String(value.to_string())
+    }
+
+    fn object(members: Vec<(&str, JsonValue)>) -> JsonValue {
+        JsonValue::JsonObject(
+            members
+                .into_iter()
+                .map(|(key, value)| JsonPair(key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    fn pointer(path: &str) -> JsonPointer {
+        JsonPointer::parse(path).unwrap()
+    }
+
+    #[test]
+    pub fn test_parse_patch_document() {
+        let document = JsonValue::JsonArray(vec![
+            object(vec![
+                ("op", string("test")),
+                ("path", string("/a/b/c")),
+                ("value", string("foo")),
+            ]),
+            object(vec![("op", string("remove")), ("path", string("/a/b/c"))]),
+            object(vec![
+                ("op", string("move")),
+                ("from", string("/a/b/c")),
+                ("path", string("/a/b/d")),
+            ]),
+        ]);
+
+        let patch = Patch::from_value(&document).unwrap();
+        assert_eq!(
+            patch.operations(),
+            &[
+                PatchOperation::Test {
+                    path: pointer("/a/b/c"),
+                    value: string("foo")
+                },
+                PatchOperation::Remove {
+                    path: pointer("/a/b/c")
+                },
+                PatchOperation::Move {
+                    from: pointer("/a/b/c"),
+                    path: pointer("/a/b/d")
+                },
+            ]
+        );
+        assert_eq!(patch.to_value(), document);
+    }
+
+    #[test]
+    pub fn test_parse_invalid_patch_document() {
+        let unknown = JsonValue::JsonArray(vec![object(vec![
+            ("op", string("frobnicate")),
+            ("path", string("")),
+        ])]);
+        assert_eq!(
+            Patch::from_value(&unknown),
+            Err(PatchError::UnknownOperation("frobnicate".to_string()))
+        );
+
+        let missing_value = JsonValue::JsonArray(vec![object(vec![
+            ("op", string("add")),
+            ("path", string("/a")),
+        ])]);
+        assert_eq!(
+            Patch::from_value(&missing_value),
+            Err(PatchError::MissingMember(0, "value"))
+        );
+
+        assert_eq!(
+            Patch::from_value(&JsonValue::Null),
+            Err(PatchError::NotAnArray)
+        );
+    }
+
+    #[test]
+    pub fn test_apply_rfc_examples() {
+        // RFC 6902, appendix A.1 and A.2
+        let mut document = object(vec![("foo", string("bar"))]);
+        let patch = Patch(vec![PatchOperation::Add {
+            path: pointer("/baz"),
+            value: string("qux"),
+        }]);
+        patch.apply(&mut document).unwrap();
+        assert_eq!(
+            document,
+            object(vec![("foo", string("bar")), ("baz", string("qux"))])
+        );
+
+        let mut document = object(vec![(
+            "foo",
+            JsonValue::JsonArray(vec![string("bar"), string("baz")]),
+        )]);
+        let patch = Patch(vec![PatchOperation::Add {
+            path: pointer("/foo/1"),
+            value: string("qux"),
+        }]);
+        patch.apply(&mut document).unwrap();
+        assert_eq!(
+            document,
+            object(vec![(
+                "foo",
+                JsonValue::JsonArray(vec![string("bar"), string("qux"), string("baz")])
+            )])
+        );
+
+        // RFC 6902, appendix A.6 and A.7
+        let mut document = object(vec![
+            (
+                "foo",
+                object(vec![("bar", string("baz")), ("waldo", string("fred"))]),
+            ),
+            ("qux", object(vec![("corge", string("grault"))])),
+        ]);
+        let patch = Patch(vec![PatchOperation::Move {
+            from: pointer("/foo/waldo"),
+            path: pointer("/qux/thud"),
+        }]);
+        patch.apply(&mut document).unwrap();
+        assert_eq!(
+            document,
+            object(vec![
+                ("foo", object(vec![("bar", string("baz"))])),
+                (
+                    "qux",
+                    object(vec![("corge", string("grault")), ("thud", string("fred"))])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    pub fn test_failed_patch_rolls_back() {
+        let original = object(vec![
+            ("baz", string("qux")),
+            (
+                "foo",
+                JsonValue::JsonArray(vec![string("a"), JsonValue::Number(2.0)]),
+            ),
+        ]);
+        let mut document = original.clone();
+
+        let patch = Patch(vec![
+            PatchOperation::Replace {
+                path: pointer("/baz"),
+                value: string("boo"),
+            },
+            PatchOperation::Copy {
+                from: pointer("/foo/0"),
+                path: pointer("/copied"),
+            },
+            PatchOperation::Test {
+                path: pointer("/foo/1"),
+                value: string("2"),
+            },
+        ]);
+
+        assert_eq!(
+            patch.apply(&mut document),
+            Err(PatchError::TestFailed(2, pointer("/foo/1")))
+        );
+        assert_eq!(document, original);
+    }
+
+    #[test]
+    pub fn test_apply_errors() {
+        let mut document = object(vec![("a", object(vec![("b", JsonValue::Null)]))]);
+
+        let patch = Patch(vec![PatchOperation::Move {
+            from: pointer("/a"),
+            path: pointer("/a/b/c"),
+        }]);
+        assert_eq!(
+            patch.apply(&mut document),
+            Err(PatchError::MoveIntoChild(0, pointer("/a")))
+        );
+
+        let patch = Patch(vec![PatchOperation::Replace {
+            path: pointer("/missing"),
+            value: JsonValue::Null,
+        }]);
+        assert_eq!(
+            patch.apply(&mut document),
+            Err(PatchError::NotFound(0, pointer("/missing")))
+        );
+
+        let patch = Patch(vec![PatchOperation::Remove {
+            path: pointer("/a/c"),
+        }]);
+        assert_eq!(
+            patch.apply(&mut document),
+            Err(PatchError::Pointer(
+                0,
+                PointerError::NotFound("/a/c".to_string())
+            ))
+        );
+    }
+
+    #[test]
+    pub fn test_test_ignores_member_order() {
+        let mut document = object(vec![(
+            "a",
+            object(vec![("x", JsonValue::Number(1.0)), ("y", JsonValue::Null)]),
+        )]);
+        let patch = Patch(vec![PatchOperation::Test {
+            path: pointer("/a"),
+            value: object(vec![("y", JsonValue::Null), ("x", JsonValue::Number(1.0))]),
+        }]);
+
+        assert_eq!(patch.apply(&mut document), Ok(()));
+    }
+
+    #[test]
+    pub fn test_diff_objects() {
+        let from = object(vec![
+            ("name", string("crate")),
+            ("version", string("0.1.0")),
+            ("private", JsonValue::Boolean(true)),
+        ]);
+        let to = object(vec![
+            ("name", string("crate")),
+            ("version", string("0.2.0")),
+            ("license", string("MIT")),
+        ]);
+
+        let patch = diff(&from, &to);
+        assert_eq!(
+            patch.operations(),
+            &[
+                PatchOperation::Replace {
+                    path: pointer("/version"),
+                    value: string("0.2.0")
+                },
+                PatchOperation::Remove {
+                    path: pointer("/private")
+                },
+                PatchOperation::Add {
+                    path: pointer("/license"),
+                    value: string("MIT")
+                },
+            ]
+        );
+
+        let mut patched = from.clone();
+        patch.apply(&mut patched).unwrap();
+        assert!(patched.equivalent(&to));
+    }
+
+    #[test]
+    pub fn test_diff_arrays() {
+        let numbers = |values: &[f32]| {
+            JsonValue::JsonArray(values.iter().map(|n| JsonValue::Number(*n)).collect())
+        };
+        let from = numbers(&[1.0, 2.0, 3.0, 4.0]);
+        let to = numbers(&[0.0, 1.0, 3.0, 4.0, 5.0]);
+
+        let patch = diff(&from, &to);
+        assert_eq!(
+            patch.operations(),
+            &[
+                PatchOperation::Add {
+                    path: pointer("/0"),
+                    value: JsonValue::Number(0.0)
+                },
+                PatchOperation::Remove {
+                    path: pointer("/2")
+                },
+                PatchOperation::Add {
+                    path: pointer("/4"),
+                    value: JsonValue::Number(5.0)
+                },
+            ]
+        );
+
+        let mut patched = from.clone();
+        patch.apply(&mut patched).unwrap();
+        assert_eq!(patched, to);
+
+        assert!(diff(&to, &to).is_empty());
+    }
+
+    #[test]
+    pub fn test_diff_nested_array_elements() {
+        let from = JsonValue::JsonArray(vec![object(vec![("id", JsonValue::Number(1.0))])]);
+        let to = JsonValue::JsonArray(vec![object(vec![("id", JsonValue::Number(2.0))])]);
+
+        assert_eq!(
+            diff(&from, &to).operations(),
+            &[PatchOperation::Replace {
+                path: pointer("/0/id"),
+                value: JsonValue::Number(2.0)
+            }]
+        );
+    }
+}
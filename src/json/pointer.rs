@@ -0,0 +1,468 @@
+use std::{fmt, str::FromStr};
+
+use thiserror::Error;
+
+use super::syntactic_analyzer::{JsonPair, JsonValue};
+
+#[derive(Debug, Clone, Error, PartialEq)]
+pub enum PointerError {
+    #[error("Json pointer must be empty or start with '/'")]
+    MissingLeadingSlash,
+
+    #[error("Invalid escape sequence in json pointer segment: {0}")]
+    InvalidEscape(String),
+
+    #[error("Invalid array index: {0}")]
+    InvalidIndex(String),
+
+    #[error("Array index {0} is out of bounds")]
+    IndexOutOfBounds(usize),
+
+    #[error("No value found at: {0}")]
+    NotFound(String),
+
+    #[error("Can not index into a scalar value at: {0}")]
+    NotAContainer(String),
+
+    #[error("The root value can not be removed")]
+    RemoveRoot,
+}
+
+// A parsed RFC 6901 pointer, stored as unescaped reference tokens.
+// The empty pointer refers to the whole document.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct JsonPointer {
+    segments: Vec<String>,
+}
+
+impl JsonPointer {
+    pub fn root() -> JsonPointer {
+        JsonPointer::default()
+    }
+
+    pub fn parse(pointer: &str) -> Result<JsonPointer, PointerError> {
+        if pointer.is_empty() {
+            return Ok(JsonPointer::root());
+        }
+
+        let Some(pointer) = pointer.strip_prefix('/') else {
+            return Err(PointerError::MissingLeadingSlash);
+        };
+
+        let segments = pointer
+            .split('/')
+            .map(unescape_segment)
+            .collect::<Result<Vec<String>, PointerError>>()?;
+
+        Ok(JsonPointer { segments })
+    }
+
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn push(&mut self, segment: impl Into<String>) {
+        self.segments.push(segment.into());
+    }
+
+    pub fn pop(&mut self) -> Option<String> {
+        self.segments.pop()
+    }
+
+    // Builder style version of push, handy when composing paths in one expression
+    pub fn with(mut self, segment: impl Into<String>) -> JsonPointer {
+        self.push(segment);
+        self
+    }
+
+    pub fn with_index(self, index: usize) -> JsonPointer {
+        self.with(index.to_string())
+    }
+
+    pub fn last(&self) -> Option<&str> {
+        self.segments.last().map(String::as_str)
+    }
+
+    pub fn parent(&self) -> Option<JsonPointer> {
+        if self.is_root() {
+            return None;
+        }
+
+        let mut parent = self.clone();
+        parent.pop();
+        Some(parent)
+    }
+
+    pub fn starts_with(&self, other: &JsonPointer) -> bool {
+        self.segments.starts_with(&other.segments)
+    }
+
+    pub fn get<'a>(&self, root: &'a JsonValue) -> Option<&'a JsonValue> {
+        let mut current = root;
+        for segment in self.segments.iter() {
+            current = match current {
+                JsonValue::JsonObject(members) => member(members, segment)?,
+                JsonValue::JsonArray(elements) => elements.get(parse_index(segment).ok()?)?,
+                _ => return None,
+            };
+        }
+
+        Some(current)
+    }
+
+    pub fn get_mut<'a>(&self, root: &'a mut JsonValue) -> Option<&'a mut JsonValue> {
+        let mut current = root;
+        for segment in self.segments.iter() {
+            current = match current {
+                JsonValue::JsonObject(members) => member_mut(members, segment)?,
+                JsonValue::JsonArray(elements) => elements.get_mut(parse_index(segment).ok()?)?,
+                _ => return None,
+            };
+        }
+
+        Some(current)
+    }
+
+    // Adds `value` at the location of the pointer following the RFC 6902 "add" rules:
+    // object members are created or replaced, array elements are inserted before the
+    // index ("-" appends) and the root pointer replaces the whole document.
+    // The replaced value, if any, is returned.
+    pub fn insert(
+        &self,
+        root: &mut JsonValue,
+        value: JsonValue,
+    ) -> Result<Option<JsonValue>, PointerError> {
+        let Some((last, parent)) = self.split_last() else {
+            return Ok(Some(std::mem::replace(root, value)));
+        };
+
+        match parent.get_mut(root) {
+            Some(JsonValue::JsonObject(members)) => match member_mut(members, last) {
+                Some(existing) => Ok(Some(std::mem::replace(existing, value))),
+                None => {
+                    members.push(JsonPair(last.to_owned(), value));
+                    Ok(None)
+                }
+            },
+            Some(JsonValue::JsonArray(elements)) => {
+                let index = if last == "-" {
+                    elements.len()
+                } else {
+                    parse_index(last)?
+                };
+
+                if index > elements.len() {
+                    return Err(PointerError::IndexOutOfBounds(index));
+                }
+
+                elements.insert(index, value);
+                Ok(None)
+            }
+            Some(_) => Err(PointerError::NotAContainer(parent.to_string())),
+            None => Err(PointerError::NotFound(parent.to_string())),
+        }
+    }
+
+    pub fn remove(&self, root: &mut JsonValue) -> Result<JsonValue, PointerError> {
+        let Some((last, parent)) = self.split_last() else {
+            return Err(PointerError::RemoveRoot);
+        };
+
+        match parent.get_mut(root) {
+            Some(JsonValue::JsonObject(members)) => {
+                let Some(position) = members.iter().position(|JsonPair(key, _)| key == last) else {
+                    return Err(PointerError::NotFound(self.to_string()));
+                };
+
+                Ok(members.remove(position).1)
+            }
+            Some(JsonValue::JsonArray(elements)) => {
+                let index = parse_index(last)?;
+                if index >= elements.len() {
+                    return Err(PointerError::IndexOutOfBounds(index));
+                }
+
+                Ok(elements.remove(index))
+            }
+            Some(_) => Err(PointerError::NotAContainer(parent.to_string())),
+            None => Err(PointerError::NotFound(parent.to_string())),
+        }
+    }
+
+    fn split_last(&self) -> Option<(&str, JsonPointer)> {
+        let (last, parent) = self.segments.split_last()?;
+        Some((
+            last.as_str(),
+            JsonPointer {
+                segments: parent.to_vec(),
+            },
+        ))
+    }
+}
+
+impl FromStr for JsonPointer {
+    type Err = PointerError;
+
+    fn from_str(pointer: &str) -> Result<Self, Self::Err> {
+        JsonPointer::parse(pointer)
+    }
+}
+
+impl fmt::Display for JsonPointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in self.segments.iter() {
+            write!(f, "/{}", escape_segment(segment))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for JsonPointer {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        JsonPointer {
+            segments: iter.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl JsonValue {
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonValue> {
+        JsonPointer::parse(pointer).ok()?.get(self)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut JsonValue> {
+        JsonPointer::parse(pointer).ok()?.get_mut(self)
+    }
+}
+
+pub fn escape_segment(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+pub fn unescape_segment(segment: &str) -> Result<String, PointerError> {
+    let mut result = String::with_capacity(segment.len());
+    let mut characters = segment.chars();
+
+    while let Some(ch) = characters.next() {
+        if ch != '~' {
+            result.push(ch);
+            continue;
+        }
+
+        match characters.next() {
+            Some('0') => result.push('~'),
+            Some('1') => result.push('/'),
+            _ => return Err(PointerError::InvalidEscape(segment.to_owned())),
+        }
+    }
+
+    Ok(result)
+}
+
+// Array indices are either "0" or a number without leading zeros
+pub fn parse_index(segment: &str) -> Result<usize, PointerError> {
+    let valid = !segment.is_empty()
+        && segment.chars().all(|ch| ch.is_ascii_digit())
+        && (segment == "0" || !segment.starts_with('0'));
+
+    if !valid {
+        return Err(PointerError::InvalidIndex(segment.to_owned()));
+    }
+
+    segment
+        .parse::<usize>()
+        .map_err(|_| PointerError::InvalidIndex(segment.to_owned()))
+}
+
+fn member<'a>(members: &'a [JsonPair], key: &str) -> Option<&'a JsonValue> {
+    members
+        .iter()
+        .find(|JsonPair(name, _)| name == key)
+        .map(|JsonPair(_, value)| value)
+}
+
+fn member_mut<'a>(members: &'a mut [JsonPair], key: &str) -> Option<&'a mut JsonValue> {
+    members
+        .iter_mut()
+        .find(|JsonPair(name, _)| name == key)
+        .map(|JsonPair(_, value)| value)
+}
+
+#[cfg(test)]
+pub mod pointer_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn pair(key: &str, value: JsonValue) -> JsonPair {
+        JsonPair(key.to_string(), value)
+    }
+
+    // The example document from RFC 6901, section 5
+    fn rfc_document() -> JsonValue {
+        JsonValue::JsonObject(vec![
+            pair(
+                "foo",
+                JsonValue::JsonArray(vec![
+                    JsonValue::String("bar".to_string()),
+                    JsonValue::String("baz".to_string()),
+                ]),
+            ),
+            pair("", JsonValue::Number(0.0)),
+            pair("a/b", JsonValue::Number(1.0)),
+            pair("c%d", JsonValue::Number(2.0)),
+            pair("e^f", JsonValue::Number(3.0)),
+            pair("g|h", JsonValue::Number(4.0)),
+            pair("i\\j", JsonValue::Number(5.0)),
+            pair("k\"l", JsonValue::Number(6.0)),
+            pair(" ", JsonValue::Number(7.0)),
+            pair("m~n", JsonValue::Number(8.0)),
+        ])
+    }
+
+    #[test]
+    pub fn test_pointer_rfc_examples() {
+        let document = rfc_document();
+
+        assert_eq!(document.pointer(""), Some(&document));
+        assert_eq!(
+            document.pointer("/foo/0"),
+            Some(&JsonValue::String("bar".to_string()))
+        );
+        assert_eq!(document.pointer("/"), Some(&JsonValue::Number(0.0)));
+        assert_eq!(document.pointer("/a~1b"), Some(&JsonValue::Number(1.0)));
+        assert_eq!(document.pointer("/c%d"), Some(&JsonValue::Number(2.0)));
+        assert_eq!(document.pointer("/e^f"), Some(&JsonValue::Number(3.0)));
+        assert_eq!(document.pointer("/g|h"), Some(&JsonValue::Number(4.0)));
+        assert_eq!(document.pointer("/i\\j"), Some(&JsonValue::Number(5.0)));
+        assert_eq!(document.pointer("/k\"l"), Some(&JsonValue::Number(6.0)));
+        assert_eq!(document.pointer("/ "), Some(&JsonValue::Number(7.0)));
+        assert_eq!(document.pointer("/m~0n"), Some(&JsonValue::Number(8.0)));
+    }
+
+    #[test]
+    pub fn test_pointer_misses() {
+        let document = rfc_document();
+
+        assert_eq!(document.pointer("foo"), None);
+        assert_eq!(document.pointer("/foo/2"), None);
+        assert_eq!(document.pointer("/foo/-"), None);
+        assert_eq!(document.pointer("/foo/01"), None);
+        assert_eq!(document.pointer("/foo/0/bar"), None);
+        assert_eq!(document.pointer("/m~2n"), None);
+    }
+
+    #[test]
+    pub fn test_pointer_mut() {
+        let mut document = rfc_document();
+
+        *document.pointer_mut("/foo/1").unwrap() = JsonValue::Null;
+        assert_eq!(document.pointer("/foo/1"), Some(&JsonValue::Null));
+    }
+
+    #[test]
+    pub fn test_parse_and_display_round_trip() {
+        let pointer = JsonPointer::parse("/a~1b/m~0n/0").unwrap();
+
+        assert_eq!(pointer.segments(), &["a/b", "m~n", "0"]);
+        assert_eq!(pointer.to_string(), "/a~1b/m~0n/0");
+        assert_eq!(JsonPointer::root().to_string(), "");
+        assert_eq!(
+            "a/b".parse::<JsonPointer>(),
+            Err(PointerError::MissingLeadingSlash)
+        );
+        assert_eq!(
+            JsonPointer::parse("/~"),
+            Err(PointerError::InvalidEscape("~".to_string()))
+        );
+    }
+
+    #[test]
+    pub fn test_build_segment_by_segment() {
+        let pointer = JsonPointer::root().with("foo").with_index(1);
+
+        assert_eq!(pointer.to_string(), "/foo/1");
+        assert_eq!(pointer.last(), Some("1"));
+        assert_eq!(pointer.parent(), Some(JsonPointer::root().with("foo")));
+        assert!(pointer.starts_with(&JsonPointer::root().with("foo")));
+        assert_eq!(
+            pointer.get(&rfc_document()),
+            Some(&JsonValue::String("baz".to_string()))
+        );
+    }
+
+    #[test]
+    pub fn test_insert() {
+        let mut document = rfc_document();
+
+        let pointer = JsonPointer::parse("/foo/1").unwrap();
+        assert_eq!(pointer.insert(&mut document, JsonValue::Null), Ok(None));
+
+        let pointer = JsonPointer::parse("/foo/-").unwrap();
+        assert_eq!(
+            pointer.insert(&mut document, JsonValue::Boolean(true)),
+            Ok(None)
+        );
+        assert_eq!(
+            document.pointer("/foo"),
+            Some(&JsonValue::JsonArray(vec![
+                JsonValue::String("bar".to_string()),
+                JsonValue::Null,
+                JsonValue::String("baz".to_string()),
+                JsonValue::Boolean(true),
+            ]))
+        );
+
+        let pointer = JsonPointer::parse("/m~0n").unwrap();
+        assert_eq!(
+            pointer.insert(&mut document, JsonValue::Null),
+            Ok(Some(JsonValue::Number(8.0)))
+        );
+
+        let pointer = JsonPointer::parse("/new").unwrap();
+        assert_eq!(pointer.insert(&mut document, JsonValue::Null), Ok(None));
+        assert_eq!(document.pointer("/new"), Some(&JsonValue::Null));
+
+        let pointer = JsonPointer::parse("/foo/9").unwrap();
+        assert_eq!(
+            pointer.insert(&mut document, JsonValue::Null),
+            Err(PointerError::IndexOutOfBounds(9))
+        );
+
+        let pointer = JsonPointer::parse("/missing/key").unwrap();
+        assert_eq!(
+            pointer.insert(&mut document, JsonValue::Null),
+            Err(PointerError::NotFound("/missing".to_string()))
+        );
+    }
+
+    #[test]
+    pub fn test_remove() {
+        let mut document = rfc_document();
+
+        let pointer = JsonPointer::parse("/foo/0").unwrap();
+        assert_eq!(
+            pointer.remove(&mut document),
+            Ok(JsonValue::String("bar".to_string()))
+        );
+
+        let pointer = JsonPointer::parse("/a~1b").unwrap();
+        assert_eq!(pointer.remove(&mut document), Ok(JsonValue::Number(1.0)));
+        assert_eq!(document.pointer("/a~1b"), None);
+
+        let pointer = JsonPointer::parse("/e^f/x").unwrap();
+        assert_eq!(
+            pointer.remove(&mut document),
+            Err(PointerError::NotAContainer("/e^f".to_string()))
+        );
+
+        assert_eq!(
+            JsonPointer::root().remove(&mut document),
+            Err(PointerError::RemoveRoot)
+        );
+    }
+}
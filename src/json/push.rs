@@ -0,0 +1,233 @@
+use super::byte_lexer::ByteLexer;
+use super::events::{Event, EventError, Grammar, Position};
+use super::syntactic_analyzer::{JsonValue, ValueBuilder};
+
+// Push parser: the caller hands over the input chunk by chunk as it arrives (from a
+// socket, a callback based API, ...) and gets back the events completed so far.
+// Everything in between, including a half read string, escape or number, is kept
+// until the next chunk.
+#[derive(Debug, Clone, Default)]
+pub struct PushParser {
+    lexer: ByteLexer,
+    grammar: Grammar,
+    error: Option<EventError>,
+}
+
+impl PushParser {
+    pub fn new() -> PushParser {
+        PushParser::default()
+    }
+
+    pub fn position(&self) -> Position {
+        self.lexer.position()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.grammar.depth()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.grammar.is_complete() && self.lexer.is_between_lexemes()
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<(Event<'static>, Position)>, EventError> {
+        let mut events = Vec::new();
+        self.feed_with(chunk, |event, position| events.push((event, position)))?;
+        Ok(events)
+    }
+
+    // Same as feed, without collecting the events. Once an error was returned every
+    // following call fails with it.
+    pub fn feed_with<F>(&mut self, chunk: &[u8], mut callback: F) -> Result<(), EventError>
+    where
+        F: FnMut(Event<'static>, Position),
+    {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+
+        let mut rest = chunk;
+        while !rest.is_empty() {
+            let (consumed, result) = self.lexer.next_lexeme(rest);
+            rest = &rest[consumed..];
+
+            let Some((lexeme, position)) = self.record(result)? else {
+                continue;
+            };
+            let event = self.grammar.accept(lexeme, position);
+            if let Some(event) = self.record(event)? {
+                callback(event, position);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Signals the end of the input. Returns the last events (a number at the very end
+    // is only complete now), or an error when the input stopped in the middle of a value.
+    pub fn finish(&mut self) -> Result<Vec<(Event<'static>, Position)>, EventError> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+
+        let mut events = Vec::new();
+        let last = self.lexer.finish();
+        if let Some((lexeme, position)) = self.record(last)? {
+            let event = self.grammar.accept(lexeme, position);
+            if let Some(event) = self.record(event)? {
+                events.push((event, position));
+            }
+        }
+
+        let end = self.grammar.finish(self.lexer.position());
+        self.record(end)?;
+        Ok(events)
+    }
+
+    fn record<T>(&mut self, result: Result<T, EventError>) -> Result<T, EventError> {
+        result.inspect_err(|error| self.error = Some(error.clone()))
+    }
+}
+
+// Push parser building the JsonValue instead of returning events
+#[derive(Debug, Default)]
+pub struct ValuePushParser {
+    parser: PushParser,
+    builder: ValueBuilder,
+    value: Option<JsonValue>,
+}
+
+impl ValuePushParser {
+    pub fn new() -> ValuePushParser {
+        ValuePushParser::default()
+    }
+
+    pub fn position(&self) -> Position {
+        self.parser.position()
+    }
+
+    // Returns the value as soon as its last byte was fed, None while it is incomplete
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Option<&JsonValue>, EventError> {
+        let builder = &mut self.builder;
+        let value = &mut self.value;
+        self.parser.feed_with(chunk, |event, _| {
+            if let Some(completed) = builder.push(event) {
+                *value = Some(completed);
+            }
+        })?;
+
+        Ok(self.value.as_ref())
+    }
+
+    pub fn finish(mut self) -> Result<JsonValue, EventError> {
+        for (event, _) in self.parser.finish()? {
+            if let Some(completed) = self.builder.push(event) {
+                self.value = Some(completed);
+            }
+        }
+
+        self.value
+            .ok_or(EventError::UnexpectedEnd(self.parser.position()))
+    }
+}
+
+#[cfg(test)]
+pub mod push_tests {
+    use super::*;
+    use crate::json::events::EventReader;
+    use crate::json::syntactic_analyzer::parse_str;
+    use pretty_assertions::assert_eq;
+
+    fn push_events(chunks: &[&[u8]]) -> Result<Vec<Event<'static>>, EventError> {
+        let mut parser = PushParser::new();
+        let mut events = Vec::new();
+        for chunk in chunks {
+            events.extend(parser.feed(chunk)?.into_iter().map(|(event, _)| event));
+        }
+        events.extend(parser.finish()?.into_iter().map(|(event, _)| event));
+        Ok(events)
+    }
+
+    #[test]
+    pub fn test_feed_byte_by_byte() {
+        let input = r#"{"name": "café ☕", "sizes": [-1.5e2, 0, 42], "ok": true}"#;
+        let chunks: Vec<&[u8]> = input.as_bytes().chunks(1).collect();
+        let expected: Vec<Event> = EventReader::new(input)
+            .map(|result| result.unwrap().0.into_owned())
+            .collect();
+
+        assert_eq!(push_events(&chunks).unwrap(), expected);
+    }
+
+    #[test]
+    pub fn test_events_are_returned_as_they_complete() {
+        let mut parser = PushParser::new();
+
+        let events = parser.feed(br#"[{"ke"#).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(parser.depth(), 2);
+
+        let events = parser.feed(br#"y": "va\"#).unwrap();
+        assert_eq!(events[0].0, Event::Key("key".into()));
+        assert_eq!(events.len(), 1);
+
+        let events = parser.feed(br#""lue"}, 12"#).unwrap();
+        assert_eq!(events[0].0, Event::String("va\"lue".into()));
+        assert_eq!(events[1].0, Event::EndObject);
+        assert_eq!(events.len(), 2);
+
+        // the number may continue in the next chunk
+        let events = parser.feed(b"34]").unwrap();
+        assert_eq!(events[0].0, Event::Number(1234.0));
+        assert_eq!(events[1].0, Event::EndArray);
+        assert!(parser.is_complete());
+        assert_eq!(parser.finish().unwrap(), vec![]);
+    }
+
+    #[test]
+    pub fn test_finish_reports_truncation() {
+        assert!(matches!(
+            push_events(&[b"[1, ", b"2"]),
+            Err(EventError::UnexpectedEnd(_))
+        ));
+        assert!(matches!(
+            push_events(&[b"\"abc\\"]),
+            Err(EventError::UnexpectedEnd(_))
+        ));
+        assert!(matches!(
+            push_events(&[b"[nu"]),
+            Err(EventError::InvalidLiteral(_))
+        ));
+        assert_eq!(
+            push_events(&[b"-1", b"7"]).unwrap(),
+            vec![Event::Number(-17.0)]
+        );
+    }
+
+    #[test]
+    pub fn test_errors_are_sticky() {
+        let mut parser = PushParser::new();
+        let error = parser.feed(b"[1 2 ").unwrap_err();
+
+        assert!(matches!(error, EventError::UnexpectedToken("number", _)));
+        assert_eq!(parser.feed(b"]"), Err(error.clone()));
+        assert_eq!(parser.finish(), Err(error));
+    }
+
+    #[test]
+    pub fn test_value_push_parser() {
+        let input = r#"{"a": [1, {"b": null}], "c": "d"}"#;
+        let mut parser = ValuePushParser::new();
+
+        for chunk in input.as_bytes()[..input.len() - 1].chunks(5) {
+            assert_eq!(parser.feed(chunk).unwrap(), None);
+        }
+        let expected = parse_str(input).unwrap();
+        assert_eq!(parser.feed(b"}").unwrap(), Some(&expected));
+        assert_eq!(parser.finish().unwrap(), expected);
+
+        let mut parser = ValuePushParser::new();
+        parser.feed(b"3.2").unwrap();
+        assert_eq!(parser.finish().unwrap(), JsonValue::Number(3.2));
+    }
+}
@@ -0,0 +1,480 @@
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::ops::{Index, Range};
+
+use thiserror::Error;
+
+use super::events::{for_each_event, EventError, EventReader, Grammar, Position};
+use super::pointer::{parse_index, JsonPointer, PointerError};
+use super::scanner::{Lexeme, Scanner};
+use super::syntactic_analyzer::{parse_events, JsonValue};
+
+#[derive(Debug, Clone, Error, PartialEq)]
+pub enum LookupError {
+    #[error(transparent)]
+    Syntax(#[from] EventError),
+
+    #[error(transparent)]
+    Pointer(#[from] PointerError),
+
+    #[error("The object has no member '{0}'")]
+    MissingKey(String),
+
+    #[error("Index {0} is out of bounds")]
+    IndexOutOfBounds(usize),
+
+    #[error("Expected an object at {0}")]
+    NotAnObject(Position),
+
+    #[error("Expected an array at {0}")]
+    NotAnArray(Position),
+
+    #[error("Expected an object or an array at {0}")]
+    NotAContainer(Position),
+}
+
+// The text of one JSON value, known to be valid, kept without building anything
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RawValue<'a> {
+    text: &'a str,
+    position: Position,
+}
+
+impl<'a> RawValue<'a> {
+    // Validates that the whole input is one JSON value, surrounding whitespace excluded
+    pub fn parse_str(input: &'a str) -> Result<RawValue<'a>, EventError> {
+        let mut scanner = Scanner::new(input);
+        let raw = skip_value(input, &mut scanner)?;
+
+        if let Some((_, position)) = scanner.next_lexeme()? {
+            return Err(EventError::TrailingCharacters(position));
+        }
+        Ok(raw)
+    }
+
+    pub fn as_str(&self) -> &'a str {
+        self.text
+    }
+
+    // where the value starts in the whole input
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.position.offset..self.position.offset + self.text.len()
+    }
+
+    pub fn parse(&self) -> Result<JsonValue, EventError> {
+        parse_events(EventReader::new(self.text))
+    }
+}
+
+// A position in a JSON text where a value starts. The whole text is validated once when
+// the document is created, so an error in a subtree that is never visited is not missed.
+// Looking up a member or an element then only scans as far as needed: the values in
+// front of it are skipped without being built.
+//
+// `get` and `at` are the fallible lookups. Indexing, as in doc["items"][3]["id"], hands
+// out a reference, so the first index into a container scans all of its members or
+// elements once and keeps where they start. A missing key or index, or indexing into a
+// scalar, gives the missing node: a `null` shared by every document.
+#[derive(Debug, Clone)]
+pub struct LazyValue<'a> {
+    input: &'a str,
+    position: Position,
+    children: OnceCell<Box<Children<'a>>>,
+}
+
+// The text of the missing node, told apart from a `null` of a document by its address
+static MISSING: &str = "null";
+
+// The members or elements of a container, the keys are None in arrays
+type Entries<'a> = Vec<(Option<Cow<'a, str>>, LazyValue<'a>)>;
+
+#[derive(Debug, Clone)]
+struct Children<'a> {
+    entries: Entries<'a>,
+    missing: LazyValue<'a>,
+}
+
+// Two nodes are equal when they start at the same place of the same text
+impl PartialEq for LazyValue<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.input, other.input) && self.position == other.position
+    }
+}
+
+impl<'a> LazyValue<'a> {
+    pub fn new(input: &'a str) -> Result<LazyValue<'a>, EventError> {
+        for_each_event(input, |_, _| {})?;
+
+        let mut scanner = Scanner::new(input);
+        scanner.skip_whitespace();
+        Ok(LazyValue::at_position(input, scanner.position()))
+    }
+
+    fn at_position(input: &'a str, position: Position) -> LazyValue<'a> {
+        LazyValue {
+            input,
+            position,
+            children: OnceCell::new(),
+        }
+    }
+
+    fn missing() -> LazyValue<'a> {
+        LazyValue::at_position(MISSING, Scanner::new(MISSING).position())
+    }
+
+    // Whether the node stands for a key or an index that was not found by indexing
+    pub fn is_missing(&self) -> bool {
+        std::ptr::eq(self.input, MISSING)
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    pub fn get(&self, key: &str) -> Result<LazyValue<'a>, LookupError> {
+        let mut scanner = Scanner::at(self.input, self.position);
+        match next(&mut scanner)? {
+            (Lexeme::LeftBrace, _) => {}
+            (_, position) => return Err(LookupError::NotAnObject(position)),
+        }
+
+        let mut first = true;
+        loop {
+            match next(&mut scanner)? {
+                (Lexeme::String(name), _) => {
+                    match next(&mut scanner)? {
+                        (Lexeme::Colon, _) => {}
+                        (lexeme, position) => return Err(unexpected(&lexeme, position)),
+                    }
+                    if name == key {
+                        return Ok(self.resume(&mut scanner));
+                    }
+                    skip_value(self.input, &mut scanner)?;
+                }
+                (Lexeme::RightBrace, _) if first => {
+                    return Err(LookupError::MissingKey(key.to_owned()))
+                }
+                (lexeme, position) => return Err(unexpected(&lexeme, position)),
+            }
+
+            match next(&mut scanner)? {
+                (Lexeme::Comma, _) => first = false,
+                (Lexeme::RightBrace, _) => return Err(LookupError::MissingKey(key.to_owned())),
+                (lexeme, position) => return Err(unexpected(&lexeme, position)),
+            }
+        }
+    }
+
+    pub fn at(&self, index: usize) -> Result<LazyValue<'a>, LookupError> {
+        let mut scanner = Scanner::at(self.input, self.position);
+        match next(&mut scanner)? {
+            (Lexeme::LeftBracket, _) => {}
+            (_, position) => return Err(LookupError::NotAnArray(position)),
+        }
+
+        // an empty array has no element at all
+        let mut after_bracket = scanner.clone();
+        if let (Lexeme::RightBracket, _) = next(&mut after_bracket)? {
+            return Err(LookupError::IndexOutOfBounds(index));
+        }
+
+        for current in 0.. {
+            if current == index {
+                return Ok(self.resume(&mut scanner));
+            }
+            skip_value(self.input, &mut scanner)?;
+
+            match next(&mut scanner)? {
+                (Lexeme::Comma, _) => {}
+                (Lexeme::RightBracket, _) => return Err(LookupError::IndexOutOfBounds(index)),
+                (lexeme, position) => return Err(unexpected(&lexeme, position)),
+            }
+        }
+        unreachable!("the loop only ends by returning")
+    }
+
+    // Follows a JSON pointer, reading numeric segments as indexes inside arrays
+    pub fn pointer(&self, pointer: &str) -> Result<LazyValue<'a>, LookupError> {
+        let pointer = JsonPointer::parse(pointer)?;
+        let mut value = self.clone();
+
+        for segment in pointer.segments() {
+            value = match self.input.as_bytes().get(value.position.offset) {
+                Some(b'{') => value.get(segment)?,
+                Some(b'[') => value.at(parse_index(segment)?)?,
+                _ => return Err(LookupError::NotAContainer(value.position)),
+            };
+        }
+
+        Ok(value)
+    }
+
+    pub fn raw(&self) -> Result<RawValue<'a>, EventError> {
+        skip_value(self.input, &mut Scanner::at(self.input, self.position))
+    }
+
+    pub fn parse(&self) -> Result<JsonValue, EventError> {
+        self.raw()?.parse()
+    }
+
+    fn resume(&self, scanner: &mut Scanner<'a>) -> LazyValue<'a> {
+        scanner.skip_whitespace();
+        LazyValue::at_position(self.input, scanner.position())
+    }
+
+    fn children(&self) -> &Children<'a> {
+        self.children.get_or_init(|| {
+            Box::new(Children {
+                // the text was validated as a whole, scanning it again cannot fail
+                entries: self.scan_children().unwrap_or_default(),
+                missing: LazyValue::missing(),
+            })
+        })
+    }
+
+    fn scan_children(&self) -> Result<Entries<'a>, LookupError> {
+        let mut scanner = Scanner::at(self.input, self.position);
+        let (object, close) = match next(&mut scanner)? {
+            (Lexeme::LeftBrace, _) => (true, Lexeme::RightBrace),
+            (Lexeme::LeftBracket, _) => (false, Lexeme::RightBracket),
+            _ => return Ok(Vec::new()),
+        };
+
+        let mut entries = Vec::new();
+        if next(&mut scanner.clone())?.0 == close {
+            return Ok(entries);
+        }
+        loop {
+            let key = if object {
+                let (Lexeme::String(name), _) = next(&mut scanner)? else {
+                    return Ok(entries);
+                };
+                // the colon
+                next(&mut scanner)?;
+                Some(name)
+            } else {
+                None
+            };
+            entries.push((key, self.resume(&mut scanner)));
+            skip_value(self.input, &mut scanner)?;
+
+            if next(&mut scanner)?.0 != Lexeme::Comma {
+                return Ok(entries);
+            }
+        }
+    }
+}
+
+// Indexing never fails, what is not there is the missing node
+impl<'a> Index<&str> for LazyValue<'a> {
+    type Output = LazyValue<'a>;
+
+    fn index(&self, key: &str) -> &LazyValue<'a> {
+        let children = self.children();
+        children
+            .entries
+            .iter()
+            .find(|(name, _)| name.as_deref() == Some(key))
+            .map_or(&children.missing, |(_, value)| value)
+    }
+}
+
+impl<'a> Index<usize> for LazyValue<'a> {
+    type Output = LazyValue<'a>;
+
+    fn index(&self, index: usize) -> &LazyValue<'a> {
+        let children = self.children();
+        match children.entries.get(index) {
+            Some((None, value)) => value,
+            _ => &children.missing,
+        }
+    }
+}
+
+fn next<'a>(scanner: &mut Scanner<'a>) -> Result<(Lexeme<'a>, Position), EventError> {
+    scanner
+        .next_lexeme()?
+        .ok_or(EventError::UnexpectedEnd(scanner.position()))
+}
+
+fn unexpected(lexeme: &Lexeme, position: Position) -> LookupError {
+    LookupError::Syntax(EventError::UnexpectedToken(lexeme.describe(), position))
+}
+
+// Runs the grammar over one value without building it
+fn skip_value<'a>(input: &'a str, scanner: &mut Scanner<'a>) -> Result<RawValue<'a>, EventError> {
+    scanner.skip_whitespace();
+    let position = scanner.position();
+    let mut grammar = Grammar::new();
+
+    while !grammar.is_complete() {
+        let (lexeme, lexeme_position) = next(scanner)?;
+        grammar.accept(lexeme, lexeme_position)?;
+    }
+
+    Ok(RawValue {
+        text: &input[position.offset..scanner.offset()],
+        position,
+    })
+}
+
+#[cfg(test)]
+pub mod raw_tests {
+    use super::*;
+    use crate::json::syntactic_analyzer::parse_str;
+    use pretty_assertions::assert_eq;
+
+    const INPUT: &str = r#"{
+        "meta": {"skipped": [1, 2, {"deep": true}]},
+        "items": [
+            {"id": 10}, {"id": 11}, {"id": 12},
+            {"name": "fourth", "id": 13}
+        ]
+    }"#;
+
+    #[test]
+    pub fn test_raw_value() {
+        let raw = RawValue::parse_str("  [1, {\"a\": null}]\n").unwrap();
+
+        assert_eq!(raw.as_str(), "[1, {\"a\": null}]");
+        assert_eq!(raw.range(), 2..18);
+        assert_eq!(
+            raw.parse().unwrap(),
+            parse_str("[1, {\"a\": null}]").unwrap()
+        );
+
+        assert!(matches!(
+            RawValue::parse_str("[1, 2"),
+            Err(EventError::UnexpectedEnd(_))
+        ));
+        assert!(matches!(
+            RawValue::parse_str("1 2"),
+            Err(EventError::TrailingCharacters(_))
+        ));
+    }
+
+    #[test]
+    pub fn test_lazy_lookup() {
+        let document = LazyValue::new(INPUT).unwrap();
+        let id = document
+            .get("items")
+            .unwrap()
+            .at(3)
+            .unwrap()
+            .get("id")
+            .unwrap();
+
+        assert_eq!(id.parse().unwrap(), JsonValue::Number(13.0));
+        assert_eq!(id.raw().unwrap().as_str(), "13");
+        assert_eq!((id.position().line, id.position().column), (5, 38));
+
+        let pointed = document.pointer("/items/3/name").unwrap();
+        assert_eq!(
+            pointed.parse().unwrap(),
+            JsonValue::String("fourth".to_string())
+        );
+        assert_eq!(document.pointer("").unwrap(), document);
+    }
+
+    #[test]
+    pub fn test_lazy_lookup_errors() {
+        let document = LazyValue::new(INPUT).unwrap();
+
+        assert_eq!(
+            document.get("nope"),
+            Err(LookupError::MissingKey("nope".to_string()))
+        );
+        assert_eq!(
+            document.get("items").unwrap().at(4),
+            Err(LookupError::IndexOutOfBounds(4))
+        );
+        assert!(matches!(document.at(0), Err(LookupError::NotAnArray(_))));
+        assert!(matches!(
+            document.pointer("/meta/skipped/x"),
+            Err(LookupError::Pointer(PointerError::InvalidIndex(_)))
+        ));
+        assert!(matches!(
+            document.pointer("/items/0/id/deeper"),
+            Err(LookupError::NotAContainer(_))
+        ));
+        assert!(matches!(
+            document.pointer("/items/01"),
+            Err(LookupError::Pointer(PointerError::InvalidIndex(_)))
+        ));
+        assert!(matches!(
+            document.pointer("/items/+1"),
+            Err(LookupError::Pointer(PointerError::InvalidIndex(_)))
+        ));
+        assert_eq!(
+            LazyValue::new("[]").unwrap().at(0),
+            Err(LookupError::IndexOutOfBounds(0))
+        );
+    }
+
+    #[test]
+    pub fn test_lazy_index() {
+        let document = LazyValue::new(INPUT).unwrap();
+        let id = &document["items"][3]["id"];
+
+        assert_eq!(id, &document.pointer("/items/3/id").unwrap());
+        assert_eq!(id.parse().unwrap(), JsonValue::Number(13.0));
+        assert!(!id.is_missing());
+        assert_eq!(
+            document["meta"]["skipped"][2]["deep"].parse().unwrap(),
+            JsonValue::Boolean(true)
+        );
+
+        // escaped keys are compared by their value
+        let escaped = LazyValue::new(r#"{"a\u0062": [null]}"#).unwrap();
+        assert_eq!(escaped["ab"][0].parse().unwrap(), JsonValue::Null);
+        assert!(!escaped["ab"][0].is_missing());
+    }
+
+    #[test]
+    pub fn test_lazy_index_missing() {
+        let document = LazyValue::new(INPUT).unwrap();
+
+        for missing in [
+            &document["nope"],
+            &document["items"][4],
+            &document["items"]["id"],
+            &document[0],
+            &document["items"][0]["id"]["deeper"],
+            &document["nope"]["deeper"][1],
+            &LazyValue::new("[]").unwrap()[0],
+        ] {
+            assert!(missing.is_missing());
+            assert_eq!(missing.parse().unwrap(), JsonValue::Null);
+        }
+        // the fallible lookups still tell what went wrong
+        assert_eq!(
+            document["items"].at(4),
+            Err(LookupError::IndexOutOfBounds(4))
+        );
+    }
+
+    #[test]
+    pub fn test_unvisited_values_are_validated() {
+        assert!(matches!(
+            LazyValue::new(r#"{"broken": [1 2], "wanted": 3}"#),
+            Err(EventError::UnexpectedToken("number", _))
+        ));
+        assert!(matches!(
+            LazyValue::new(r#"[{"a": tru}, 2]"#),
+            Err(EventError::InvalidLiteral(_))
+        ));
+        // the values after the one looked up are checked too
+        assert!(matches!(
+            LazyValue::new("[0, 1, oops"),
+            Err(EventError::UnexpectedCharacter('o', _))
+        ));
+        assert!(matches!(
+            LazyValue::new(r#"{"type": "ping"} 2"#),
+            Err(EventError::TrailingCharacters(_))
+        ));
+    }
+}
@@ -0,0 +1,768 @@
+use std::borrow::Cow;
+
+use super::dialect::Dialect;
+use super::events::{EventError, Position};
+
+// The lexical tokens of a JSON text. Unlike lexer::Token these keep the original
+// spelling of numbers and borrow strings from the input whenever there is nothing
+// to unescape.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lexeme<'a> {
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
+    Comma,
+    String(Cow<'a, str>),
+    Number(Cow<'a, str>),
+    // a number known only by its value, as the tokens of lexer::lex carry them
+    NumberValue(f32),
+    // an unquoted object key, only produced when the dialect allows them
+    Identifier(Cow<'a, str>),
+    True,
+    False,
+    Null,
+}
+
+impl Lexeme<'_> {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Lexeme::LeftBrace => "'{'",
+            Lexeme::RightBrace => "'}'",
+            Lexeme::LeftBracket => "'['",
+            Lexeme::RightBracket => "']'",
+            Lexeme::Colon => "':'",
+            Lexeme::Comma => "','",
+            Lexeme::String(_) => "string",
+            Lexeme::Number(_) | Lexeme::NumberValue(_) => "number",
+            Lexeme::Identifier(_) => "identifier",
+            Lexeme::True | Lexeme::False => "boolean",
+            Lexeme::Null => "null",
+        }
+    }
+
+    // The name a keyword stands for as an unquoted key. Keys are ECMAScript
+    // IdentifierNames, which the reserved words are too.
+    pub fn keyword_name(&self) -> Option<&'static str> {
+        match self {
+            Lexeme::True => Some("true"),
+            Lexeme::False => Some("false"),
+            Lexeme::Null => Some("null"),
+            Lexeme::Number(text) if text == "Infinity" => Some("Infinity"),
+            Lexeme::Number(text) if text == "NaN" => Some("NaN"),
+            _ => None,
+        }
+    }
+
+    pub fn into_owned(self) -> Lexeme<'static> {
+        match self {
+            Lexeme::LeftBrace => Lexeme::LeftBrace,
+            Lexeme::RightBrace => Lexeme::RightBrace,
+            Lexeme::LeftBracket => Lexeme::LeftBracket,
+            Lexeme::RightBracket => Lexeme::RightBracket,
+            Lexeme::Colon => Lexeme::Colon,
+            Lexeme::Comma => Lexeme::Comma,
+            Lexeme::String(string) => Lexeme::String(Cow::Owned(string.into_owned())),
+            Lexeme::Number(number) => Lexeme::Number(Cow::Owned(number.into_owned())),
+            Lexeme::NumberValue(number) => Lexeme::NumberValue(number),
+            Lexeme::Identifier(name) => Lexeme::Identifier(Cow::Owned(name.into_owned())),
+            Lexeme::True => Lexeme::True,
+            Lexeme::False => Lexeme::False,
+            Lexeme::Null => Lexeme::Null,
+        }
+    }
+}
+
+// A `// line` or `/* block */` comment skipped by the scanner, delimiters included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Comment<'a> {
+    pub text: &'a str,
+    pub position: Position,
+}
+
+impl Comment<'_> {
+    pub fn is_block(&self) -> bool {
+        self.text.starts_with("/*")
+    }
+
+    // the line the comment ends on
+    pub fn end_line(&self) -> usize {
+        self.position.line + self.text.matches('\n').count()
+    }
+}
+
+// Splits a &str into lexemes, keeping track of the position of each of them.
+// Columns are counted in bytes from the start of the line, both starting at 1.
+#[derive(Debug, Clone)]
+pub struct Scanner<'a> {
+    input: &'a str,
+    dialect: Dialect,
+    offset: usize,
+    line: usize,
+    line_start: usize,
+    // only collected when asked for with keep_comments
+    comments: Option<Vec<Comment<'a>>>,
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(input: &'a str) -> Scanner<'a> {
+        Scanner::with_dialect(input, Dialect::JSON)
+    }
+
+    pub fn with_dialect(input: &'a str, dialect: Dialect) -> Scanner<'a> {
+        Scanner {
+            input,
+            dialect,
+            offset: 0,
+            line: 1,
+            line_start: 0,
+            comments: None,
+        }
+    }
+
+    // Resumes scanning at a position previously reported by a scanner over the same input
+    pub fn at(input: &'a str, position: Position) -> Scanner<'a> {
+        Scanner {
+            input,
+            dialect: Dialect::JSON,
+            offset: position.offset,
+            line: position.line,
+            line_start: position.offset + 1 - position.column,
+            comments: None,
+        }
+    }
+
+    // Scans the rest of the input in another dialect, e.g. after Scanner::at
+    pub fn in_dialect(mut self, dialect: Dialect) -> Scanner<'a> {
+        self.dialect = dialect;
+        self
+    }
+
+    // Records the comments skipped from now on, to be collected with take_comments
+    pub fn keep_comments(mut self) -> Scanner<'a> {
+        self.comments = Some(Vec::new());
+        self
+    }
+
+    // The comments skipped since the last call, in input order
+    pub fn take_comments(&mut self) -> Vec<Comment<'a>> {
+        self.comments
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    pub fn input(&self) -> &'a str {
+        self.input
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn position(&self) -> Position {
+        Position {
+            offset: self.offset,
+            line: self.line,
+            column: self.offset - self.line_start + 1,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.offset).copied()
+    }
+
+    fn unexpected(&self) -> EventError {
+        match self.input[self.offset..].chars().next() {
+            Some(ch) => EventError::UnexpectedCharacter(ch, self.position()),
+            None => EventError::UnexpectedEnd(self.position()),
+        }
+    }
+
+    fn newline(&mut self) {
+        self.line += 1;
+        self.line_start = self.offset;
+    }
+
+    // Skips whitespace, and comments when the dialect has them. An unterminated block
+    // comment is left in place for next_lexeme to report.
+    pub fn skip_whitespace(&mut self) {
+        while let Some(byte) = self.peek() {
+            match byte {
+                b' ' | b'\t' | b'\r' => self.offset += 1,
+                b'\n' => {
+                    self.offset += 1;
+                    self.newline();
+                }
+                0x0b | 0x0c if self.dialect.extended_whitespace => self.offset += 1,
+                0x80.. if self.dialect.extended_whitespace => {
+                    match self.input[self.offset..].chars().next() {
+                        Some(ch) if ch.is_whitespace() || ch == '\u{feff}' => {
+                            self.offset += ch.len_utf8()
+                        }
+                        _ => break,
+                    }
+                }
+                b'/' if self.dialect.comments => {
+                    let rest = &self.input[self.offset..];
+                    let position = self.position();
+                    let text = if rest.starts_with("//") {
+                        let text = &rest[..rest.find('\n').unwrap_or(rest.len())];
+                        self.offset += text.len();
+                        text
+                    } else if let Some(body) = rest.strip_prefix("/*") {
+                        let Some(end) = body.find("*/") else {
+                            break;
+                        };
+                        let text = &rest[..2 + end + 2];
+                        let comment_end = self.offset + text.len();
+                        while self.offset < comment_end {
+                            self.offset += 1;
+                            if self.input.as_bytes()[self.offset - 1] == b'\n' {
+                                self.newline();
+                            }
+                        }
+                        text
+                    } else {
+                        break;
+                    };
+                    if let Some(comments) = self.comments.as_mut() {
+                        comments.push(Comment { text, position });
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    // Returns None once only whitespace is left
+    pub fn next_lexeme(&mut self) -> Result<Option<(Lexeme<'a>, Position)>, EventError> {
+        if self.dialect.hjson {
+            return Err(EventError::UnsupportedDialect);
+        }
+        self.skip_whitespace();
+        let position = self.position();
+
+        let Some(byte) = self.peek() else {
+            return Ok(None);
+        };
+
+        let punctuation = match byte {
+            b'{' => Some(Lexeme::LeftBrace),
+            b'}' => Some(Lexeme::RightBrace),
+            b'[' => Some(Lexeme::LeftBracket),
+            b']' => Some(Lexeme::RightBracket),
+            b':' => Some(Lexeme::Colon),
+            b',' => Some(Lexeme::Comma),
+            _ => None,
+        };
+        if let Some(lexeme) = punctuation {
+            self.offset += 1;
+            return Ok(Some((lexeme, position)));
+        }
+
+        let dialect = self.dialect;
+        let lexeme = match byte {
+            b'"' => Lexeme::String(self.scan_string(b'"')?),
+            b'\'' if dialect.single_quoted_strings => Lexeme::String(self.scan_string(b'\'')?),
+            b'-' | b'0'..=b'9' => Lexeme::Number(Cow::Borrowed(self.scan_number()?)),
+            b'+' | b'.' if dialect.extended_numbers => {
+                Lexeme::Number(Cow::Borrowed(self.scan_number()?))
+            }
+            b'/' if dialect.comments && self.input[self.offset..].starts_with("/*") => {
+                return Err(EventError::UnterminatedComment(position))
+            }
+            _ if dialect.identifier_keys && self.at_identifier_start() => self.scan_identifier(),
+            b'I' | b'N' if dialect.extended_numbers => {
+                Lexeme::Number(Cow::Borrowed(self.scan_number()?))
+            }
+            b't' => self.scan_literal("true", Lexeme::True)?,
+            b'f' => self.scan_literal("false", Lexeme::False)?,
+            b'n' => self.scan_literal("null", Lexeme::Null)?,
+            _ => return Err(self.unexpected()),
+        };
+
+        Ok(Some((lexeme, position)))
+    }
+
+    fn at_identifier_start(&self) -> bool {
+        self.input[self.offset..]
+            .chars()
+            .next()
+            .is_some_and(|ch| ch == '$' || ch == '_' || ch.is_alphabetic())
+    }
+
+    // Identifiers double as the keywords, so `true`, `NaN`, ... come out as values
+    fn scan_identifier(&mut self) -> Lexeme<'a> {
+        let start = self.offset;
+        let length = self.input[start..]
+            .find(|ch: char| !(ch == '$' || ch == '_' || ch.is_alphanumeric()))
+            .unwrap_or(self.input.len() - start);
+        self.offset += length;
+
+        let name = &self.input[start..self.offset];
+        match name {
+            "true" => Lexeme::True,
+            "false" => Lexeme::False,
+            "null" => Lexeme::Null,
+            "Infinity" | "NaN" if self.dialect.extended_numbers => {
+                Lexeme::Number(Cow::Borrowed(name))
+            }
+            name => Lexeme::Identifier(Cow::Borrowed(name)),
+        }
+    }
+
+    fn scan_literal(
+        &mut self,
+        literal: &'static str,
+        lexeme: Lexeme<'a>,
+    ) -> Result<Lexeme<'a>, EventError> {
+        if !self.input[self.offset..].starts_with(literal) {
+            return Err(EventError::InvalidLiteral(self.position()));
+        }
+
+        self.offset += literal.len();
+        Ok(lexeme)
+    }
+
+    fn scan_number(&mut self) -> Result<&'a str, EventError> {
+        let start = self.offset;
+        let position = self.position();
+        let extended = self.dialect.extended_numbers;
+        let bytes = self.input.as_bytes();
+        let digits = |offset: &mut usize, radix: u32| {
+            let begin = *offset;
+            while bytes
+                .get(*offset)
+                .is_some_and(|byte| (*byte as char).is_digit(radix))
+            {
+                *offset += 1;
+            }
+            *offset - begin
+        };
+
+        let mut offset = self.offset;
+        match bytes[offset] {
+            b'-' => offset += 1,
+            b'+' if extended => offset += 1,
+            _ => {}
+        }
+
+        if extended {
+            let rest = &self.input[offset..];
+            let keyword = ["Infinity", "NaN"]
+                .into_iter()
+                .find(|keyword| rest.starts_with(keyword));
+            let hex = rest.starts_with("0x") || rest.starts_with("0X");
+
+            if keyword.is_some() || hex {
+                offset += keyword.map_or(2, str::len);
+                if hex && digits(&mut offset, 16) == 0 {
+                    return Err(EventError::InvalidNumber(position));
+                }
+                self.offset = offset;
+                return Ok(&self.input[start..offset]);
+            }
+        }
+
+        let integer_digits = match bytes.get(offset) {
+            Some(b'0') => {
+                offset += 1;
+                1
+            }
+            Some(b'1'..=b'9') => digits(&mut offset, 10),
+            Some(b'.') if extended => 0,
+            _ => return Err(EventError::InvalidNumber(position)),
+        };
+
+        if bytes.get(offset) == Some(&b'.') {
+            offset += 1;
+            // the extended syntax allows 1. and .1, but not a lone point
+            let fraction_digits = digits(&mut offset, 10);
+            if fraction_digits == 0 && !(extended && integer_digits > 0) {
+                return Err(EventError::InvalidNumber(position));
+            }
+        }
+
+        if let Some(b'e' | b'E') = bytes.get(offset) {
+            offset += 1;
+            if let Some(b'+' | b'-') = bytes.get(offset) {
+                offset += 1;
+            }
+            if digits(&mut offset, 10) == 0 {
+                return Err(EventError::InvalidNumber(position));
+            }
+        }
+
+        // a number directly followed by more digits means a leading zero, e.g. 01
+        if bytes.get(offset).is_some_and(u8::is_ascii_digit) {
+            return Err(EventError::InvalidNumber(position));
+        }
+
+        self.offset = offset;
+        Ok(&self.input[start..offset])
+    }
+
+    fn scan_string(&mut self, quote: u8) -> Result<Cow<'a, str>, EventError> {
+        let bytes = self.input.as_bytes();
+        let allows_tab = self.dialect.extended_escapes;
+        let is_control = |byte: u8| byte < 0x20 && !(allows_tab && byte == b'\t');
+
+        // skip the opening quote
+        self.offset += 1;
+        let start = self.offset;
+
+        // fast path: no escapes means the string can be borrowed from the input
+        loop {
+            match bytes.get(self.offset) {
+                None => return Err(EventError::UnexpectedEnd(self.position())),
+                Some(byte) if *byte == quote => {
+                    let string = &self.input[start..self.offset];
+                    self.offset += 1;
+                    return Ok(Cow::Borrowed(string));
+                }
+                Some(b'\\') => break,
+                Some(byte) if is_control(*byte) => {
+                    return Err(EventError::InvalidString(self.position()))
+                }
+                Some(_) => self.offset += 1,
+            }
+        }
+
+        let mut string = self.input[start..self.offset].to_owned();
+        loop {
+            match bytes.get(self.offset) {
+                None => return Err(EventError::UnexpectedEnd(self.position())),
+                Some(byte) if *byte == quote => {
+                    self.offset += 1;
+                    return Ok(Cow::Owned(string));
+                }
+                Some(b'\\') => {
+                    let escape_position = self.position();
+                    self.offset += 1;
+                    let escaped = match bytes.get(self.offset) {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            self.offset += 1;
+                            let ch = self.scan_unicode_escape(escape_position)?;
+                            string.push(ch);
+                            continue;
+                        }
+                        Some(_) if self.dialect.extended_escapes => {
+                            if let Some(ch) = self.scan_extended_escape(escape_position)? {
+                                string.push(ch);
+                            }
+                            continue;
+                        }
+                        _ => return Err(EventError::InvalidEscape(escape_position)),
+                    };
+                    string.push(escaped);
+                    self.offset += 1;
+                }
+                Some(byte) if is_control(*byte) => {
+                    return Err(EventError::InvalidString(self.position()))
+                }
+                Some(_) => {
+                    // copy everything up to the next quote or escape in one go
+                    let chunk_start = self.offset;
+                    while bytes
+                        .get(self.offset)
+                        .is_some_and(|byte| *byte != quote && *byte != b'\\' && !is_control(*byte))
+                    {
+                        self.offset += 1;
+                    }
+                    string.push_str(&self.input[chunk_start..self.offset]);
+                }
+            }
+        }
+    }
+
+    // The ECMAScript escapes JSON does not have, called on the character after the
+    // backslash. Returns None for a line continuation.
+    fn scan_extended_escape(
+        &mut self,
+        escape_position: Position,
+    ) -> Result<Option<char>, EventError> {
+        let bytes = self.input.as_bytes();
+        let ch = self.input[self.offset..]
+            .chars()
+            .next()
+            .ok_or(EventError::UnexpectedEnd(self.position()))?;
+        self.offset += ch.len_utf8();
+
+        let escaped = match ch {
+            'v' => '\u{b}',
+            '0' if !bytes.get(self.offset).is_some_and(u8::is_ascii_digit) => '\0',
+            '1'..='9' => return Err(EventError::InvalidEscape(escape_position)),
+            'x' => {
+                let code = self
+                    .input
+                    .get(self.offset..self.offset + 2)
+                    .filter(|digits| digits.bytes().all(|byte| byte.is_ascii_hexdigit()))
+                    .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                    .ok_or(EventError::InvalidEscape(escape_position))?;
+                self.offset += 2;
+                char::from_u32(code).ok_or(EventError::InvalidEscape(escape_position))?
+            }
+            '\r' | '\n' => {
+                if ch == '\r' && bytes.get(self.offset) == Some(&b'\n') {
+                    self.offset += 1;
+                }
+                self.newline();
+                return Ok(None);
+            }
+            '\u{2028}' | '\u{2029}' => return Ok(None),
+            ch => ch,
+        };
+
+        Ok(Some(escaped))
+    }
+
+    fn scan_hex4(&mut self, escape_position: Position) -> Result<u32, EventError> {
+        let digits = self
+            .input
+            .get(self.offset..self.offset + 4)
+            .filter(|digits| digits.bytes().all(|byte| byte.is_ascii_hexdigit()))
+            .ok_or(EventError::InvalidEscape(escape_position))?;
+        self.offset += 4;
+
+        u32::from_str_radix(digits, 16).map_err(|_| EventError::InvalidEscape(escape_position))
+    }
+
+    // Called right after "\u", combines surrogate pairs into one character
+    fn scan_unicode_escape(&mut self, escape_position: Position) -> Result<char, EventError> {
+        let high = self.scan_hex4(escape_position)?;
+        let code = match high {
+            0xD800..=0xDBFF => {
+                if !self.input[self.offset..].starts_with("\\u") {
+                    return Err(EventError::InvalidEscape(escape_position));
+                }
+                self.offset += 2;
+                let low = self.scan_hex4(escape_position)?;
+                if !(0xDC00..=0xDFFF).contains(&low) {
+                    return Err(EventError::InvalidEscape(escape_position));
+                }
+                0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+            }
+            0xDC00..=0xDFFF => return Err(EventError::InvalidEscape(escape_position)),
+            code => code,
+        };
+
+        char::from_u32(code).ok_or(EventError::InvalidEscape(escape_position))
+    }
+}
+
+#[cfg(test)]
+pub mod scanner_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn lexemes(input: &str, dialect: Dialect) -> Result<Vec<Lexeme<'_>>, EventError> {
+        let mut scanner = Scanner::with_dialect(input, dialect);
+        let mut lexemes = Vec::new();
+        while let Some((lexeme, _)) = scanner.next_lexeme()? {
+            lexemes.push(lexeme);
+        }
+        Ok(lexemes)
+    }
+
+    fn position(offset: usize, line: usize, column: usize) -> Position {
+        Position {
+            offset,
+            line,
+            column,
+        }
+    }
+
+    #[test]
+    pub fn test_lexemes_and_positions() {
+        let mut scanner = Scanner::new("{\"a\": [1.5e3,\n  true, false, null]}");
+        let mut scanned = Vec::new();
+        while let Some(lexeme) = scanner.next_lexeme().unwrap() {
+            scanned.push(lexeme);
+        }
+
+        assert_eq!(
+            scanned,
+            vec![
+                (Lexeme::LeftBrace, position(0, 1, 1)),
+                (Lexeme::String(Cow::Borrowed("a")), position(1, 1, 2)),
+                (Lexeme::Colon, position(4, 1, 5)),
+                (Lexeme::LeftBracket, position(6, 1, 7)),
+                (Lexeme::Number(Cow::Borrowed("1.5e3")), position(7, 1, 8)),
+                (Lexeme::Comma, position(12, 1, 13)),
+                (Lexeme::True, position(16, 2, 3)),
+                (Lexeme::Comma, position(20, 2, 7)),
+                (Lexeme::False, position(22, 2, 9)),
+                (Lexeme::Comma, position(27, 2, 14)),
+                (Lexeme::Null, position(29, 2, 16)),
+                (Lexeme::RightBracket, position(33, 2, 20)),
+                (Lexeme::RightBrace, position(34, 2, 21)),
+            ]
+        );
+        assert_eq!(scanner.position(), position(35, 2, 22));
+    }
+
+    #[test]
+    pub fn test_strings() {
+        let scanned = lexemes(r#"["plain", "tab\té😀", "é"]"#, Dialect::JSON).unwrap();
+
+        // strings without escapes are borrowed from the input
+        assert!(matches!(scanned[1], Lexeme::String(Cow::Borrowed("plain"))));
+        assert!(matches!(&scanned[3], Lexeme::String(Cow::Owned(string)) if string == "tab\té😀"));
+        assert_eq!(scanned[5], Lexeme::String(Cow::Borrowed("é")));
+    }
+
+    #[test]
+    pub fn test_errors() {
+        for (input, expected) in [
+            (
+                "[x]",
+                EventError::UnexpectedCharacter('x', position(1, 1, 2)),
+            ),
+            (
+                "[\n é]",
+                EventError::UnexpectedCharacter('é', position(3, 2, 2)),
+            ),
+            ("[tru]", EventError::InvalidLiteral(position(1, 1, 2))),
+            ("[01]", EventError::InvalidNumber(position(1, 1, 2))),
+            ("[1.]", EventError::InvalidNumber(position(1, 1, 2))),
+            ("[-]", EventError::InvalidNumber(position(1, 1, 2))),
+            ("[1e+]", EventError::InvalidNumber(position(1, 1, 2))),
+            (r#"["\x"]"#, EventError::InvalidEscape(position(2, 1, 3))),
+            (
+                r#"["\udc00"]"#,
+                EventError::InvalidEscape(position(2, 1, 3)),
+            ),
+            (
+                r#"["\ud800A"]"#,
+                EventError::InvalidEscape(position(2, 1, 3)),
+            ),
+            ("[\"\t\"]", EventError::InvalidString(position(2, 1, 3))),
+            ("\"open", EventError::UnexpectedEnd(position(5, 1, 6))),
+            (
+                "['single']",
+                EventError::UnexpectedCharacter('\'', position(1, 1, 2)),
+            ),
+            (
+                "[1] // comment",
+                EventError::UnexpectedCharacter('/', position(4, 1, 5)),
+            ),
+        ] {
+            assert_eq!(lexemes(input, Dialect::JSON), Err(expected), "{input}");
+        }
+
+        assert_eq!(
+            lexemes("[1] /* open", Dialect::JSONC),
+            Err(EventError::UnterminatedComment(position(4, 1, 5)))
+        );
+        assert_eq!(
+            lexemes("{a: b\n}", Dialect::HJSON),
+            Err(EventError::UnsupportedDialect)
+        );
+    }
+
+    #[test]
+    pub fn test_json5_lexemes() {
+        let scanned = lexemes(
+            "{key: 'it\\'s', $x: [+1, .5, 0xFF, -Infinity, NaN],}\u{a0}",
+            Dialect::JSON5,
+        )
+        .unwrap();
+
+        assert_eq!(
+            scanned,
+            vec![
+                Lexeme::LeftBrace,
+                Lexeme::Identifier(Cow::Borrowed("key")),
+                Lexeme::Colon,
+                Lexeme::String(Cow::Owned("it's".to_string())),
+                Lexeme::Comma,
+                Lexeme::Identifier(Cow::Borrowed("$x")),
+                Lexeme::Colon,
+                Lexeme::LeftBracket,
+                Lexeme::Number(Cow::Borrowed("+1")),
+                Lexeme::Comma,
+                Lexeme::Number(Cow::Borrowed(".5")),
+                Lexeme::Comma,
+                Lexeme::Number(Cow::Borrowed("0xFF")),
+                Lexeme::Comma,
+                Lexeme::Number(Cow::Borrowed("-Infinity")),
+                Lexeme::Comma,
+                Lexeme::Number(Cow::Borrowed("NaN")),
+                Lexeme::RightBracket,
+                Lexeme::Comma,
+                Lexeme::RightBrace,
+            ]
+        );
+        assert_eq!(scanned[17].describe(), "']'");
+        assert_eq!(scanned[16].keyword_name(), Some("NaN"));
+        assert_eq!(Lexeme::Null.keyword_name(), Some("null"));
+        assert_eq!(scanned[1].keyword_name(), None);
+    }
+
+    #[test]
+    pub fn test_comments() {
+        let input = "// head\n[1, /* two\n */ 2]";
+        let mut scanner = Scanner::with_dialect(input, Dialect::JSONC).keep_comments();
+        let mut scanned = Vec::new();
+        while let Some((lexeme, position)) = scanner.next_lexeme().unwrap() {
+            scanned.push((lexeme, position.line));
+        }
+
+        assert_eq!(
+            scanned,
+            vec![
+                (Lexeme::LeftBracket, 2),
+                (Lexeme::Number(Cow::Borrowed("1")), 2),
+                (Lexeme::Comma, 2),
+                (Lexeme::Number(Cow::Borrowed("2")), 3),
+                (Lexeme::RightBracket, 3),
+            ]
+        );
+
+        let comments = scanner.take_comments();
+        assert_eq!(
+            comments
+                .iter()
+                .map(|comment| comment.text)
+                .collect::<Vec<_>>(),
+            vec!["// head", "/* two\n */"]
+        );
+        assert!(!comments[0].is_block());
+        assert_eq!((comments[1].position.line, comments[1].end_line()), (2, 3));
+        assert!(scanner.take_comments().is_empty());
+    }
+
+    #[test]
+    pub fn test_resume_at_position() {
+        let input = "[1, 'two']";
+        let mut scanner = Scanner::new(input);
+        for _ in 0..3 {
+            scanner.next_lexeme().unwrap();
+        }
+
+        let mut resumed = Scanner::at(input, scanner.position()).in_dialect(Dialect::JSON5);
+        assert_eq!(
+            resumed.next_lexeme().unwrap(),
+            Some((Lexeme::String(Cow::Borrowed("two")), position(4, 1, 5)))
+        );
+        assert_eq!(resumed.dialect(), Dialect::JSON5);
+        assert_eq!(
+            scanner.next_lexeme(),
+            Err(EventError::UnexpectedCharacter('\'', position(4, 1, 5)))
+        );
+    }
+}
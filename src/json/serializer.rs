@@ -0,0 +1,104 @@
+use std::fmt::{self, Write};
+
+use super::syntactic_analyzer::{JsonPair, JsonValue};
+
+// Compact serialization, without any insignificant whitespace
+pub fn to_string(value: &JsonValue) -> String {
+    value.to_string()
+}
+
+pub fn write_value<W: Write>(out: &mut W, value: &JsonValue) -> fmt::Result {
+    match value {
+        JsonValue::String(string) => write_string(out, string),
+        JsonValue::Number(number) => write_number(out, *number),
+        JsonValue::Boolean(boolean) => write!(out, "{boolean}"),
+        JsonValue::Null => out.write_str("null"),
+        JsonValue::JsonArray(elements) => {
+            out.write_char('[')?;
+            for (index, element) in elements.iter().enumerate() {
+                if index > 0 {
+                    out.write_char(',')?;
+                }
+                write_value(out, element)?;
+            }
+            out.write_char(']')
+        }
+        JsonValue::JsonObject(members) => {
+            out.write_char('{')?;
+            for (index, JsonPair(key, member)) in members.iter().enumerate() {
+                if index > 0 {
+                    out.write_char(',')?;
+                }
+                write_string(out, key)?;
+                out.write_char(':')?;
+                write_value(out, member)?;
+            }
+            out.write_char('}')
+        }
+    }
+}
+
+// JSON has no representation for NaN and the infinities, they are written as null
+pub fn write_number<W: Write>(out: &mut W, number: f32) -> fmt::Result {
+    if number.is_finite() {
+        write!(out, "{number}")
+    } else {
+        out.write_str("null")
+    }
+}
+
+pub fn write_string<W: Write>(out: &mut W, string: &str) -> fmt::Result {
+    out.write_char('"')?;
+    for ch in string.chars() {
+        match ch {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            '\u{8}' => out.write_str("\\b")?,
+            '\u{c}' => out.write_str("\\f")?,
+            ch if (ch as u32) < 0x20 => write!(out, "\\u{:04x}", ch as u32)?,
+            ch => out.write_char(ch)?,
+        }
+    }
+    out.write_char('"')
+}
+
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_value(f, self)
+    }
+}
+
+#[cfg(test)]
+pub mod serializer_tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    pub fn test_serialize_scalars() {
+        assert_eq!(to_string(&JsonValue::Null), "null");
+        assert_eq!(to_string(&JsonValue::Boolean(false)), "false");
+        assert_eq!(to_string(&JsonValue::Number(42.0)), "42");
+        assert_eq!(to_string(&JsonValue::Number(-1.5)), "-1.5");
+        assert_eq!(to_string(&JsonValue::Number(f32::NAN)), "null");
+        assert_eq!(
+            to_string(&JsonValue::String("a \"quoted\"\n\u{1}".to_string())),
+            r#""a \"quoted\"\n\u0001""#
+        );
+    }
+
+    #[test]
+    pub fn test_serialize_containers() {
+        let value = JsonValue::JsonObject(vec![
+            JsonPair(
+                "list".to_string(),
+                JsonValue::JsonArray(vec![JsonValue::Number(1.0), JsonValue::Null]),
+            ),
+            JsonPair("empty".to_string(), JsonValue::JsonObject(vec![])),
+        ]);
+
+        assert_eq!(value.to_string(), r#"{"list":[1,null],"empty":{}}"#);
+    }
+}
@@ -0,0 +1,631 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use super::binary::{as_i64, Cursor, UnexpectedEnd};
+use super::syntactic_analyzer::{JsonPair, JsonValue};
+
+pub use super::binary::MAX_DEPTH;
+
+// Every Smile document starts with ":)\n" and a byte of version and flags
+pub const HEADER: &[u8; 3] = b":)\n";
+
+// Byte offsets point at the start of the offending token
+#[derive(Debug, Clone, Error, PartialEq)]
+pub enum SmileError {
+    #[error("The data does not start with the Smile header")]
+    MissingHeader,
+
+    #[error("Smile version {0} is not supported")]
+    UnsupportedVersion(u8),
+
+    #[error("Unexpected end of the Smile data at byte {0}")]
+    UnexpectedEnd(usize),
+
+    #[error("Invalid token 0x{0:02x} at byte {1}")]
+    InvalidToken(u8, usize),
+
+    #[error("No shared string at index {0} (byte {1})")]
+    InvalidReference(usize, usize),
+
+    #[error("Invalid number at byte {0}")]
+    InvalidNumber(usize),
+
+    #[error("Invalid UTF-8 in the string at byte {0}")]
+    InvalidUtf8(usize),
+
+    #[error("Binary data has no json representation (byte {0})")]
+    Binary(usize),
+
+    #[error("Values are nested deeper than {0} levels")]
+    TooDeep(usize),
+
+    #[error("{0} bytes follow the Smile document")]
+    TrailingBytes(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SmileOptions {
+    // a key seen before is written as a back-reference to its first occurrence
+    pub shared_names: bool,
+}
+
+impl Default for SmileOptions {
+    fn default() -> Self {
+        SmileOptions { shared_names: true }
+    }
+}
+
+const SHARED_NAMES: u8 = 0x01;
+const SHARED_VALUES: u8 = 0x02;
+
+// Both tables start over once they hold this many strings
+const MAX_SHARED: usize = 1024;
+const MAX_SHARED_VALUE_BYTES: usize = 64;
+
+const EMPTY_STRING: u8 = 0x20;
+const NULL: u8 = 0x21;
+const FALSE: u8 = 0x22;
+const TRUE: u8 = 0x23;
+const INT32: u8 = 0x24;
+const INT64: u8 = 0x25;
+const BIG_INTEGER: u8 = 0x26;
+const FLOAT32: u8 = 0x28;
+const FLOAT64: u8 = 0x29;
+const BIG_DECIMAL: u8 = 0x2a;
+const LONG_ASCII: u8 = 0xe0;
+const LONG_UNICODE: u8 = 0xe4;
+const BINARY_7BIT: u8 = 0xe8;
+const ARRAY_START: u8 = 0xf8;
+const ARRAY_END: u8 = 0xf9;
+const OBJECT_START: u8 = 0xfa;
+const OBJECT_END: u8 = 0xfb;
+const STRING_END: u8 = 0xfc;
+const RAW_BINARY: u8 = 0xfd;
+const CONTENT_END: u8 = 0xff;
+
+const LONG_NAME: u8 = 0x34;
+
+// Encodes a value as Smile, the binary json of Jackson. Integral numbers become
+// integers, the other numbers 32 bit floats. String values are never shared.
+pub fn to_smile(value: &JsonValue, options: SmileOptions) -> Vec<u8> {
+    let mut encoder = Encoder {
+        out: HEADER.to_vec(),
+        names: options.shared_names.then(HashMap::new),
+    };
+    encoder.out.push(if options.shared_names {
+        SHARED_NAMES
+    } else {
+        0
+    });
+    encoder.value(value);
+    encoder.out
+}
+
+struct Encoder {
+    out: Vec<u8>,
+    names: Option<HashMap<String, usize>>,
+}
+
+impl Encoder {
+    fn value(&mut self, value: &JsonValue) {
+        match value {
+            JsonValue::Null => self.out.push(NULL),
+            JsonValue::Boolean(false) => self.out.push(FALSE),
+            JsonValue::Boolean(true) => self.out.push(TRUE),
+            JsonValue::Number(number) => self.number(*number),
+            JsonValue::String(string) => self.string(string),
+            JsonValue::JsonArray(elements) => {
+                self.out.push(ARRAY_START);
+                for element in elements {
+                    self.value(element);
+                }
+                self.out.push(ARRAY_END);
+            }
+            JsonValue::JsonObject(members) => {
+                self.out.push(OBJECT_START);
+                for JsonPair(key, member) in members {
+                    self.name(key);
+                    self.value(member);
+                }
+                self.out.push(OBJECT_END);
+            }
+        }
+    }
+
+    fn number(&mut self, number: f32) {
+        let Some(integer) = as_i64(number) else {
+            let bits = number.to_bits();
+            self.out.push(FLOAT32);
+            for shift in [28, 21, 14, 7, 0] {
+                self.out.push((bits >> shift) as u8 & 0x7f);
+            }
+            return;
+        };
+
+        match i32::try_from(integer) {
+            Ok(small @ -16..=15) => self.out.push(0xc0 | zigzag(small as i64) as u8),
+            Ok(_) => {
+                self.out.push(INT32);
+                vint(&mut self.out, zigzag(integer));
+            }
+            Err(_) => {
+                self.out.push(INT64);
+                vint(&mut self.out, zigzag(integer));
+            }
+        }
+    }
+
+    fn string(&mut self, string: &str) {
+        let bytes = string.as_bytes();
+        let length = bytes.len();
+        match (string.is_ascii(), length) {
+            (_, 0) => return self.out.push(EMPTY_STRING),
+            (true, 1..=32) => self.out.push(0x40 + (length - 1) as u8),
+            (true, 33..=64) => self.out.push(0x60 + (length - 33) as u8),
+            (false, 2..=33) => self.out.push(0x80 + (length - 2) as u8),
+            (false, 34..=65) => self.out.push(0xa0 + (length - 34) as u8),
+            (ascii, _) => {
+                self.out.push(if ascii { LONG_ASCII } else { LONG_UNICODE });
+                self.out.extend_from_slice(bytes);
+                return self.out.push(STRING_END);
+            }
+        }
+        self.out.extend_from_slice(bytes);
+    }
+
+    fn name(&mut self, name: &str) {
+        if name.is_empty() {
+            return self.out.push(EMPTY_STRING);
+        }
+        if let Some(&index) = self.names.as_ref().and_then(|names| names.get(name)) {
+            if index < 64 {
+                self.out.push(0x40 | index as u8);
+            } else {
+                self.out
+                    .extend_from_slice(&[0x30 | (index >> 8) as u8, index as u8]);
+            }
+            return;
+        }
+
+        let bytes = name.as_bytes();
+        let length = bytes.len();
+        let short = match (name.is_ascii(), length) {
+            (true, 1..=64) => Some(0x80 + (length - 1) as u8),
+            (false, 2..=57) => Some(0xc0 + (length - 2) as u8),
+            _ => None,
+        };
+        self.out.push(short.unwrap_or(LONG_NAME));
+        self.out.extend_from_slice(bytes);
+        if short.is_none() {
+            self.out.push(STRING_END);
+        }
+
+        if let Some(names) = &mut self.names {
+            if names.len() == MAX_SHARED {
+                names.clear();
+            }
+            let index = names.len();
+            names.insert(name.to_string(), index);
+        }
+    }
+}
+
+fn zigzag(integer: i64) -> u64 {
+    ((integer << 1) ^ (integer >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+// Seven bits in every byte but the last, which holds six and has its top bit set
+fn vint(out: &mut Vec<u8>, value: u64) {
+    let mut groups = vec![0x80 | (value & 0x3f) as u8];
+    let mut rest = value >> 6;
+    while rest > 0 {
+        groups.push((rest & 0x7f) as u8);
+        rest >>= 7;
+    }
+    out.extend(groups.iter().rev());
+}
+
+// Decodes a Smile document, with or without shared names and shared string values
+pub fn from_smile(bytes: &[u8]) -> Result<JsonValue, SmileError> {
+    if !bytes.starts_with(HEADER) {
+        return Err(SmileError::MissingHeader);
+    }
+    let flags = *bytes.get(3).ok_or(SmileError::UnexpectedEnd(3))?;
+    if flags >> 4 != 0 {
+        return Err(SmileError::UnsupportedVersion(flags >> 4));
+    }
+
+    let mut decoder = Decoder {
+        cursor: Cursor::new(bytes, 4),
+        names: (flags & SHARED_NAMES != 0).then(Vec::new),
+        values: (flags & SHARED_VALUES != 0).then(Vec::new),
+    };
+    let value = decoder.value(0)?;
+    if decoder.cursor.peek() == Ok(CONTENT_END) {
+        decoder.cursor.offset += 1;
+    }
+    match decoder.cursor.remaining() {
+        0 => Ok(value),
+        trailing => Err(SmileError::TrailingBytes(trailing)),
+    }
+}
+
+struct Decoder<'a> {
+    cursor: Cursor<'a>,
+    names: Option<Vec<String>>,
+    values: Option<Vec<String>>,
+}
+
+fn share(table: &mut Option<Vec<String>>, string: &str) {
+    if let Some(table) = table {
+        if table.len() == MAX_SHARED {
+            table.clear();
+        }
+        table.push(string.to_string());
+    }
+}
+
+fn shared(table: &Option<Vec<String>>, index: usize, start: usize) -> Result<String, SmileError> {
+    table
+        .as_ref()
+        .and_then(|table| table.get(index))
+        .cloned()
+        .ok_or(SmileError::InvalidReference(index, start))
+}
+
+impl From<UnexpectedEnd> for SmileError {
+    fn from(UnexpectedEnd(offset): UnexpectedEnd) -> Self {
+        SmileError::UnexpectedEnd(offset)
+    }
+}
+
+impl Decoder<'_> {
+    fn text(&mut self, length: usize, start: usize) -> Result<String, SmileError> {
+        let bytes = self.cursor.take(length)?;
+        std::str::from_utf8(bytes)
+            .map(str::to_string)
+            .map_err(|_| SmileError::InvalidUtf8(start))
+    }
+
+    // A string running to the end-of-string marker
+    fn long_text(&mut self, start: usize) -> Result<String, SmileError> {
+        let rest = &self.cursor.bytes[self.cursor.offset..];
+        let length = rest
+            .iter()
+            .position(|byte| *byte == STRING_END)
+            .ok_or(SmileError::UnexpectedEnd(self.cursor.bytes.len()))?;
+        let text = self.text(length, start)?;
+        self.cursor.offset += 1;
+        Ok(text)
+    }
+
+    fn vint(&mut self) -> Result<u64, SmileError> {
+        let start = self.cursor.offset;
+        let mut value = 0u64;
+        loop {
+            let byte = self.cursor.byte()?;
+            if self.cursor.offset - start > 10 {
+                return Err(SmileError::InvalidNumber(start));
+            }
+            if byte & 0x80 != 0 {
+                return Ok(value << 6 | (byte & 0x3f) as u64);
+            }
+            value = value << 7 | byte as u64;
+        }
+    }
+
+    // Big endian bits spread over bytes of seven
+    fn seven_bit_bits(&mut self, count: usize) -> Result<u64, SmileError> {
+        let bytes = self.cursor.take(count)?;
+        Ok(bytes
+            .iter()
+            .fold(0u64, |bits, byte| bits << 7 | (byte & 0x7f) as u64))
+    }
+
+    // Bytes in the 7 bit encoding, eight encoded bytes for every seven and one more
+    // than the remaining bytes at the end
+    fn seven_bit_bytes(&mut self, length: u64, start: usize) -> Result<Vec<u8>, SmileError> {
+        if length > self.cursor.remaining() as u64 {
+            return Err(SmileError::UnexpectedEnd(self.cursor.bytes.len()));
+        }
+        let length = length as usize;
+        let encoded = self.cursor.take(length + length.div_ceil(7))?;
+
+        let mut data = Vec::with_capacity(length);
+        let (mut accumulator, mut bits, mut remaining) = (0u32, 0, 8 * length);
+        for byte in encoded {
+            let width = remaining.min(7);
+            accumulator = accumulator << width | (*byte as u32 & ((1 << width) - 1));
+            bits += width;
+            remaining -= width;
+            if bits >= 8 {
+                bits -= 8;
+                data.push((accumulator >> bits) as u8);
+                accumulator &= (1 << bits) - 1;
+            }
+        }
+        if data.is_empty() {
+            return Err(SmileError::InvalidNumber(start));
+        }
+        Ok(data)
+    }
+
+    // A two's complement big endian integer, as BigInteger.toByteArray writes it
+    fn big_integer(&mut self, start: usize) -> Result<f64, SmileError> {
+        let length = self.vint()?;
+        let bytes = self.seven_bit_bytes(length, start)?;
+        let magnitude = bytes
+            .iter()
+            .fold(0f64, |value, byte| value * 256.0 + *byte as f64);
+        Ok(if bytes[0] >= 0x80 {
+            magnitude - 256f64.powi(bytes.len() as i32)
+        } else {
+            magnitude
+        })
+    }
+
+    fn value(&mut self, depth: usize) -> Result<JsonValue, SmileError> {
+        if depth > MAX_DEPTH {
+            return Err(SmileError::TooDeep(MAX_DEPTH));
+        }
+
+        let start = self.cursor.offset;
+        let token = self.cursor.byte()?;
+        let number = |number: f64| Ok(JsonValue::Number(number as f32));
+        match token {
+            0x01..=0x1f => shared(&self.values, (token - 1) as usize, start).map(JsonValue::String),
+            EMPTY_STRING => Ok(JsonValue::String(String::new())),
+            NULL => Ok(JsonValue::Null),
+            FALSE => Ok(JsonValue::Boolean(false)),
+            TRUE => Ok(JsonValue::Boolean(true)),
+            INT32 | INT64 => number(unzigzag(self.vint()?) as f64),
+            BIG_INTEGER => number(self.big_integer(start)?),
+            FLOAT32 => {
+                let bits = self.seven_bit_bits(5)?;
+                Ok(JsonValue::Number(f32::from_bits(bits as u32)))
+            }
+            FLOAT64 => number(f64::from_bits(self.seven_bit_bits(10)?)),
+            BIG_DECIMAL => {
+                let scale = unzigzag(self.vint()?);
+                let unscaled = self.big_integer(start)?;
+                let exponent = i32::try_from(scale)
+                    .ok()
+                    .and_then(i32::checked_neg)
+                    .ok_or(SmileError::InvalidNumber(start))?;
+                number(unscaled * 10f64.powi(exponent))
+            }
+            0x40..=0xbf => {
+                let length = match token {
+                    0x40..=0x5f => (token & 0x1f) as usize + 1,
+                    0x60..=0x7f => (token & 0x1f) as usize + 33,
+                    0x80..=0x9f => (token & 0x1f) as usize + 2,
+                    _ => (token & 0x1f) as usize + 34,
+                };
+                let text = self.text(length, start)?;
+                if length <= MAX_SHARED_VALUE_BYTES {
+                    share(&mut self.values, &text);
+                }
+                Ok(JsonValue::String(text))
+            }
+            0xc0..=0xdf => number(unzigzag((token & 0x1f) as u64) as f64),
+            LONG_ASCII | LONG_UNICODE => self.long_text(start).map(JsonValue::String),
+            0xec..=0xef => {
+                let index = ((token & 0x03) as usize) << 8 | self.cursor.byte()? as usize;
+                shared(&self.values, index, start).map(JsonValue::String)
+            }
+            BINARY_7BIT | RAW_BINARY => Err(SmileError::Binary(start)),
+            ARRAY_START => {
+                let mut elements = Vec::new();
+                while self.cursor.peek()? != ARRAY_END {
+                    elements.push(self.value(depth + 1)?);
+                }
+                self.cursor.offset += 1;
+                Ok(JsonValue::JsonArray(elements))
+            }
+            OBJECT_START => {
+                let mut members = Vec::new();
+                while self.cursor.peek()? != OBJECT_END {
+                    let key = self.name()?;
+                    members.push(JsonPair(key, self.value(depth + 1)?));
+                }
+                self.cursor.offset += 1;
+                Ok(JsonValue::JsonObject(members))
+            }
+            _ => Err(SmileError::InvalidToken(token, start)),
+        }
+    }
+
+    fn name(&mut self) -> Result<String, SmileError> {
+        let start = self.cursor.offset;
+        let token = self.cursor.byte()?;
+        let name = match token {
+            EMPTY_STRING => return Ok(String::new()),
+            0x30..=0x33 => {
+                let index = ((token & 0x03) as usize) << 8 | self.cursor.byte()? as usize;
+                return shared(&self.names, index, start);
+            }
+            0x40..=0x7f => return shared(&self.names, (token & 0x3f) as usize, start),
+            LONG_NAME => self.long_text(start)?,
+            0x80..=0xbf => self.text((token & 0x3f) as usize + 1, start)?,
+            0xc0..=0xf7 => self.text((token & 0x3f) as usize + 2, start)?,
+            _ => return Err(SmileError::InvalidToken(token, start)),
+        };
+        share(&mut self.names, &name);
+        Ok(name)
+    }
+}
+
+#[cfg(test)]
+pub mod smile_tests {
+    use super::*;
+    use crate::json::syntactic_analyzer::parse_str;
+    use pretty_assertions::assert_eq;
+
+    fn json(text: &str) -> JsonValue {
+        parse_str(text).unwrap()
+    }
+
+    fn smile(flags: u8, content: &[u8]) -> Vec<u8> {
+        let mut bytes = HEADER.to_vec();
+        bytes.push(flags);
+        bytes.extend_from_slice(content);
+        bytes
+    }
+
+    #[test]
+    pub fn test_smile() {
+        let examples: [(&str, &[u8]); 10] = [
+            ("null", b"\x21"),
+            ("[true, false]", b"\xf8\x23\x22\xf9"),
+            ("1", b"\xc2"),
+            ("-16", b"\xdf"),
+            ("100", b"\x24\x03\x88"),
+            ("4294967296", b"\x25\x40\x00\x00\x00\x80"),
+            ("1.5", b"\x28\x03\x7e\x00\x00\x00"),
+            (r#"["", "abc", "é"]"#, b"\xf8\x20\x42abc\x80\xc3\xa9\xf9"),
+            (r#"{"a": 1}"#, b"\xfa\x80a\xc2\xfb"),
+            // the second "name" refers back to the first one
+            (
+                r#"[{"name": 1}, {"name": 2, "": 3}]"#,
+                b"\xf8\xfa\x83name\xc2\xfb\xfa\x40\xc4\x20\xc6\xfb\xf9",
+            ),
+        ];
+
+        for (text, content) in examples {
+            let bytes = smile(SHARED_NAMES, content);
+            assert_eq!(
+                to_smile(&json(text), SmileOptions::default()),
+                bytes,
+                "{text}"
+            );
+            assert_eq!(from_smile(&bytes).unwrap(), json(text));
+        }
+
+        let unshared = SmileOptions {
+            shared_names: false,
+        };
+        assert_eq!(
+            to_smile(&json(r#"[{"a": 1}, {"a": 2}]"#), unshared),
+            smile(0, b"\xf8\xfa\x80a\xc2\xfb\xfa\x80a\xc4\xfb\xf9")
+        );
+
+        let long = "x".repeat(70);
+        let mut content = vec![LONG_ASCII];
+        content.extend_from_slice(long.as_bytes());
+        content.push(STRING_END);
+        assert_eq!(
+            to_smile(&JsonValue::String(long.clone()), SmileOptions::default()),
+            smile(SHARED_NAMES, &content)
+        );
+        assert_eq!(
+            from_smile(&smile(0, &content)).unwrap(),
+            JsonValue::String(long)
+        );
+    }
+
+    #[test]
+    pub fn test_shared_names_table() {
+        // more names than the table holds, it starts over after 1024 of them
+        let objects: Vec<JsonValue> = (0..1100)
+            .map(|index| {
+                JsonValue::JsonObject(vec![
+                    JsonPair(format!("key {index}"), JsonValue::Number(index as f32)),
+                    JsonPair("id".to_string(), JsonValue::Null),
+                    JsonPair(format!("key {}", index / 2), JsonValue::Null),
+                ])
+            })
+            .collect();
+        let value = JsonValue::JsonArray(objects);
+
+        let shared = to_smile(&value, SmileOptions::default());
+        let unshared = to_smile(
+            &value,
+            SmileOptions {
+                shared_names: false,
+            },
+        );
+        assert!(shared.len() < unshared.len());
+        assert_eq!(from_smile(&shared).unwrap(), value);
+        assert_eq!(from_smile(&unshared).unwrap(), value);
+
+        let long_name = "n".repeat(100);
+        let value = JsonValue::JsonArray(vec![
+            JsonValue::JsonObject(vec![JsonPair(long_name.clone(), JsonValue::Null)]),
+            JsonValue::JsonObject(vec![JsonPair(long_name, JsonValue::Null)]),
+        ]);
+        assert_eq!(
+            from_smile(&to_smile(&value, SmileOptions::default())).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    pub fn test_decode() {
+        let examples: [(u8, &[u8], &str); 6] = [
+            (0, b"\x29\x00\x3f\x7c\x00\x00\x00\x00\x00\x00\x00", "1.5"),
+            (0, b"\x26\x82\x00\x4b\x00", "300"),
+            (0, b"\x2a\x84\x81\x3e\x01", "1.25"),
+            (0, b"\x21\xff", "null"),
+            // shared string values
+            (SHARED_VALUES, b"\xf8\x42abc\x01\xf9", r#"["abc", "abc"]"#),
+            (
+                SHARED_NAMES,
+                b"\xfa\x34long\xfc\x21\x40\x22\xfb",
+                r#"{"long": null, "long": false}"#,
+            ),
+        ];
+        for (flags, content, text) in examples {
+            assert_eq!(
+                from_smile(&smile(flags, content)).unwrap(),
+                json(text),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_smile_errors() {
+        assert_eq!(from_smile(b"{}"), Err(SmileError::MissingHeader));
+        assert_eq!(from_smile(b":)\n"), Err(SmileError::UnexpectedEnd(3)));
+        assert_eq!(
+            from_smile(b":)\n\x10\x21"),
+            Err(SmileError::UnsupportedVersion(1))
+        );
+
+        let errors: [(u8, &[u8], SmileError); 9] = [
+            // a scale of i32::MIN has no negation
+            (
+                0,
+                b"\x2a\x1f\x7f\x7f\x7f\xbf\x81\x01\x00",
+                SmileError::InvalidNumber(4),
+            ),
+            (0, b"\x2c", SmileError::InvalidToken(0x2c, 4)),
+            (0, b"\x01", SmileError::InvalidReference(0, 4)),
+            (
+                SHARED_NAMES,
+                b"\xfa\x41\x21\xfb",
+                SmileError::InvalidReference(1, 5),
+            ),
+            (0, b"\xe0abc", SmileError::UnexpectedEnd(8)),
+            (0, b"\x43ab", SmileError::UnexpectedEnd(7)),
+            (0, b"\xe8\x81\x00\x00", SmileError::Binary(4)),
+            (0, b"\xf8\x21", SmileError::UnexpectedEnd(6)),
+            (0, b"\x21\x21", SmileError::TrailingBytes(1)),
+        ];
+        for (flags, content, error) in errors {
+            assert_eq!(
+                from_smile(&smile(flags, content)),
+                Err(error),
+                "{content:?}"
+            );
+        }
+
+        let nested = smile(0, &vec![ARRAY_START; MAX_DEPTH + 2]);
+        assert_eq!(from_smile(&nested), Err(SmileError::TooDeep(MAX_DEPTH)));
+    }
+}
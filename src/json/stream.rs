@@ -0,0 +1,258 @@
+use std::io::{BufRead, BufReader, Read};
+
+use super::byte_lexer::ByteLexer;
+use super::events::{EventError, EventReader, LexemeSource, Position};
+use super::scanner::Lexeme;
+
+pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
+// Lexes a BufRead one buffer at a time, so memory use is bounded by the buffer plus
+// the lexeme being read, whatever the size of the document.
+#[derive(Debug)]
+pub struct StreamLexer<R> {
+    reader: R,
+    lexer: ByteLexer,
+    finished: bool,
+}
+
+impl<R: BufRead> StreamLexer<R> {
+    pub fn new(reader: R) -> StreamLexer<R> {
+        StreamLexer::with_lexer(reader, ByteLexer::new())
+    }
+
+    // For a lexer set up differently, e.g. ByteLexer::with_comments
+    pub fn with_lexer(reader: R, lexer: ByteLexer) -> StreamLexer<R> {
+        StreamLexer {
+            reader,
+            lexer,
+            finished: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn lexer_mut(&mut self) -> &mut ByteLexer {
+        &mut self.lexer
+    }
+}
+
+impl<R: Read> StreamLexer<BufReader<R>> {
+    pub fn from_reader(reader: R) -> StreamLexer<BufReader<R>> {
+        StreamLexer::with_capacity(DEFAULT_BUFFER_SIZE, reader)
+    }
+
+    pub fn with_capacity(capacity: usize, reader: R) -> StreamLexer<BufReader<R>> {
+        StreamLexer::new(BufReader::with_capacity(capacity, reader))
+    }
+}
+
+impl<R: BufRead> LexemeSource<'static> for StreamLexer<R> {
+    fn next_lexeme(&mut self) -> Result<Option<(Lexeme<'static>, Position)>, EventError> {
+        while !self.finished {
+            let buffer = self
+                .reader
+                .fill_buf()
+                .map_err(|error| EventError::Io(error.to_string()))?;
+
+            if buffer.is_empty() {
+                self.finished = true;
+                return self.lexer.finish();
+            }
+
+            let (consumed, result) = self.lexer.next_lexeme(buffer);
+            self.reader.consume(consumed);
+            if let Some(lexeme) = result? {
+                return Ok(Some(lexeme));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn position(&self) -> Position {
+        self.lexer.position()
+    }
+}
+
+pub type StreamEventReader<R> = EventReader<'static, StreamLexer<R>>;
+
+// Pull parser over anything implementing Read, every event owns its strings
+pub fn event_reader<R: Read>(reader: R) -> StreamEventReader<BufReader<R>> {
+    EventReader::from_source(StreamLexer::from_reader(reader))
+}
+
+#[cfg(test)]
+pub mod stream_tests {
+    use super::*;
+    use crate::json::events::Event;
+    use crate::json::syntactic_analyzer::{parse_reader, parse_str};
+    use pretty_assertions::assert_eq;
+    use std::io;
+
+    fn stream_events(input: &str, capacity: usize) -> Result<Vec<Event<'static>>, EventError> {
+        EventReader::from_source(StreamLexer::with_capacity(capacity, input.as_bytes()))
+            .map(|result| result.map(|(event, _)| event))
+            .collect()
+    }
+
+    fn str_events(input: &str) -> Result<Vec<Event<'static>>, EventError> {
+        EventReader::new(input)
+            .map(|result| result.map(|(event, _)| event.into_owned()))
+            .collect()
+    }
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk on fire"))
+        }
+    }
+
+    #[test]
+    pub fn test_lexemes_across_buffer_boundaries() {
+        let long_string = "x".repeat(100);
+        let input = format!(
+            r#"{{"long": "{long_string}", "utf8": "é 😀 ✓", "escapes": "a\"\\é😀",
+                "numbers": [0, -12.5e+3, 1E2, 123456], "literals": [true, false, null]}}"#
+        );
+
+        for capacity in [1, 2, 3, 7, 64] {
+            assert_eq!(stream_events(&input, capacity), str_events(&input));
+        }
+        assert_eq!(
+            parse_reader(input.as_bytes()).unwrap(),
+            parse_str(&input).unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_stream_errors_match_str_errors() {
+        let inputs = [
+            "",
+            "{",
+            "[01]",
+            "[1.]",
+            "[tru]",
+            "['a']",
+            r#"["\x"]"#,
+            "[\"\t\"]",
+            r#"["\ud800"]"#,
+            r#"["\ud800A"]"#,
+            "{} {}",
+            "[1, 2",
+            "\"open",
+            "12",
+            "-",
+            "é",
+            "[1, ✓]",
+            "{\"a\":\n 😀}",
+        ];
+
+        for input in inputs {
+            for capacity in [1, 2, 4] {
+                assert_eq!(stream_events(input, capacity), str_events(input), "{input}");
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_stream_positions() {
+        let input = "{\n  \"key\": [\n    false\n  ]\n}";
+        let positions: Vec<Position> =
+            EventReader::from_source(StreamLexer::with_capacity(2, input.as_bytes()))
+                .map(|result| result.unwrap().1)
+                .collect();
+        let expected: Vec<Position> = EventReader::new(input)
+            .map(|result| result.unwrap().1)
+            .collect();
+
+        assert_eq!(positions, expected);
+    }
+
+    #[test]
+    pub fn test_comments_across_buffer_boundaries() {
+        let input = "// first\r\n[1, /* two\n*/ 2 /**/]// last";
+        let expected: Vec<Event> = str_events("[1, 2]").unwrap();
+
+        for capacity in [1, 2, 3, 64] {
+            let lexer = ByteLexer::with_comments().keep_comments();
+            let source = StreamLexer::with_lexer(
+                BufReader::with_capacity(capacity, input.as_bytes()),
+                lexer,
+            );
+            let mut reader = EventReader::from_source(source);
+            let mut events = Vec::new();
+            while let Some((event, _)) = reader.next_event().unwrap() {
+                events.push(event);
+            }
+
+            assert_eq!(events, expected);
+            assert_eq!(
+                reader.source_mut().lexer_mut().take_comments(),
+                vec!["// first", "/* two\n*/", "/**/", "// last"]
+            );
+        }
+
+        let lexer = || ByteLexer::with_comments();
+        for (input, expected) in [
+            (
+                "[1] /",
+                EventError::UnexpectedCharacter(
+                    '/',
+                    Position {
+                        offset: 4,
+                        line: 1,
+                        column: 5,
+                    },
+                ),
+            ),
+            (
+                "[1 /x]",
+                EventError::UnexpectedCharacter(
+                    '/',
+                    Position {
+                        offset: 3,
+                        line: 1,
+                        column: 4,
+                    },
+                ),
+            ),
+            (
+                "[1] /* open *",
+                EventError::UnterminatedComment(Position {
+                    offset: 4,
+                    line: 1,
+                    column: 5,
+                }),
+            ),
+        ] {
+            let source = StreamLexer::with_lexer(input.as_bytes(), lexer());
+            let result: Result<Vec<_>, _> = EventReader::from_source(source).collect();
+            assert_eq!(result.unwrap_err(), expected, "{input}");
+        }
+    }
+
+    #[test]
+    pub fn test_invalid_utf8_and_io_errors() {
+        let bytes: &[u8] = b"[\"\xff\"]";
+        assert!(matches!(
+            parse_reader(bytes),
+            Err(EventError::InvalidUtf8(_))
+        ));
+
+        // a multi-byte character cut in half by the end of the input
+        let bytes: &[u8] = b"\"\xc3";
+        assert!(matches!(
+            parse_reader(bytes),
+            Err(EventError::UnexpectedEnd(_))
+        ));
+
+        assert_eq!(
+            parse_reader(FailingReader),
+            Err(EventError::Io("disk on fire".to_string()))
+        );
+    }
+}
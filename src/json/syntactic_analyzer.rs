@@ -1,11 +1,20 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::io::Read;
+
 use thiserror::Error;
 
+use super::dialect::Dialect;
+use super::events::{Event, EventError, EventReader, Grammar, LexemeSource, Position};
+use super::hjson;
 use super::lexer::*;
+use super::scanner::Lexeme;
+use super::stream;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct JsonPair(pub String, pub JsonValue);
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum JsonValue {
     String(String),
     Number(f32),
@@ -15,6 +24,31 @@ pub enum JsonValue {
     Null,
 }
 
+impl JsonValue {
+    // Structural equality where object members are compared regardless of their order
+    pub fn equivalent(&self, other: &JsonValue) -> bool {
+        match (self, other) {
+            (JsonValue::JsonArray(left), JsonValue::JsonArray(right)) => {
+                left.len() == right.len()
+                    && left
+                        .iter()
+                        .zip(right.iter())
+                        .all(|(left, right)| left.equivalent(right))
+            }
+            (JsonValue::JsonObject(left), JsonValue::JsonObject(right)) => {
+                left.len() == right.len()
+                    && left.iter().all(|JsonPair(name, value)| {
+                        right
+                            .iter()
+                            .find(|pair| pair.0 == *name)
+                            .is_some_and(|JsonPair(_, other)| value.equivalent(other))
+                    })
+            }
+            (left, right) => left == right,
+        }
+    }
+}
+
 #[derive(Error, Debug, PartialEq)]
 pub enum ParseError {
     #[error("Non matching parantheses")]
@@ -34,6 +68,12 @@ pub enum ParseError {
 
     #[error("Empty json is invalid")]
     EmptyJson,
+
+    #[error("Could not read the json file: {0}")]
+    Io(String),
+
+    #[error(transparent)]
+    Syntax(#[from] EventError),
 }
 
 fn valid_parantheses(token_stream: &TokenStream) -> bool {
@@ -75,7 +115,7 @@ fn valid_parantheses(token_stream: &TokenStream) -> bool {
     true
 }
 
-pub fn parse(mut input_stream: TokenStream) -> Result<JsonValue, ParseError> {
+pub fn parse(input_stream: TokenStream) -> Result<JsonValue, ParseError> {
     if !valid_parantheses(&input_stream) {
         return Err(ParseError::InvalidParantheses);
     }
@@ -90,83 +130,292 @@ pub fn parse(mut input_stream: TokenStream) -> Result<JsonValue, ParseError> {
         return Err(ParseError::NotValidJsonObjectOrArray);
     }
 
-    input_stream.retain(|x| *x != '\n'.into());
+    parse_value(&input_stream)
+}
 
-    parse_object(&mut input_stream[1..])
+// Parses the tokens following the '{' of an object, up to its '}'
+pub fn parse_object(token_stream: &mut [Token]) -> Result<JsonValue, ParseError> {
+    Ok(parse_events(token_reader(token_stream, true))?)
 }
 
-pub fn parse_object(mut token_stream: &mut [Token]) -> Result<JsonValue, ParseError> {
-    let members = parse_object_members(&mut token_stream.to_vec())?;
-    Ok(JsonValue::JsonObject(members))
+pub fn parse_object_members(token_stream: &mut [Token]) -> Result<Vec<JsonPair>, ParseError> {
+    match parse_object(token_stream)? {
+        JsonValue::JsonObject(members) => Ok(members),
+        _ => unreachable!("the tokens follow a '{{'"),
+    }
 }
 
-pub fn parse_object_members(mut token_stream: &mut [Token]) -> Result<Vec<JsonPair>, ParseError> {
-    let mut pair_index = 0;
-    let mut result = Vec::new();
-    while pair_index < token_stream.len() {
-        let pair = parse_pair(&mut token_stream[pair_index..]);
+// Parses the first member of the tokens following a '{' or a ','. What comes after
+// that member is not read.
+pub fn parse_pair(token_stream: &mut [Token]) -> Result<JsonPair, ParseError> {
+    let tokens = match token_stream.first() {
+        Some(token) if *token == ','.into() => &token_stream[1..],
+        _ => &token_stream[..],
+    };
+    let mut reader = token_reader(tokens, true);
+    let mut next_event = || {
+        reader
+            .next_event()?
+            .ok_or(EventError::UnexpectedEnd(reader.position()))
+    };
 
-        match pair {
-            Ok(pair) => {
-                result.push(pair);
-                pair_index += 3;
-            }
-            Err(ParseError::EmptyObject) => {
-                token_stream = &mut token_stream[1..];
-                break;
-            }
-            Err(e) => {
-                return Err(e);
-            }
+    // the '{' of the reader
+    next_event()?;
+    let key = match next_event()? {
+        (Event::Key(key), _) => key.into_owned(),
+        (Event::EndObject, _) => return Err(ParseError::EmptyObject),
+        _ => return Err(ParseError::InvalidPair),
+    };
+
+    let mut builder = ValueBuilder::new();
+    loop {
+        if let Some(value) = builder.push(next_event()?.0) {
+            return Ok(JsonPair(key, value));
         }
     }
+}
 
-    Ok(result)
+// Parses the tokens of one value of any kind, running them through the same grammar
+// as every other reader
+pub fn parse_value(token_stream: &[Token]) -> Result<JsonValue, ParseError> {
+    Ok(parse_events(token_reader(token_stream, false))?)
 }
 
-pub fn parse_pair(mut token_stream: &mut [Token]) -> Result<JsonPair, ParseError> {
-    if token_stream.len() < 3 {
-        if token_stream[0] != '}'.into() {
-            return Err(ParseError::InvalidPair);
-        } else {
-            return Err(ParseError::EmptyObject);
+fn token_reader(tokens: &[Token], open_brace: bool) -> EventReader<'_, TokenSource<'_>> {
+    let source = TokenSource {
+        tokens,
+        index: 0,
+        open_brace,
+    };
+    // the lexer keeps the commas it meets but does not require them
+    EventReader::with_grammar(source, Grammar::with_optional_commas())
+}
+
+// Hands the tokens of the lexer to the grammar as lexemes. Tokens carry no position,
+// the offsets in the errors are token indexes.
+struct TokenSource<'t> {
+    tokens: &'t [Token],
+    index: usize,
+    // a '{' is read before the tokens, which follow it
+    open_brace: bool,
+}
+
+impl<'t> LexemeSource<'t> for TokenSource<'t> {
+    fn next_lexeme(&mut self) -> Result<Option<(Lexeme<'t>, Position)>, EventError> {
+        if std::mem::take(&mut self.open_brace) {
+            return Ok(Some((Lexeme::LeftBrace, self.position())));
+        }
+        loop {
+            let Some(token) = self.tokens.get(self.index) else {
+                return Ok(None);
+            };
+            let position = self.position();
+            let lexeme = match token {
+                Token::JsonString(string) => Lexeme::String(Cow::Borrowed(string)),
+                Token::Number(number) => Lexeme::NumberValue(*number),
+                Token::Boolean(true) => Lexeme::True,
+                Token::Boolean(false) => Lexeme::False,
+                Token::Null => Lexeme::Null,
+                Token::JsonCharacter(Character(ch)) | Token::Other(ch) => match ch {
+                    '{' => Lexeme::LeftBrace,
+                    '}' => Lexeme::RightBrace,
+                    '[' => Lexeme::LeftBracket,
+                    ']' => Lexeme::RightBracket,
+                    ':' => Lexeme::Colon,
+                    ',' => Lexeme::Comma,
+                    '\n' => {
+                        self.index += 1;
+                        continue;
+                    }
+                    ch => return Err(EventError::UnexpectedCharacter(*ch, position)),
+                },
+            };
+            self.index += 1;
+            return Ok(Some((lexeme, position)));
         }
     }
 
-    if token_stream[0] == ','.into() {
-        token_stream = &mut token_stream[1..];
+    fn position(&self) -> Position {
+        Position {
+            offset: self.index,
+            line: 1,
+            column: self.index + 1,
+        }
     }
+}
 
-    let Token::JsonString(name) = token_stream[0].clone() else {
-        return Err(ParseError::InvalidPair);
-    };
+// A tree of values that TreeBuilder can assemble
+pub trait TreeValue: Sized + fmt::Debug {
+    type Key: fmt::Debug;
+    type Member: fmt::Debug;
+
+    fn number(number: f32) -> Self;
+    fn boolean(boolean: bool) -> Self;
+    fn null() -> Self;
+    fn member(key: Self::Key, value: Self) -> Self::Member;
+    fn object(members: Vec<Self::Member>) -> Self;
+    fn array(elements: Vec<Self>) -> Self;
+}
+
+// The strings of the tree, out of events borrowing from the input for 'a
+pub trait FromEvents<'a>: TreeValue {
+    fn key(key: Cow<'a, str>) -> Self::Key;
+    fn string(string: Cow<'a, str>) -> Self;
+}
+
+impl TreeValue for JsonValue {
+    type Key = String;
+    type Member = JsonPair;
 
-    if token_stream[1] != ':'.into() {
-        return Err(ParseError::InvalidPair);
+    fn number(number: f32) -> Self {
+        JsonValue::Number(number)
     }
 
-    let value = parse_value(&token_stream[2..])?;
-    let result: JsonPair = JsonPair(name, value);
-    Ok(result)
+    fn boolean(boolean: bool) -> Self {
+        JsonValue::Boolean(boolean)
+    }
+
+    fn null() -> Self {
+        JsonValue::Null
+    }
+
+    fn member(key: String, value: Self) -> JsonPair {
+        JsonPair(key, value)
+    }
+
+    fn object(members: Vec<JsonPair>) -> Self {
+        JsonValue::JsonObject(members)
+    }
+
+    fn array(elements: Vec<Self>) -> Self {
+        JsonValue::JsonArray(elements)
+    }
 }
 
-pub fn parse_value(token_stream: &[Token]) -> Result<JsonValue, ParseError> {
-    match &token_stream[0] {
-        Token::JsonString(st) => Ok(JsonValue::String(st.to_owned())),
-        Token::Number(nr) => Ok(JsonValue::Number(nr.to_owned())),
-        Token::Boolean(b) => Ok(JsonValue::Boolean(b.to_owned())),
-        Token::JsonCharacter(_) => Err(ParseError::InvalidValue),
-        Token::Null => Ok(JsonValue::Null),
-        Token::Other(_) => Err(ParseError::InvalidValue),
+impl<'a> FromEvents<'a> for JsonValue {
+    fn key(key: Cow<'a, str>) -> String {
+        key.into_owned()
+    }
+
+    fn string(string: Cow<'a, str>) -> Self {
+        JsonValue::String(string.into_owned())
+    }
+}
+
+#[derive(Debug)]
+enum Frame<V: TreeValue> {
+    Object(Vec<V::Member>, Option<V::Key>),
+    Array(Vec<V>),
+}
+
+// Builds a tree of values out of parser events, one event at a time. The events are
+// expected to be well formed, which every reader in the crate guarantees.
+#[derive(Debug)]
+pub struct TreeBuilder<V: TreeValue> {
+    stack: Vec<Frame<V>>,
+}
+
+pub type ValueBuilder = TreeBuilder<JsonValue>;
+
+impl<V: TreeValue> Default for TreeBuilder<V> {
+    fn default() -> Self {
+        TreeBuilder { stack: Vec::new() }
+    }
+}
+
+impl<V: TreeValue> TreeBuilder<V> {
+    pub fn new() -> TreeBuilder<V> {
+        TreeBuilder::default()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    // Returns the root value once its last event was pushed
+    pub fn push<'a>(&mut self, event: Event<'a>) -> Option<V>
+    where
+        V: FromEvents<'a>,
+    {
+        let value = match event {
+            Event::StartObject => {
+                self.stack.push(Frame::Object(Vec::new(), None));
+                return None;
+            }
+            Event::StartArray => {
+                self.stack.push(Frame::Array(Vec::new()));
+                return None;
+            }
+            Event::Key(key) => {
+                if let Some(Frame::Object(_, pending_key)) = self.stack.last_mut() {
+                    *pending_key = Some(V::key(key));
+                }
+                return None;
+            }
+            Event::EndObject | Event::EndArray => match self.stack.pop()? {
+                Frame::Object(members, _) => V::object(members),
+                Frame::Array(elements) => V::array(elements),
+            },
+            Event::String(string) => V::string(string),
+            Event::Number(number) => V::number(number),
+            Event::Bool(boolean) => V::boolean(boolean),
+            Event::Null => V::null(),
+        };
+
+        match self.stack.last_mut() {
+            None => Some(value),
+            Some(Frame::Object(members, pending_key)) => {
+                let key = pending_key
+                    .take()
+                    .unwrap_or_else(|| V::key(Cow::Borrowed("")));
+                members.push(V::member(key, value));
+                None
+            }
+            Some(Frame::Array(elements)) => {
+                elements.push(value);
+                None
+            }
+        }
+    }
+}
+
+// Parses a complete json text, built on top of the event reader
+pub fn parse_str(input: &str) -> Result<JsonValue, EventError> {
+    parse_events(EventReader::new(input))
+}
+
+pub fn parse_str_with_dialect(input: &str, dialect: Dialect) -> Result<JsonValue, EventError> {
+    if dialect.hjson {
+        return hjson::parse_str(input);
     }
+    parse_events(EventReader::with_dialect(input, dialect))
 }
 
-pub fn parse_array(token_stream: &Vec<Token>, current_index: usize) {
-    todo!("parse array elements");
+// Same as parse_str, reading the input in bounded chunks instead of all at once
+pub fn parse_reader<R: Read>(reader: R) -> Result<JsonValue, EventError> {
+    parse_events(stream::event_reader(reader))
 }
 
-pub fn parse_array_elements(token_stream: &Vec<Token>, current_index: usize) {
-    todo!("Ensure element structure and parse value");
+pub fn parse_events<'a, S: LexemeSource<'a>>(
+    reader: EventReader<'a, S>,
+) -> Result<JsonValue, EventError> {
+    build_events(reader)
+}
+
+// Same as parse_events, for any kind of tree
+pub fn build_events<'a, S: LexemeSource<'a>, V: FromEvents<'a>>(
+    mut reader: EventReader<'a, S>,
+) -> Result<V, EventError> {
+    let mut builder = TreeBuilder::new();
+    let mut result = None;
+
+    while let Some((event, _)) = reader.next_event()? {
+        if let Some(value) = builder.push(event) {
+            result = Some(value);
+        }
+    }
+
+    result.ok_or(EventError::UnexpectedEnd(reader.position()))
 }
 
 #[cfg(test)]
@@ -298,4 +547,126 @@ pub mod syntactic_analyzer_tests {
             ])
         );
     }
+
+    #[test]
+    pub fn test_parse_nested_tokens() {
+        let json = r#"{"key": [1, {"inner": "x"}], "other": true}"#;
+        let res = parse(lex(json).unwrap()).unwrap();
+        assert_eq!(res, parse_str(json).unwrap());
+
+        // the commas the lexer drops are not needed
+        let mut token_stream = TokenStream::default();
+        token_stream.push(constants::LEFT_BRACKET.into());
+        token_stream.push(Token::Number(1.0));
+        token_stream.push(constants::LEFT_BRACE.into());
+        token_stream.push(constants::RIGHT_BRACE.into());
+        token_stream.push(constants::RIGHT_BRACKET.into());
+        let res = parse(token_stream).unwrap();
+        assert_eq!(
+            res,
+            JsonValue::JsonArray(vec![JsonValue::Number(1.0), JsonValue::JsonObject(vec![])])
+        );
+    }
+
+    #[test]
+    pub fn test_parse_token_errors() {
+        let mut token_stream = TokenStream::default();
+        token_stream.push(constants::LEFT_BRACE.into());
+        token_stream.push(Token::JsonString(String::from("key")));
+        token_stream.push(Token::Number(1.0));
+        token_stream.push(constants::RIGHT_BRACE.into());
+        assert!(matches!(
+            parse(token_stream),
+            Err(ParseError::Syntax(EventError::UnexpectedToken(
+                "number",
+                Position { offset: 2, .. }
+            )))
+        ));
+
+        let mut token_stream = TokenStream::default();
+        token_stream.push(constants::LEFT_BRACKET.into());
+        token_stream.push(Token::Other('x'));
+        token_stream.push(constants::RIGHT_BRACKET.into());
+        assert!(matches!(
+            parse(token_stream),
+            Err(ParseError::Syntax(EventError::UnexpectedCharacter('x', _)))
+        ));
+    }
+
+    #[test]
+    pub fn test_parse_object_wrappers() {
+        // the tokens after the '{', as parse used to hand them over
+        let mut token_stream = lex(r#"{"a": 1, "b": "two"} "#).unwrap();
+        let expected = vec![
+            JsonPair("a".to_string(), JsonValue::Number(1.0)),
+            JsonPair("b".to_string(), JsonValue::String("two".to_string())),
+        ];
+        assert_eq!(
+            parse_object(&mut token_stream[1..]).unwrap(),
+            JsonValue::JsonObject(expected.clone())
+        );
+        assert_eq!(
+            parse_object_members(&mut token_stream[1..]).unwrap(),
+            expected
+        );
+
+        // only the first member is read, after an optional comma
+        assert_eq!(parse_pair(&mut token_stream[1..]).unwrap(), expected[0]);
+        assert_eq!(parse_pair(&mut token_stream[4..]).unwrap(), expected[1]);
+        assert_eq!(
+            parse_pair(&mut token_stream[7..]),
+            Err(ParseError::EmptyObject)
+        );
+        assert!(matches!(
+            parse_pair(&mut token_stream[2..]),
+            Err(ParseError::Syntax(EventError::UnexpectedToken("':'", _)))
+        ));
+    }
+
+    #[test]
+    pub fn test_parse_out_of_range_number() {
+        // the lexer's value is kept as it is, infinite or not
+        let json = format!(
+            "{{\"big\": 1{},\n\"small\": -1{}\n}}",
+            "0".repeat(40),
+            "0".repeat(40)
+        );
+        assert_eq!(
+            parse(lex(&json).unwrap()).unwrap(),
+            JsonValue::JsonObject(vec![
+                JsonPair("big".to_string(), JsonValue::Number(f32::INFINITY)),
+                JsonPair("small".to_string(), JsonValue::Number(f32::NEG_INFINITY)),
+            ])
+        );
+    }
+
+    #[test]
+    pub fn test_parse_str_nested() {
+        let res = parse_str(r#"{"key": [1, {"inner": null}], "other": "value"}"#).unwrap();
+        assert_eq!(
+            res,
+            JsonValue::JsonObject(vec![
+                JsonPair(
+                    "key".to_string(),
+                    JsonValue::JsonArray(vec![
+                        JsonValue::Number(1.0),
+                        JsonValue::JsonObject(vec![JsonPair("inner".to_string(), JsonValue::Null)])
+                    ])
+                ),
+                JsonPair("other".to_string(), JsonValue::String("value".to_string())),
+            ])
+        );
+    }
+
+    #[test]
+    pub fn test_parse_str_errors() {
+        assert!(matches!(
+            parse_str(r#"{"key": }"#),
+            Err(EventError::UnexpectedToken("'}'", _))
+        ));
+        assert!(matches!(
+            parse_str("[1, 2"),
+            Err(EventError::UnexpectedEnd(_))
+        ));
+    }
 }
@@ -0,0 +1,509 @@
+use thiserror::Error;
+
+use super::binary::{as_i64, Cursor, UnexpectedEnd};
+use super::dialect::number_value;
+use super::syntactic_analyzer::{JsonPair, JsonValue};
+
+pub use super::binary::MAX_DEPTH;
+
+// Byte offsets point at the start of the offending value
+#[derive(Debug, Clone, Error, PartialEq)]
+pub enum UbjsonError {
+    #[error("Unexpected end of the UBJSON data at byte {0}")]
+    UnexpectedEnd(usize),
+
+    #[error("Invalid marker 0x{0:02x} at byte {1}")]
+    InvalidMarker(u8, usize),
+
+    #[error("Invalid length {0} at byte {1}")]
+    InvalidLength(i64, usize),
+
+    #[error("Invalid UTF-8 in the string at byte {0}")]
+    InvalidUtf8(usize),
+
+    #[error("Invalid high precision number at byte {0}")]
+    InvalidHighPrecision(usize),
+
+    #[error("A typed container needs a count (byte {0})")]
+    MissingCount(usize),
+
+    #[error("Values are nested deeper than {0} levels")]
+    TooDeep(usize),
+
+    #[error("{0} bytes follow the UBJSON value")]
+    TrailingBytes(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UbjsonOptions {
+    // containers start with their count instead of ending with a marker, and the
+    // markers of their values are written once when they are all of the same type
+    pub optimized: bool,
+}
+
+const NULL: u8 = b'Z';
+const NO_OP: u8 = b'N';
+const TRUE: u8 = b'T';
+const FALSE: u8 = b'F';
+const INT8: u8 = b'i';
+const UINT8: u8 = b'U';
+const INT16: u8 = b'I';
+const INT32: u8 = b'l';
+const INT64: u8 = b'L';
+const FLOAT32: u8 = b'd';
+const FLOAT64: u8 = b'D';
+const HIGH_PRECISION: u8 = b'H';
+const CHAR: u8 = b'C';
+const STRING: u8 = b'S';
+const ARRAY_START: u8 = b'[';
+const ARRAY_END: u8 = b']';
+const OBJECT_START: u8 = b'{';
+const OBJECT_END: u8 = b'}';
+const TYPE: u8 = b'$';
+const COUNT: u8 = b'#';
+
+// Encodes a value as UBJSON (draft 12). Integral numbers take the smallest integer
+// type, the others are 32 bit floats and NaN and the infinities are written as null.
+pub fn to_ubjson(value: &JsonValue, options: UbjsonOptions) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode(&mut out, value, options);
+    out
+}
+
+fn encode(out: &mut Vec<u8>, value: &JsonValue, options: UbjsonOptions) {
+    let marker = marker(value);
+    out.push(marker);
+    payload(out, value, marker, options);
+}
+
+// The marker a value is written with on its own
+fn marker(value: &JsonValue) -> u8 {
+    match value {
+        JsonValue::Null => NULL,
+        JsonValue::Boolean(true) => TRUE,
+        JsonValue::Boolean(false) => FALSE,
+        JsonValue::Number(number) if !number.is_finite() => NULL,
+        JsonValue::Number(number) => match as_i64(*number) {
+            Some(integer) => integer_marker(integer, integer),
+            None => FLOAT32,
+        },
+        JsonValue::String(string) if string.len() == 1 && string.is_ascii() => CHAR,
+        JsonValue::String(_) => STRING,
+        JsonValue::JsonArray(_) => ARRAY_START,
+        JsonValue::JsonObject(_) => OBJECT_START,
+    }
+}
+
+// The smallest integer type holding both bounds
+fn integer_marker(min: i64, max: i64) -> u8 {
+    let fits = |low: i64, high: i64| low <= min && max <= high;
+    if fits(i8::MIN as i64, i8::MAX as i64) {
+        INT8
+    } else if fits(0, u8::MAX as i64) {
+        UINT8
+    } else if fits(i16::MIN as i64, i16::MAX as i64) {
+        INT16
+    } else if fits(i32::MIN as i64, i32::MAX as i64) {
+        INT32
+    } else {
+        INT64
+    }
+}
+
+// The type marker of an optimized container, when its values can share one
+fn shared_marker<'v>(mut values: impl Iterator<Item = &'v JsonValue> + Clone) -> Option<u8> {
+    let first = marker(values.clone().next()?);
+    if values.clone().all(|value| marker(value) == first) {
+        return Some(first);
+    }
+
+    // numbers of different types share the widest of them
+    let mut range = (i64::MAX, i64::MIN);
+    let mut integral = true;
+    let all_numbers = values.all(|value| match value {
+        JsonValue::Number(number) if number.is_finite() => {
+            match as_i64(*number) {
+                Some(integer) => range = (range.0.min(integer), range.1.max(integer)),
+                None => integral = false,
+            }
+            true
+        }
+        _ => false,
+    });
+    match (all_numbers, integral) {
+        (true, true) => Some(integer_marker(range.0, range.1)),
+        (true, false) => Some(FLOAT32),
+        _ => None,
+    }
+}
+
+fn payload(out: &mut Vec<u8>, value: &JsonValue, marker: u8, options: UbjsonOptions) {
+    match value {
+        JsonValue::Null | JsonValue::Boolean(_) => {}
+        JsonValue::Number(number) => match marker {
+            FLOAT32 => out.extend_from_slice(&number.to_be_bytes()),
+            NULL => {}
+            marker => integer_payload(out, *number as i64, marker),
+        },
+        JsonValue::String(string) if marker == CHAR => out.push(string.as_bytes()[0]),
+        JsonValue::String(string) => string_payload(out, string),
+        JsonValue::JsonArray(elements) => {
+            let element_marker = header(out, elements.iter(), elements.len(), options);
+            for element in elements {
+                value_in(out, element, element_marker, options);
+            }
+            if !options.optimized {
+                out.push(ARRAY_END);
+            }
+        }
+        JsonValue::JsonObject(members) => {
+            let values = members.iter().map(|JsonPair(_, member)| member);
+            let member_marker = header(out, values, members.len(), options);
+            for JsonPair(key, member) in members {
+                string_payload(out, key);
+                value_in(out, member, member_marker, options);
+            }
+            if !options.optimized {
+                out.push(OBJECT_END);
+            }
+        }
+    }
+}
+
+// Writes the type and the count of an optimized container, returns the shared type
+fn header<'v>(
+    out: &mut Vec<u8>,
+    values: impl Iterator<Item = &'v JsonValue> + Clone,
+    count: usize,
+    options: UbjsonOptions,
+) -> Option<u8> {
+    if !options.optimized {
+        return None;
+    }
+    // a single value gains nothing from a type marker
+    let marker = shared_marker(values).filter(|_| count > 1);
+    if let Some(marker) = marker {
+        out.extend_from_slice(&[TYPE, marker]);
+    }
+    out.push(COUNT);
+    length(out, count);
+    marker
+}
+
+fn value_in(out: &mut Vec<u8>, value: &JsonValue, shared: Option<u8>, options: UbjsonOptions) {
+    match shared {
+        Some(marker) => payload(out, value, marker, options),
+        None => encode(out, value, options),
+    }
+}
+
+fn integer_payload(out: &mut Vec<u8>, integer: i64, marker: u8) {
+    match marker {
+        INT8 => out.push(integer as i8 as u8),
+        UINT8 => out.push(integer as u8),
+        INT16 => out.extend_from_slice(&(integer as i16).to_be_bytes()),
+        INT32 => out.extend_from_slice(&(integer as i32).to_be_bytes()),
+        _ => out.extend_from_slice(&integer.to_be_bytes()),
+    }
+}
+
+fn length(out: &mut Vec<u8>, length: usize) {
+    let marker = integer_marker(length as i64, length as i64);
+    out.push(marker);
+    integer_payload(out, length as i64, marker);
+}
+
+fn string_payload(out: &mut Vec<u8>, string: &str) {
+    length(out, string.len());
+    out.extend_from_slice(string.as_bytes());
+}
+
+// Decodes one UBJSON value, with or without optimized containers
+pub fn from_ubjson(bytes: &[u8]) -> Result<JsonValue, UbjsonError> {
+    let mut decoder = Decoder {
+        cursor: Cursor::new(bytes, 0),
+    };
+    let value = decoder.value(0)?;
+    match decoder.cursor.remaining() {
+        0 => Ok(value),
+        trailing => Err(UbjsonError::TrailingBytes(trailing)),
+    }
+}
+
+impl From<UnexpectedEnd> for UbjsonError {
+    fn from(UnexpectedEnd(offset): UnexpectedEnd) -> Self {
+        UbjsonError::UnexpectedEnd(offset)
+    }
+}
+
+struct Decoder<'a> {
+    cursor: Cursor<'a>,
+}
+
+impl Decoder<'_> {
+    // The next marker, past the no-ops
+    fn marker(&mut self) -> Result<u8, UbjsonError> {
+        loop {
+            let marker = self.cursor.byte()?;
+            if marker != NO_OP {
+                return Ok(marker);
+            }
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<JsonValue, UbjsonError> {
+        let start = self.cursor.offset;
+        let marker = self.marker()?;
+        self.payload(marker, start, depth)
+    }
+
+    fn integer(&mut self, marker: u8, start: usize) -> Result<i64, UbjsonError> {
+        Ok(match marker {
+            INT8 => self.cursor.byte()? as i8 as i64,
+            UINT8 => self.cursor.byte()? as i64,
+            INT16 => i16::from_be_bytes(self.cursor.fixed()?) as i64,
+            INT32 => i32::from_be_bytes(self.cursor.fixed()?) as i64,
+            INT64 => i64::from_be_bytes(self.cursor.fixed()?),
+            _ => return Err(UbjsonError::InvalidMarker(marker, start)),
+        })
+    }
+
+    fn length(&mut self) -> Result<usize, UbjsonError> {
+        let start = self.cursor.offset;
+        let marker = self.marker()?;
+        let length = self.integer(marker, start)?;
+        if length < 0 {
+            return Err(UbjsonError::InvalidLength(length, start));
+        }
+        Ok(length as usize)
+    }
+
+    fn string(&mut self) -> Result<String, UbjsonError> {
+        let start = self.cursor.offset;
+        let length = self.length()?;
+        let bytes = self.cursor.take(length)?;
+        std::str::from_utf8(bytes)
+            .map(str::to_string)
+            .map_err(|_| UbjsonError::InvalidUtf8(start))
+    }
+
+    fn payload(
+        &mut self,
+        marker: u8,
+        start: usize,
+        depth: usize,
+    ) -> Result<JsonValue, UbjsonError> {
+        if depth > MAX_DEPTH {
+            return Err(UbjsonError::TooDeep(MAX_DEPTH));
+        }
+
+        match marker {
+            NULL => Ok(JsonValue::Null),
+            TRUE => Ok(JsonValue::Boolean(true)),
+            FALSE => Ok(JsonValue::Boolean(false)),
+            INT8 | UINT8 | INT16 | INT32 | INT64 => {
+                Ok(JsonValue::Number(self.integer(marker, start)? as f32))
+            }
+            FLOAT32 => Ok(JsonValue::Number(f32::from_be_bytes(self.cursor.fixed()?))),
+            FLOAT64 => Ok(JsonValue::Number(
+                f64::from_be_bytes(self.cursor.fixed()?) as f32
+            )),
+            HIGH_PRECISION => {
+                let text = self.string()?;
+                number_value(&text)
+                    .map(JsonValue::Number)
+                    .ok_or(UbjsonError::InvalidHighPrecision(start))
+            }
+            CHAR => {
+                let byte = self.cursor.byte()?;
+                if !byte.is_ascii() {
+                    return Err(UbjsonError::InvalidUtf8(start));
+                }
+                Ok(JsonValue::String(char::from(byte).to_string()))
+            }
+            STRING => self.string().map(JsonValue::String),
+            ARRAY_START => {
+                let mut elements = Vec::new();
+                match self.container_header(0)? {
+                    Some((shared, count)) => {
+                        for _ in 0..count {
+                            elements.push(self.typed_value(shared, depth + 1)?);
+                        }
+                    }
+                    None => loop {
+                        let start = self.cursor.offset;
+                        match self.marker()? {
+                            ARRAY_END => break,
+                            marker => elements.push(self.payload(marker, start, depth + 1)?),
+                        }
+                    },
+                }
+                Ok(JsonValue::JsonArray(elements))
+            }
+            OBJECT_START => {
+                let mut members = Vec::new();
+                // a key takes a length marker and a length at least
+                match self.container_header(2)? {
+                    Some((shared, count)) => {
+                        for _ in 0..count {
+                            let key = self.string()?;
+                            members.push(JsonPair(key, self.typed_value(shared, depth + 1)?));
+                        }
+                    }
+                    None => loop {
+                        while self.cursor.peek()? == NO_OP {
+                            self.cursor.offset += 1;
+                        }
+                        if self.cursor.peek()? == OBJECT_END {
+                            self.cursor.offset += 1;
+                            break;
+                        }
+                        let key = self.string()?;
+                        members.push(JsonPair(key, self.value(depth + 1)?));
+                    },
+                }
+                Ok(JsonValue::JsonObject(members))
+            }
+            _ => Err(UbjsonError::InvalidMarker(marker, start)),
+        }
+    }
+
+    // The shared type and the count of an optimized container. The count is refused when
+    // the data cannot hold that many entries of `key_size` bytes and a value each.
+    fn container_header(
+        &mut self,
+        key_size: usize,
+    ) -> Result<Option<(Option<u8>, usize)>, UbjsonError> {
+        let start = self.cursor.offset;
+        let shared = match self.cursor.peek()? {
+            TYPE => {
+                self.cursor.offset += 1;
+                let marker = self.cursor.byte()?;
+                if self.cursor.peek()? != COUNT {
+                    return Err(UbjsonError::MissingCount(start));
+                }
+                Some(marker)
+            }
+            COUNT => None,
+            _ => return Ok(None),
+        };
+        self.cursor.offset += 1;
+
+        let count_start = self.cursor.offset;
+        let count = self.length()?;
+        let value_size = match shared {
+            Some(NULL | TRUE | FALSE) => 0,
+            // a marker or a payload of one byte at least
+            _ => 1,
+        };
+        if !self.cursor.holds(count as u64, key_size + value_size) {
+            return Err(UbjsonError::InvalidLength(count as i64, count_start));
+        }
+        Ok(Some((shared, count)))
+    }
+
+    fn typed_value(&mut self, shared: Option<u8>, depth: usize) -> Result<JsonValue, UbjsonError> {
+        match shared {
+            Some(marker) => {
+                let start = self.cursor.offset;
+                self.payload(marker, start, depth)
+            }
+            None => self.value(depth),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod ubjson_tests {
+    use super::*;
+    use crate::json::syntactic_analyzer::parse_str;
+    use pretty_assertions::assert_eq;
+
+    fn json(text: &str) -> JsonValue {
+        parse_str(text).unwrap()
+    }
+
+    #[test]
+    pub fn test_ubjson() {
+        let plain = UbjsonOptions::default();
+        let examples: [(&str, &[u8]); 12] = [
+            ("null", b"Z"),
+            ("true", b"T"),
+            ("-1", b"i\xff"),
+            ("200", b"U\xc8"),
+            ("-200", b"I\xff\x38"),
+            ("70000", b"l\x00\x01\x11\x70"),
+            ("4294967296", b"L\x00\x00\x00\x01\x00\x00\x00\x00"),
+            ("1.5", b"d\x3f\xc0\x00\x00"),
+            (r#""a""#, b"Ca"),
+            (r#""json""#, b"Si\x04json"),
+            ("[1, [], true]", b"[i\x01[]T]"),
+            (r#"{"a": {}, "bc": null}"#, b"{i\x01a{}i\x02bcZ}"),
+        ];
+
+        for (text, ubjson) in examples {
+            assert_eq!(to_ubjson(&json(text), plain), ubjson, "{text}");
+            assert_eq!(from_ubjson(ubjson).unwrap(), json(text));
+        }
+        assert_eq!(to_ubjson(&JsonValue::Number(f32::NAN), plain), b"Z");
+    }
+
+    #[test]
+    pub fn test_optimized_containers() {
+        let optimized = UbjsonOptions { optimized: true };
+        let examples: [(&str, &[u8]); 6] = [
+            ("[]", b"[#i\x00"),
+            ("[1, 2, 300]", b"[$I#i\x03\x00\x01\x00\x02\x01\x2c"),
+            ("[0.5, 1]", b"[$d#i\x02\x3f\x00\x00\x00\x3f\x80\x00\x00"),
+            (r#"["ab", 1]"#, b"[#i\x02Si\x02abi\x01"),
+            ("[null, null]", b"[$Z#i\x02"),
+            (
+                r#"{"x": [true, true], "y": [false]}"#,
+                b"{$[#i\x02i\x01x$T#i\x02i\x01y#i\x01F",
+            ),
+        ];
+
+        for (text, ubjson) in examples {
+            assert_eq!(to_ubjson(&json(text), optimized), ubjson, "{text}");
+            assert_eq!(from_ubjson(ubjson).unwrap(), json(text));
+        }
+
+        // optimized containers nested in plain ones, no-ops and the other number types
+        assert_eq!(
+            from_ubjson(b"[N{#U\x01i\x01kD\x3f\xf8\x00\x00\x00\x00\x00\x00N[$C#i\x02xyHi\x031.5]")
+                .unwrap(),
+            json(r#"[{"k": 1.5}, ["x", "y"], 1.5]"#)
+        );
+    }
+
+    #[test]
+    pub fn test_ubjson_errors() {
+        let errors: [(&[u8], UbjsonError); 12] = [
+            // counts that the data cannot hold
+            (
+                b"[$Z#l\x01\x00\x00\x00",
+                UbjsonError::InvalidLength(16777216, 4),
+            ),
+            (
+                b"[$T#L\x7f\xff\xff\xff\xff\xff\xff\xff",
+                UbjsonError::InvalidLength(i64::MAX, 4),
+            ),
+            (b"[#i\x03ZZ", UbjsonError::InvalidLength(3, 2)),
+            (b"{$Z#i\x02i\x01a", UbjsonError::InvalidLength(2, 4)),
+            (b"Si\x05abc", UbjsonError::UnexpectedEnd(6)),
+            (b"[i\x01", UbjsonError::UnexpectedEnd(3)),
+            (b"X", UbjsonError::InvalidMarker(b'X', 0)),
+            (b"Sd\x00\x00\x00\x00", UbjsonError::InvalidMarker(b'd', 1)),
+            (b"Si\xff", UbjsonError::InvalidLength(-1, 1)),
+            (b"[$i\x01\x02", UbjsonError::MissingCount(1)),
+            (b"Hi\x02zz", UbjsonError::InvalidHighPrecision(0)),
+            (b"TT", UbjsonError::TrailingBytes(1)),
+        ];
+        for (ubjson, error) in errors {
+            assert_eq!(from_ubjson(ubjson), Err(error), "{ubjson:?}");
+        }
+
+        let nested = vec![b'['; MAX_DEPTH + 2];
+        assert_eq!(from_ubjson(&nested), Err(UbjsonError::TooDeep(MAX_DEPTH)));
+    }
+}
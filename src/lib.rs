@@ -1,17 +1,44 @@
 pub mod json;
 
+use std::fs::File;
 use std::path::PathBuf;
 
-use json::lexer;
+use json::dialect::Dialect;
+use json::events::EventError;
+use json::mmap::Document;
 use json::syntactic_analyzer;
 
 use json::syntactic_analyzer::JsonValue;
 use json::syntactic_analyzer::ParseError;
 
 pub fn parse_json_file(path: &PathBuf) -> Result<JsonValue, ParseError> {
-    // todo: Fix the unwrap
-    let content = std::fs::read_to_string(path).unwrap();
-    let token_stream = lexer::lex(&content).unwrap();
+    let file = File::open(path).map_err(|error| ParseError::Io(error.to_string()))?;
+    syntactic_analyzer::parse_reader(file).map_err(|error| match error {
+        EventError::Io(message) => ParseError::Io(message),
+        error => ParseError::Syntax(error),
+    })
+}
+
+// Reads a file written in a dialect of json, such as Dialect::HJSON for configuration
+// files. Plain json is streamed as parse_json_file does.
+pub fn parse_json_file_with_dialect(
+    path: &PathBuf,
+    dialect: Dialect,
+) -> Result<JsonValue, ParseError> {
+    if dialect == Dialect::JSON {
+        return parse_json_file(path);
+    }
+    let input = std::fs::read_to_string(path).map_err(|error| ParseError::Io(error.to_string()))?;
+    syntactic_analyzer::parse_str_with_dialect(&input, dialect).map_err(ParseError::Syntax)
+}
 
-    syntactic_analyzer::parse(token_stream)
+/// Maps the file in memory instead of reading it, the values of the document borrow
+/// their strings from the mapping.
+///
+/// # Safety
+///
+/// As for `Document::open`, the file must not change while the Document is alive.
+pub unsafe fn parse_json_file_mmap(path: &PathBuf) -> Result<Document, ParseError> {
+    // SAFETY: forwarded to the caller
+    unsafe { Document::open(path) }
 }
@@ -3,9 +3,23 @@ pub mod integration_tests {
 
     use std::path::PathBuf;
 
+    use json_parser_rs::{parse_json_file, parse_json_file_mmap, parse_json_file_with_dialect};
+
+    #[allow(unused_imports)]
     use json_parser_rs::json::{
+        bson::{from_bson, to_bson, to_extended_json, ExtendedJsonMode},
+        cbor::{self, CborOptions},
+        cst::Cst,
+        dialect::Dialect,
+        events::EventError,
+        formatter::{format, FormatOptions},
+        hjson,
         lexer::{self, lex, LexerError},
+        msgpack,
+        ndjson::NdjsonReader,
+        smile::{self, SmileOptions},
         syntactic_analyzer::{self, parse, JsonPair, JsonValue, ParseError},
+        ubjson::{self, UbjsonOptions},
     };
 
     #[test]
@@ -108,9 +122,232 @@ pub mod integration_tests {
         assert!(file.is_file());
         let content = std::fs::read_to_string(file).unwrap();
         let token_stream = lex(&content).unwrap();
-        let value = syntactic_analyzer::parse(token_stream);
+        let value = syntactic_analyzer::parse(token_stream).unwrap();
+
+        assert_eq!(
+            value,
+            JsonValue::JsonObject(vec![
+                JsonPair(
+                    String::from("key"),
+                    JsonValue::String(String::from("value"))
+                ),
+                JsonPair(String::from("key-n"), JsonValue::Number(101.0)),
+                JsonPair(String::from("key-o"), JsonValue::JsonObject(vec![])),
+                JsonPair(String::from("key-l"), JsonValue::JsonArray(vec![])),
+            ])
+        );
+    }
+
+    #[test]
+    pub fn integration_test_parse_json_file() {
+        let file = PathBuf::from("tests/step4/valid2.json");
+        let value = parse_json_file(&file).unwrap();
+
+        assert_eq!(
+            value,
+            JsonValue::JsonObject(vec![
+                JsonPair(
+                    String::from("key"),
+                    JsonValue::String(String::from("value"))
+                ),
+                JsonPair(String::from("key-n"), JsonValue::Number(101.0)),
+                JsonPair(
+                    String::from("key-o"),
+                    JsonValue::JsonObject(vec![JsonPair(
+                        String::from("inner key"),
+                        JsonValue::String(String::from("inner value"))
+                    )])
+                ),
+                JsonPair(
+                    String::from("key-l"),
+                    JsonValue::JsonArray(vec![JsonValue::String(String::from("list value"))])
+                ),
+            ])
+        );
+
+        let file = PathBuf::from("tests/step4/invalid.json");
+        let value = parse_json_file(&file);
+        assert!(matches!(
+            value,
+            Err(ParseError::Syntax(EventError::UnexpectedCharacter('\'', _)))
+        ));
+
+        let file = PathBuf::from("tests/step4/missing.json");
+        assert!(matches!(parse_json_file(&file), Err(ParseError::Io(_))));
+    }
+
+    #[test]
+    pub fn integration_test_parse_json_file_mmap() {
+        let file = PathBuf::from("tests/step4/valid2.json");
+        // SAFETY: the fixtures are not changed while they are mapped
+        let document = unsafe { parse_json_file_mmap(&file) }.unwrap();
+        let root = document.root();
+
+        assert_eq!(root.to_json_value(), parse_json_file(&file).unwrap());
+        assert_eq!(
+            root.get("key-o")
+                .and_then(|inner| inner.get("inner key"))
+                .and_then(|value| value.as_str()),
+            Some("inner value")
+        );
+
+        let file = PathBuf::from("tests/step4/invalid.json");
+        assert!(matches!(
+            unsafe { parse_json_file_mmap(&file) },
+            Err(ParseError::Syntax(EventError::UnexpectedCharacter('\'', _)))
+        ));
+    }
+
+    #[test]
+    pub fn integration_test_cst_edits() {
+        for file in [
+            "tests/step1/valid.json",
+            "tests/step2/valid.json",
+            "tests/step2/valid2.json",
+            "tests/step3/valid.json",
+            "tests/step4/valid.json",
+            "tests/step4/valid2.json",
+        ] {
+            let content = std::fs::read_to_string(file).unwrap();
+            let cst = Cst::parse(&content).unwrap();
+            assert_eq!(cst.to_string(), content, "{file}");
+            assert_eq!(
+                cst.value().unwrap(),
+                parse_json_file(&PathBuf::from(file)).unwrap()
+            );
+        }
+
+        let content = std::fs::read_to_string("tests/step4/valid2.json").unwrap();
+        let mut cst = Cst::parse(&content).unwrap();
+        cst.set("/key-n", &JsonValue::Number(102.0)).unwrap();
+        cst.insert("/key-l/-", &JsonValue::Null).unwrap();
+        cst.remove("/key-o/inner key").unwrap();
+
+        assert_eq!(
+            cst.to_string(),
+            content
+                .replace("101", "102")
+                .replace("\"list value\"]", "\"list value\", null]")
+                .replace("\n    \"inner key\": \"inner value\"", "")
+        );
+    }
+
+    #[test]
+    pub fn integration_test_format_fixtures() {
+        let defaults = FormatOptions::default();
+        let fixtures = [
+            ("package", "json", defaults),
+            (
+                "settings",
+                "jsonc",
+                FormatOptions {
+                    dialect: Dialect::JSONC,
+                    ..defaults
+                },
+            ),
+            (
+                "sorted",
+                "json",
+                FormatOptions {
+                    indent_width: 4,
+                    max_line_width: 40,
+                    sort_keys: true,
+                    ..defaults
+                },
+            ),
+        ];
+
+        for (name, extension, options) in fixtures {
+            let read = |kind| {
+                std::fs::read_to_string(format!("tests/format/{name}.{kind}.{extension}")).unwrap()
+            };
+            let (input, expected) = (read("input"), read("expected"));
+
+            assert_eq!(format(&input, options).unwrap(), expected, "{name}");
+            assert_eq!(format(&expected, options).unwrap(), expected, "{name}");
+        }
+    }
+
+    #[test]
+    pub fn integration_test_parse_hjson_file() {
+        let file = PathBuf::from("tests/hjson/config.hjson");
+        let value = parse_json_file_with_dialect(&file, Dialect::HJSON).unwrap();
+
+        let expected = parse_json_file(&PathBuf::from("tests/hjson/config.json")).unwrap();
+        assert_eq!(value, expected);
+        assert_eq!(
+            hjson::parse_str(&hjson::to_hjson_string(&value)).unwrap(),
+            value
+        );
+
+        assert!(matches!(
+            parse_json_file(&file),
+            Err(ParseError::Syntax(EventError::UnexpectedCharacter('#', _)))
+        ));
+        let file = PathBuf::from("tests/hjson/missing.hjson");
+        assert!(matches!(
+            parse_json_file_with_dialect(&file, Dialect::HJSON),
+            Err(ParseError::Io(_))
+        ));
+    }
+
+    #[test]
+    pub fn integration_test_extended_json_dump() {
+        let read = |mode| {
+            let file = std::fs::File::open(format!("tests/bson/orders.{mode}.json")).unwrap();
+            let mut reader = NdjsonReader::new(std::io::BufReader::new(file));
+            std::iter::from_fn(|| reader.next_value())
+                .map(Result::unwrap)
+                .collect::<Vec<_>>()
+        };
+        let (canonical, relaxed) = (read("canonical"), read("relaxed"));
+        assert_eq!(canonical.len(), 2);
+
+        for (canonical, relaxed) in canonical.iter().zip(&relaxed) {
+            let bytes = to_bson(canonical).unwrap();
+            assert_eq!(
+                from_bson(&bytes, ExtendedJsonMode::Canonical).unwrap(),
+                *canonical
+            );
+            assert_eq!(
+                from_bson(&bytes, ExtendedJsonMode::Relaxed).unwrap(),
+                *relaxed
+            );
+            assert_eq!(
+                to_extended_json(relaxed, ExtendedJsonMode::Relaxed).unwrap(),
+                *relaxed
+            );
+        }
+    }
+
+    #[test]
+    pub fn integration_test_binary_formats() {
+        for file in [
+            "tests/step2/valid2.json",
+            "tests/step3/valid.json",
+            "tests/step4/valid.json",
+            "tests/step4/valid2.json",
+            "tests/hjson/config.json",
+        ] {
+            let value = parse_json_file(&PathBuf::from(file)).unwrap();
 
-        // Inner object or arrays are not yet supported
-        assert_eq!(value.err(), Some(ParseError::InvalidValue));
+            let bytes = cbor::to_cbor(&value, CborOptions::default());
+            assert_eq!(cbor::from_cbor(&bytes).unwrap(), value, "{file}");
+            // canonical mode sorts the keys, so only its output is stable
+            let canonical = CborOptions { canonical: true };
+            let bytes = cbor::to_cbor(&value, canonical);
+            let sorted = cbor::from_cbor(&bytes).unwrap();
+            assert_eq!(cbor::to_cbor(&sorted, canonical), bytes, "{file}");
+            let bytes = msgpack::to_msgpack(&value).unwrap();
+            assert_eq!(msgpack::from_msgpack(&bytes).unwrap(), value, "{file}");
+            for optimized in [false, true] {
+                let bytes = ubjson::to_ubjson(&value, UbjsonOptions { optimized });
+                assert_eq!(ubjson::from_ubjson(&bytes).unwrap(), value, "{file}");
+            }
+            for shared_names in [false, true] {
+                let bytes = smile::to_smile(&value, SmileOptions { shared_names });
+                assert_eq!(smile::from_smile(&bytes).unwrap(), value, "{file}");
+            }
+        }
     }
 }